@@ -1,10 +1,9 @@
 // 处理命令行参数
 pub mod args_handle {
-    use std::env::Args;
-
     #[derive(Debug)]
     pub struct ArgsConfig {
         pub job_config_path: String,
+        pub rollback: Option<String>, // rollback <run-id>：回滚到同步前备份
     }
 
     pub trait PrintMe: std::fmt::Debug {
@@ -18,13 +17,24 @@ pub mod args_handle {
     }
 
     impl ArgsConfig {
-        pub fn build(mut args: Args) -> Result<Self, &'static str> {
+        pub fn build(mut args: impl Iterator<Item = String>) -> Result<Self, &'static str> {
             args.next();
             let job_config_path = match args.next() {
                 Some(path) => path,
                 None => return Err("No job config path provided"),
             };
-            let job_config = ArgsConfig { job_config_path };
+            let rollback = match args.next().as_deref() {
+                None => None,
+                Some("rollback") => match args.next() {
+                    Some(run_id) => Some(run_id),
+                    None => return Err("rollback requires a run-id"),
+                },
+                Some(_) => return Err("Unknown command, expected: rollback <run-id>"),
+            };
+            let job_config = ArgsConfig {
+                job_config_path,
+                rollback,
+            };
             Ok(job_config)
         }
     }
@@ -48,3 +58,26 @@ mod test_job_config {
         println!("{:?}", job);
     }
 }
+
+#[cfg(test)]
+mod test_args {
+    use crate::args::args_handle::ArgsConfig;
+
+    fn build(args: &[&str]) -> Result<ArgsConfig, &'static str> {
+        ArgsConfig::build(args.iter().map(|a| a.to_string()))
+    }
+
+    #[test]
+    fn test_build() {
+        let config = build(&["datasync", "job/sync.toml"]).unwrap();
+        assert_eq!(config.job_config_path, "job/sync.toml");
+        assert_eq!(config.rollback, None);
+        let config = build(&["datasync", "job/sync.toml", "rollback", "sync_pre_sync_1"]).unwrap();
+        assert_eq!(config.rollback.as_deref(), Some("sync_pre_sync_1"));
+
+        assert!(build(&["datasync"]).is_err());
+        // rollback 缺少 run-id，或未知命令
+        assert!(build(&["datasync", "job/sync.toml", "rollback"]).is_err());
+        assert!(build(&["datasync", "job/sync.toml", "restore"]).is_err());
+    }
+}
@@ -1 +1,2 @@
 pub mod mysql_db;
+pub mod postgres_db;
@@ -32,5 +32,18 @@ pub async fn init_mysql_db_pool(dns: &str, pool_name: &str) -> Result<(), String
         .await
         .insert(pool_name.to_string(), pool_arc);
 
-    return Ok(());
+    Ok(())
+}
+
+// 初始化（已存在时复用）并返回数据库连接池
+pub async fn get_mysql_db_pool(dns: &str, pool_name: &str) -> Result<Arc<Pool<MySql>>, String> {
+    init_mysql_db_pool(dns, pool_name).await?;
+    let pool_map = MYSQL_DB_POOLS.lock().await;
+    match pool_map.get(pool_name) {
+        Some(pool) => {
+            println!("数据库连接池创建成功: {}", pool_name);
+            Ok(pool.clone())
+        }
+        None => Err(format!("数据库连接池获取失败: {}", pool_name)),
+    }
 }
@@ -0,0 +1,50 @@
+use std::collections::HashMap;
+use std::sync::Arc;
+use std::sync::LazyLock;
+
+use sqlx::Pool;
+use sqlx::Postgres;
+use sqlx::postgres::PgPoolOptions;
+use tokio::sync::Mutex;
+
+// 使用一个map来存储数据库连接池，与 MYSQL_DB_POOLS 相同
+pub static POSTGRES_DB_POOLS: LazyLock<Mutex<HashMap<String, Arc<Pool<Postgres>>>>> =
+    LazyLock::new(|| Mutex::new(HashMap::new()));
+
+// 初始化数据库连接池
+pub async fn init_postgres_db_pool(dns: &str, pool_name: &str) -> Result<(), String> {
+    let pool = PgPoolOptions::new()
+        .max_connections(5)
+        .connect_lazy(dns)
+        .map_err(|e| format!("Failed to create pool: {}", e))?;
+
+    // 检查db_name是否已经存在
+    if POSTGRES_DB_POOLS.lock().await.contains_key(pool_name) {
+        println!("Database pool for {} already exists", pool_name);
+        return Ok(());
+    }
+
+    let pool_arc = Arc::new(pool);
+    POSTGRES_DB_POOLS
+        .lock()
+        .await
+        .insert(pool_name.to_string(), pool_arc);
+
+    Ok(())
+}
+
+// 初始化（已存在时复用）并返回数据库连接池
+pub async fn get_postgres_db_pool(
+    dns: &str,
+    pool_name: &str,
+) -> Result<Arc<Pool<Postgres>>, String> {
+    init_postgres_db_pool(dns, pool_name).await?;
+    let pool_map = POSTGRES_DB_POOLS.lock().await;
+    match pool_map.get(pool_name) {
+        Some(pool) => {
+            println!("数据库连接池创建成功: {}", pool_name);
+            Ok(pool.clone())
+        }
+        None => Err(format!("数据库连接池获取失败: {}", pool_name)),
+    }
+}
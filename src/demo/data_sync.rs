@@ -109,5 +109,5 @@ pub async fn mysqldump_database_backup(db_name: &str) -> String {
         println!("Database {} dumped to {}", db_name, output_file_path);
     }
 
-    return output_file_path;
+    output_file_path
 }
@@ -0,0 +1,590 @@
+// 备份任务 / 还原任务 / 同步前备份和回滚
+// 备份任务只导出数据并写入备份集清单，还原任务从备份目录中选择备份集还原到目标库
+
+use std::{
+    error::Error,
+    io::{self, Read, Write},
+    sync::Arc,
+};
+
+use chrono::Local;
+use flate2::{Compression, read::GzDecoder, write::GzEncoder};
+use tokio::sync::Semaphore;
+
+use crate::{
+    handle::{
+        catalog::{BackupCatalog, BackupEntry, BackupManifest, TIME_FORMAT},
+        compat::ServerVersion,
+        conflict::{ConflictPolicy, Resolution, confirm_destructive, resolve_conflict},
+        help::{
+            mysqldump_to_writer, query_all_databases, query_mysql_version, query_tables,
+            restore_database,
+        },
+        mysql_schema::quote_ident,
+        mysql_sync::SYSTEM_DATABASES,
+        sync::SyncOptions,
+    },
+    model::job::{Backup, Source, Target},
+    storage::backend::{BackupStorage, open_storage},
+    util::{
+        checksum::{HashingReader, HashingWriter, MYSQLDUMP_COMPLETED, check_dump_completed},
+        crypto::{AES_256_GCM, DecryptReader, EncryptWriter, key_id, load_key},
+    },
+};
+
+// 备份文件的压缩/加密方式
+#[derive(Clone)]
+pub struct BackupCodec {
+    pub compress: bool,
+    pub key: Option<[u8; 32]>,
+    pub source_version: Option<ServerVersion>, // 备份的源库版本，用于选择 mysqldump 参数
+}
+
+impl BackupCodec {
+    pub fn from_config(backup: &Backup) -> io::Result<Self> {
+        let key = match &backup.encryption {
+            Some(encryption) => {
+                let encryption_type = encryption.encryption_type.as_deref().unwrap_or(AES_256_GCM);
+                if encryption_type != AES_256_GCM {
+                    return Err(io::Error::other(format!(
+                        "暂不支持的加密方式: {}",
+                        encryption_type
+                    )));
+                }
+                Some(load_key(&encryption.key_file)?)
+            }
+            None => None,
+        };
+        Ok(BackupCodec {
+            compress: backup.compress.unwrap_or(false),
+            key,
+            source_version: None,
+        })
+    }
+
+    // 备份文件扩展名
+    pub fn extension(&self) -> String {
+        let mut extension = String::from("sql");
+        if self.compress {
+            extension.push_str(".gz");
+        }
+        if self.key.is_some() {
+            extension.push_str(".enc");
+        }
+        extension
+    }
+
+    // 记录到备份清单
+    pub fn describe(&self, manifest: &mut BackupManifest) {
+        manifest.compression = self.compress.then(|| "gzip".to_string());
+        manifest.encryption = self.key.map(|_| AES_256_GCM.to_string());
+        manifest.key_id = self.key.as_ref().map(key_id);
+    }
+
+    // 导出数据库：mysqldump -> 压缩 -> 加密 -> writer
+    pub fn dump<W: Write>(&self, source: &Source, db_name: &str, writer: W) -> io::Result<W> {
+        match &self.key {
+            Some(key) => {
+                let writer = EncryptWriter::new(writer, key)?;
+                self.dump_compressed(source, db_name, writer)?.finish()
+            }
+            None => self.dump_compressed(source, db_name, writer),
+        }
+    }
+
+    fn dump_compressed<W: Write>(
+        &self,
+        source: &Source,
+        db_name: &str,
+        writer: W,
+    ) -> io::Result<W> {
+        if self.compress {
+            let mut gz = GzEncoder::new(writer, Compression::default());
+            mysqldump_to_writer(source, db_name, &[], &self.dump_options(), &mut gz)
+                .map_err(io::Error::other)?;
+            gz.finish()
+        } else {
+            let mut writer = writer;
+            mysqldump_to_writer(source, db_name, &[], &self.dump_options(), &mut writer)
+                .map_err(io::Error::other)?;
+            Ok(writer)
+        }
+    }
+
+    fn dump_options(&self) -> SyncOptions {
+        SyncOptions {
+            source_version: self.source_version,
+            ..SyncOptions::default()
+        }
+    }
+
+    // 还原时按备份清单解密、解压
+    pub fn decode(
+        &self,
+        manifest: &BackupManifest,
+        reader: Box<dyn Read + Send>,
+    ) -> io::Result<Box<dyn Read + Send>> {
+        let reader: Box<dyn Read + Send> = match manifest.encryption.as_deref() {
+            None => reader,
+            Some(AES_256_GCM) => {
+                let Some(key) = &self.key else {
+                    return Err(io::Error::other("备份集已加密，请配置 [backup.encryption]"));
+                };
+                if manifest.key_id.as_deref() != Some(key_id(key).as_str()) {
+                    return Err(io::Error::other("配置的密钥与备份集加密时使用的密钥不一致"));
+                }
+                Box::new(DecryptReader::new(reader, key)?)
+            }
+            Some(other) => {
+                return Err(io::Error::other(format!("暂不支持的加密方式: {}", other)));
+            }
+        };
+        match manifest.compression.as_deref() {
+            None => Ok(reader),
+            Some("gzip") => Ok(Box::new(GzDecoder::new(reader))),
+            Some(other) => Err(io::Error::other(format!("暂不支持的压缩方式: {}", other))),
+        }
+    }
+}
+
+// 备份所有（或指定）数据库，返回备份集清单
+pub async fn backup_job(
+    source_pool: Arc<sqlx::Pool<sqlx::MySql>>,
+    job_name: &str,
+    source: &Source,
+    backup: &Backup,
+) -> Result<BackupManifest, Box<dyn Error + Send + Sync>> {
+    let storage = open_storage(backup.storage.as_ref())?;
+    let catalog = BackupCatalog::new(storage.clone(), backup.dir());
+    let server_version = query_mysql_version(&source_pool).await?;
+    let mut codec = BackupCodec::from_config(backup)?;
+    codec.source_version = ServerVersion::parse(&server_version);
+    let mut manifest = BackupManifest::new(job_name, "mysql", &server_version);
+    codec.describe(&mut manifest);
+    println!("备份集：{}，源数据库版本：{}", manifest.id, server_version);
+
+    let databases = match &backup.databases {
+        Some(dbs) => dbs.clone(),
+        None => user_databases(query_all_databases(&source_pool).await?),
+    };
+
+    // 设置最大并发数为 5
+    let semaphore = Arc::new(Semaphore::new(5));
+    let mut tasks = Vec::new();
+    for db_name in databases {
+        let source_cloned = source.clone();
+        let storage = storage.clone();
+        let codec = codec.clone();
+        let file = format!("{}.{}", db_name, codec.extension());
+        let output_path = catalog.entry_path(&manifest.id, &file);
+        let permit = semaphore.clone().acquire_owned().await.unwrap();
+        let task = tokio::task::spawn_blocking(move || {
+            let started_at = Local::now().format(TIME_FORMAT).to_string();
+            let result = dump_to_storage(&*storage, &codec, &source_cloned, &db_name, &output_path)
+                .map(|(size, sha256)| BackupEntry {
+                    db_name: db_name.clone(),
+                    file,
+                    size,
+                    sha256,
+                    started_at,
+                    finished_at: Local::now().format(TIME_FORMAT).to_string(),
+                });
+            drop(permit);
+            (db_name, result)
+        });
+        tasks.push(task);
+    }
+
+    for task in tasks {
+        match task.await {
+            Ok((_, Ok(entry))) => manifest.entries.push(entry),
+            Ok((db_name, Err(e))) => {
+                eprintln!("数据库 {} 备份失败: {}", db_name, e);
+                manifest.failed.push(db_name);
+            }
+            Err(e) => eprintln!("Task failed: {}", e),
+        }
+    }
+
+    manifest.finished_at = Local::now().format(TIME_FORMAT).to_string();
+    catalog.save(&manifest)?;
+    println!(
+        "[ok] 备份集 {} 已写入 {}，成功 {} 个，失败 {} 个",
+        manifest.id,
+        storage.location(&catalog.set_dir(&manifest.id)),
+        manifest.entries.len(),
+        manifest.failed.len()
+    );
+    Ok(manifest)
+}
+
+// 未配置 backup.databases 时备份的库：不包括系统库，还原时不会覆盖目标库的用户和授权表
+fn user_databases(databases: Vec<String>) -> Vec<String> {
+    databases
+        .into_iter()
+        .filter(|db| !SYSTEM_DATABASES.contains(&db.as_str()))
+        .collect()
+}
+
+// 从备份目录中选择备份集并还原到目标库
+pub async fn restore_job(
+    target_pool: Arc<sqlx::Pool<sqlx::MySql>>,
+    target: &Target,
+    backup: &Backup,
+) -> Result<BackupManifest, Box<dyn Error + Send + Sync>> {
+    restore_set(target_pool, target, backup, false).await
+}
+
+// 同步前备份目标库（[target] pre_sync_backup），databases 为将被同步覆盖的目标库
+// 备份集id（job_name_pre_sync_时间）即回滚使用的 run-id
+pub async fn pre_sync_backup(
+    target_pool: Arc<sqlx::Pool<sqlx::MySql>>,
+    job_name: &str,
+    target: &Target,
+    backup: &Backup,
+    databases: Vec<String>,
+) -> Result<Option<BackupManifest>, Box<dyn Error + Send + Sync>> {
+    let existing = query_all_databases(&target_pool).await?;
+    let Some(backup) = pre_sync_backup_config(backup, databases, &existing) else {
+        println!("目标库都不存在，不需要同步前备份");
+        return Ok(None);
+    };
+    let source = target_as_source(target);
+    let manifest = backup_job(
+        target_pool,
+        &format!("{}_pre_sync", job_name),
+        &source,
+        &backup,
+    )
+    .await?;
+    if !manifest.failed.is_empty() {
+        return Err(format!("同步前备份失败: {:?}，不执行同步", manifest.failed).into());
+    }
+    println!(
+        "[ok] 同步前备份完成，run-id：{}，回滚：datasync <job.toml> rollback {}",
+        manifest.id, manifest.id
+    );
+    Ok(Some(manifest))
+}
+
+// 同步前备份的配置：只备份目标库中已存在的库，都不存在时不需要备份
+fn pre_sync_backup_config(
+    backup: &Backup,
+    databases: Vec<String>,
+    existing: &[String],
+) -> Option<Backup> {
+    let databases = databases
+        .into_iter()
+        .filter(|db| existing.contains(db))
+        .collect::<Vec<_>>();
+    if databases.is_empty() {
+        return None;
+    }
+    Some(Backup {
+        databases: Some(databases),
+        ..backup.clone()
+    })
+}
+
+// 目标库的连接信息作为导出的源
+fn target_as_source(target: &Target) -> Source {
+    Source {
+        database_type: target.database_type.clone(),
+        host: target.host.clone(),
+        port: target.port.clone(),
+        user: target.user.clone(),
+        password: target.password.clone(),
+        db_name: None,
+        table_name: None,
+        filters: Vec::new(),
+    }
+}
+
+// 回滚：删除备份集中的目标库，还原到同步前的快照
+pub async fn rollback_job(
+    target_pool: Arc<sqlx::Pool<sqlx::MySql>>,
+    target: &Target,
+    backup: &Backup,
+) -> Result<BackupManifest, Box<dyn Error + Send + Sync>> {
+    restore_set(target_pool, target, backup, true).await
+}
+
+// 还原备份集
+// 回滚（recreate）时先删除目标库（需要确认）；还原时按 conflict_policy 处理已存在的目标库
+async fn restore_set(
+    target_pool: Arc<sqlx::Pool<sqlx::MySql>>,
+    target: &Target,
+    backup: &Backup,
+    recreate: bool,
+) -> Result<BackupManifest, Box<dyn Error + Send + Sync>> {
+    if !recreate
+        && ConflictPolicy::parse(target.conflict_policy.as_deref())? == ConflictPolicy::Merge
+    {
+        return Err("还原任务不支持 conflict_policy = merge".into());
+    }
+    let storage = open_storage(backup.storage.as_ref())?;
+    let catalog = BackupCatalog::new(storage.clone(), backup.dir());
+    let codec = BackupCodec::from_config(backup)?;
+    let manifest = catalog.select(backup.set_id.as_deref(), backup.time.as_deref())?;
+    println!(
+        "选择备份集：{}（{}，源数据库版本：{}）",
+        manifest.id, manifest.started_at, manifest.server_version
+    );
+
+    let entries = manifest
+        .entries
+        .iter()
+        .filter(|entry| {
+            backup
+                .databases
+                .as_ref()
+                .is_none_or(|dbs| dbs.contains(&entry.db_name))
+        })
+        .collect::<Vec<_>>();
+    // 回滚还原到备份时的库
+    let db_name = if recreate {
+        None
+    } else {
+        target.db_name.as_deref()
+    };
+    let entries = restore_targets(entries, db_name)?;
+
+    let mut failed = Vec::new();
+    let mut restore_failed = Vec::new();
+    for (entry, target_db_name) in entries {
+        let path = catalog.entry_path(&manifest.id, &entry.file);
+        // 还原前校验，校验失败的数据库不还原
+        if let Err(e) = verify_entry(&*storage, &codec, &manifest, entry, &path) {
+            eprintln!(
+                "数据库 {} 的备份文件校验失败，跳过还原: {}",
+                entry.db_name, e
+            );
+            failed.push(entry.db_name.clone());
+            continue;
+        }
+        println!("[ok] 备份文件校验通过: {}", storage.location(&path));
+        let resolution = if recreate {
+            confirm_destructive(
+                target,
+                &target_db_name,
+                &format!(
+                    "回滚将删除目标库 {} 后还原到 {}",
+                    target_db_name, manifest.id
+                ),
+            )?;
+            Resolution::Recreate(target_db_name)
+        } else {
+            let existing = query_tables(&target_pool, &target_db_name).await?;
+            match resolve_conflict(target, &target_db_name, &existing) {
+                Ok(resolution) => resolution,
+                Err(e) => {
+                    eprintln!("数据库 {} 未还原: {}", entry.db_name, e);
+                    restore_failed.push(entry.db_name.clone());
+                    continue;
+                }
+            }
+        };
+        let target_db_name = match resolution {
+            Resolution::Skip => continue,
+            Resolution::Restore(name) => name,
+            Resolution::Recreate(name) => {
+                sqlx::raw_sql(&format!("DROP DATABASE IF EXISTS {}", quote_ident(&name)))
+                    .execute(&*target_pool)
+                    .await?;
+                println!("Database {} dropped", name);
+                name
+            }
+            Resolution::Merge(_) => unreachable!("merge 在还原前已拒绝"),
+        };
+        let reader = codec.decode(&manifest, storage.open(&path)?)?;
+        if let Err(e) = restore_database(
+            &target_pool,
+            reader,
+            &storage.location(&path),
+            target,
+            &target_db_name,
+        )
+        .await
+        {
+            eprintln!("数据库 {} 还原失败: {}", target_db_name, e);
+            restore_failed.push(entry.db_name.clone());
+        }
+    }
+    if !failed.is_empty() {
+        return Err(format!("以下数据库的备份文件校验失败: {:?}", failed).into());
+    }
+    if !restore_failed.is_empty() {
+        return Err(format!("以下数据库还原失败: {:?}", restore_failed).into());
+    }
+    Ok(manifest)
+}
+
+// 还原到的目标库：配置了 [target] db_name 时还原到该库（只能还原一个数据库），否则还原到同名库
+fn restore_targets<'a>(
+    entries: Vec<&'a BackupEntry>,
+    db_name: Option<&str>,
+) -> Result<Vec<(&'a BackupEntry, String)>, String> {
+    match db_name {
+        Some(db_name) if entries.len() > 1 => Err(format!(
+            "[target] db_name = {} 只能还原一个数据库，备份集中选择了 {} 个，请用 [backup] databases 指定",
+            db_name,
+            entries.len()
+        )),
+        Some(db_name) => Ok(entries
+            .into_iter()
+            .map(|entry| (entry, db_name.to_string()))
+            .collect()),
+        None => Ok(entries
+            .into_iter()
+            .map(|entry| (entry, entry.db_name.clone()))
+            .collect()),
+    }
+}
+
+// 校验备份文件：大小和sha256与清单一致，解密、解压后以 "Dump completed" 结尾
+fn verify_entry(
+    storage: &dyn BackupStorage,
+    codec: &BackupCodec,
+    manifest: &BackupManifest,
+    entry: &BackupEntry,
+    path: &str,
+) -> io::Result<()> {
+    let (reader, digest) = HashingReader::new(storage.open(path)?);
+    let completed = check_dump_completed(
+        codec.decode(manifest, Box::new(reader))?,
+        MYSQLDUMP_COMPLETED,
+    )?;
+    let (size, sha256) = digest.digest();
+    if size != entry.size || sha256 != entry.sha256 {
+        return Err(io::Error::other(format!(
+            "大小/sha256 {}/{} 与清单记录的 {}/{} 不一致",
+            size, sha256, entry.size, entry.sha256
+        )));
+    }
+    if !completed {
+        return Err(io::Error::other(
+            "备份文件不完整：缺少 \"Dump completed\" 标记",
+        ));
+    }
+    Ok(())
+}
+
+// 导出数据库并流式写入存储，返回 (写入存储的大小, sha256)
+fn dump_to_storage(
+    storage: &dyn BackupStorage,
+    codec: &BackupCodec,
+    source: &Source,
+    db_name: &str,
+    path: &str,
+) -> Result<(u64, String), Box<dyn Error + Send + Sync>> {
+    let writer = HashingWriter::new(storage.create(path)?);
+    let (writer, size, sha256) = codec.dump(source, db_name, writer)?.into_parts();
+    writer.finish()?;
+    println!(
+        "[ok] Database {} dumped to {}",
+        db_name,
+        storage.location(path)
+    );
+    Ok((size, sha256))
+}
+
+#[cfg(test)]
+mod test_backup {
+    use super::*;
+
+    #[test]
+    fn test_user_databases() {
+        let databases = [
+            "canteen",
+            "information_schema",
+            "mysql",
+            "performance_schema",
+            "sys",
+            "canteen_report",
+        ]
+        .map(String::from)
+        .to_vec();
+        assert_eq!(user_databases(databases), vec!["canteen", "canteen_report"]);
+    }
+
+    fn entry(db_name: &str) -> BackupEntry {
+        BackupEntry {
+            db_name: db_name.to_string(),
+            file: format!("{}.sql", db_name),
+            size: 0,
+            sha256: String::new(),
+            started_at: String::new(),
+            finished_at: String::new(),
+        }
+    }
+
+    #[test]
+    fn test_restore_targets() {
+        let (canteen, report) = (entry("canteen"), entry("canteen_report"));
+        let names = |targets: Vec<(&BackupEntry, String)>| {
+            targets
+                .into_iter()
+                .map(|(entry, name)| (entry.db_name.clone(), name))
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(
+            names(restore_targets(vec![&canteen, &report], None).unwrap()),
+            vec![
+                ("canteen".to_string(), "canteen".to_string()),
+                ("canteen_report".to_string(), "canteen_report".to_string())
+            ]
+        );
+        assert_eq!(
+            names(restore_targets(vec![&canteen], Some("canteen_copy")).unwrap()),
+            vec![("canteen".to_string(), "canteen_copy".to_string())]
+        );
+        // 多个库不能还原到同一个目标库
+        assert!(restore_targets(vec![&canteen, &report], Some("canteen_copy")).is_err());
+    }
+
+    #[test]
+    fn test_pre_sync_backup_config() {
+        let backup = Backup {
+            dir: Some("backup".to_string()),
+            compress: Some(true),
+            ..Backup::default()
+        };
+        let existing = ["canteen", "canteen_report"].map(String::from);
+        let databases = vec!["canteen".to_string(), "canteen_new".to_string()];
+        let config = pre_sync_backup_config(&backup, databases, &existing).unwrap();
+        // 只备份已存在的目标库，其余配置不变
+        assert_eq!(config.databases, Some(vec!["canteen".to_string()]));
+        assert_eq!(config.dir.as_deref(), Some("backup"));
+        assert_eq!(config.compress, Some(true));
+        assert!(
+            pre_sync_backup_config(&backup, vec!["canteen_new".to_string()], &existing).is_none()
+        );
+    }
+
+    #[test]
+    fn test_target_as_source() {
+        let target: Target = toml::from_str(
+            r#"
+            host = "10.0.0.2"
+            port = "3307"
+            user = "sync"
+            password = "secret"
+            db_name = "canteen"
+            "#,
+        )
+        .unwrap();
+        let source = target_as_source(&target);
+        assert_eq!(
+            (
+                source.host.as_str(),
+                source.port.as_str(),
+                source.user.as_str()
+            ),
+            ("10.0.0.2", "3307", "sync")
+        );
+        assert_eq!(source.password, "secret");
+        // 备份所有选定的库，不限定源库
+        assert_eq!(source.db_name, None);
+        assert!(source.filters.is_empty());
+    }
+}
@@ -0,0 +1,186 @@
+// 备份目录（catalog）
+// 每次备份生成一个备份集：{dir}/{set_id}/manifest.json + {dir}/{set_id}/{db_name}.sql
+// 备份集保存在 BackupStorage 中（本地目录、S3、SFTP）
+
+use std::{error::Error, sync::Arc};
+
+use chrono::{Local, NaiveDateTime};
+use serde::{Deserialize, Serialize};
+
+use crate::storage::backend::{BackupStorage, join_path};
+
+pub const MANIFEST_FILE: &str = "manifest.json";
+pub const TIME_FORMAT: &str = "%Y-%m-%d %H:%M:%S";
+
+// 备份集清单
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct BackupManifest {
+    pub id: String,
+    pub job_name: String,
+    pub database_type: String,
+    pub server_version: String,
+    pub started_at: String,
+    pub finished_at: String,
+    pub entries: Vec<BackupEntry>,
+    pub failed: Vec<String>, // 备份失败的数据库
+    #[serde(default)]
+    pub compression: Option<String>, // gzip
+    #[serde(default)]
+    pub encryption: Option<String>, // aes-256-gcm
+    #[serde(default)]
+    pub key_id: Option<String>, // 加密密钥标识
+}
+
+// 单个数据库的备份文件
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct BackupEntry {
+    pub db_name: String,
+    pub file: String, // 相对于备份集目录的文件名
+    pub size: u64,
+    pub sha256: String,
+    pub started_at: String,
+    pub finished_at: String,
+}
+
+impl BackupManifest {
+    pub fn new(job_name: &str, database_type: &str, server_version: &str) -> Self {
+        let now = Local::now();
+        BackupManifest {
+            id: format!("{}_{}", job_name, now.format("%Y%m%d_%H%M%S")),
+            job_name: job_name.to_string(),
+            database_type: database_type.to_string(),
+            server_version: server_version.to_string(),
+            started_at: now.format(TIME_FORMAT).to_string(),
+            finished_at: String::new(),
+            entries: Vec::new(),
+            failed: Vec::new(),
+            compression: None,
+            encryption: None,
+            key_id: None,
+        }
+    }
+
+    pub fn started_at_time(&self) -> Option<NaiveDateTime> {
+        NaiveDateTime::parse_from_str(&self.started_at, TIME_FORMAT).ok()
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct BackupCatalog {
+    pub storage: Arc<dyn BackupStorage>,
+    pub root: String,
+}
+
+impl BackupCatalog {
+    pub fn new(storage: Arc<dyn BackupStorage>, root: &str) -> Self {
+        BackupCatalog {
+            storage,
+            root: root.to_string(),
+        }
+    }
+
+    // 备份集目录
+    pub fn set_dir(&self, set_id: &str) -> String {
+        join_path(&self.root, set_id)
+    }
+
+    // 备份集中某个文件的路径
+    pub fn entry_path(&self, set_id: &str, file: &str) -> String {
+        join_path(&self.set_dir(set_id), file)
+    }
+
+    // 写入备份集清单
+    pub fn save(&self, manifest: &BackupManifest) -> Result<(), Box<dyn Error + Send + Sync>> {
+        let json = serde_json::to_string_pretty(manifest)?;
+        self.storage.write_all(
+            &self.entry_path(&manifest.id, MANIFEST_FILE),
+            json.as_bytes(),
+        )?;
+        Ok(())
+    }
+
+    // 读取指定备份集
+    pub fn load(&self, set_id: &str) -> Result<BackupManifest, Box<dyn Error + Send + Sync>> {
+        let json = self
+            .storage
+            .read_to_string(&self.entry_path(set_id, MANIFEST_FILE))?;
+        Ok(serde_json::from_str(&json)?)
+    }
+
+    // 列出所有备份集，按开始时间升序
+    pub fn list(&self) -> Result<Vec<BackupManifest>, Box<dyn Error + Send + Sync>> {
+        let mut manifests = Vec::new();
+        for set_id in self.storage.list_dirs(&self.root)? {
+            if !self
+                .storage
+                .exists(&self.entry_path(&set_id, MANIFEST_FILE))?
+            {
+                continue;
+            }
+            match self.load(&set_id) {
+                Ok(manifest) => manifests.push(manifest),
+                Err(e) => eprintln!("跳过无法读取的备份集 {}: {}", set_id, e),
+            }
+        }
+        manifests.sort_by(|a, b| a.started_at.cmp(&b.started_at));
+        Ok(manifests)
+    }
+
+    // 选择备份集：优先按id，其次按时间（该时间之前最近的一次），都未指定则取最新
+    pub fn select(
+        &self,
+        set_id: Option<&str>,
+        time: Option<&str>,
+    ) -> Result<BackupManifest, Box<dyn Error + Send + Sync>> {
+        if let Some(set_id) = set_id {
+            return self.load(set_id);
+        }
+        let manifests = self.list()?;
+        let selected = match time {
+            Some(time) => {
+                let time = NaiveDateTime::parse_from_str(time, TIME_FORMAT)
+                    .map_err(|e| format!("备份时间格式错误({}): {}", TIME_FORMAT, e))?;
+                manifests
+                    .into_iter()
+                    .rev()
+                    .find(|m| m.started_at_time().is_some_and(|t| t <= time))
+            }
+            None => manifests.into_iter().last(),
+        };
+        selected.ok_or_else(|| "没有找到符合条件的备份集".into())
+    }
+}
+
+#[cfg(test)]
+mod test_catalog {
+    use std::fs;
+
+    use super::*;
+    use crate::storage::local::LocalStorage;
+
+    #[test]
+    fn test_select_backup_set() {
+        let root = std::env::temp_dir().join("datasync_test_catalog");
+        let _ = fs::remove_dir_all(&root);
+        let catalog = BackupCatalog::new(Arc::new(LocalStorage::new(&root)), "sql");
+        for (id, started_at) in [
+            ("job_20250101_020000", "2025-01-01 02:00:00"),
+            ("job_20250102_020000", "2025-01-02 02:00:00"),
+        ] {
+            let mut manifest = BackupManifest::new("job", "mysql", "8.0.36");
+            manifest.id = id.to_string();
+            manifest.started_at = started_at.to_string();
+            catalog.save(&manifest).unwrap();
+        }
+
+        let latest = catalog.select(None, None).unwrap();
+        assert_eq!(latest.id, "job_20250102_020000");
+        let by_time = catalog.select(None, Some("2025-01-01 12:00:00")).unwrap();
+        assert_eq!(by_time.id, "job_20250101_020000");
+        let by_id = catalog.select(Some("job_20250102_020000"), None).unwrap();
+        assert_eq!(by_id.server_version, "8.0.36");
+        assert!(catalog.select(None, Some("2024-12-31 00:00:00")).is_err());
+
+        fs::remove_dir_all(&root).unwrap();
+    }
+}
@@ -0,0 +1,272 @@
+// 字符集和排序规则：检测源库表和列的字符集，按 [target] charset/collation 转换目标库的表
+// （例如 latin1、gbk 的旧库转换为 utf8mb4），转换前在源库检查数据能否无损地转换为目标字符集
+
+use std::collections::BTreeMap;
+
+use sqlx::{MySql, Pool, Row};
+
+use crate::{
+    handle::{mysql_schema::quote_ident, parallel::execute, sync::BoxError},
+    model::job::Target,
+};
+
+// 可以表示所有Unicode字符的字符集，任何字符集都能无损地转换为这些字符集
+const UNICODE_CHARSETS: [&str; 5] = ["utf8mb4", "utf16", "utf16le", "utf32", "gb18030"];
+
+// 目标库表的字符集和排序规则，collation 不配置时为目标库中该字符集的默认排序规则
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TargetCharset {
+    pub charset: String,
+    pub collation: Option<String>,
+}
+
+impl TargetCharset {
+    // 只配置 collation 时字符集取排序规则名的前缀，例如 utf8mb4_general_ci -> utf8mb4
+    pub fn from_target(target: &Target) -> Option<Self> {
+        let collation = target.collation.clone();
+        let charset = target.charset.clone().or_else(|| {
+            collation
+                .as_deref()
+                .and_then(|c| c.split('_').next())
+                .map(|c| c.to_string())
+        })?;
+        Some(TargetCharset {
+            charset: normalize(&charset).to_string(),
+            collation,
+        })
+    }
+}
+
+// 源库一列的字符集
+#[derive(Debug, Clone)]
+pub struct ColumnCharset {
+    pub table: String,
+    pub column: String,
+    pub charset: String,
+    pub collation: String,
+}
+
+// utf8 是 utf8mb3 的别名（8.0 的 information_schema 中为 utf8mb3）
+fn normalize(charset: &str) -> &str {
+    match charset {
+        "utf8" => "utf8mb3",
+        other => other,
+    }
+}
+
+// from 的所有字符都能用 to 表示；utf8mb3 只能表示基本多文种平面（不含emoji等4字节字符）
+pub fn lossless(from: &str, to: &str) -> bool {
+    let (from, to) = (normalize(from), normalize(to));
+    from == to
+        || from == "ascii"
+        || UNICODE_CHARSETS.contains(&to)
+        || (to == "utf8mb3" && !UNICODE_CHARSETS.contains(&from))
+}
+
+// 读取库中表的文本列的字符集，tables 为空时为所有表
+pub async fn read_charsets(
+    pool: &Pool<MySql>,
+    db_name: &str,
+    tables: &[String],
+) -> Result<Vec<ColumnCharset>, sqlx::Error> {
+    let rows = sqlx::query(
+        "SELECT c.TABLE_NAME, c.COLUMN_NAME, c.CHARACTER_SET_NAME, c.COLLATION_NAME \
+         FROM information_schema.COLUMNS c \
+         JOIN information_schema.TABLES t \
+           ON t.TABLE_SCHEMA = c.TABLE_SCHEMA AND t.TABLE_NAME = c.TABLE_NAME \
+         WHERE c.TABLE_SCHEMA = ? AND t.TABLE_TYPE = 'BASE TABLE' \
+           AND c.CHARACTER_SET_NAME IS NOT NULL \
+         ORDER BY c.TABLE_NAME, c.ORDINAL_POSITION",
+    )
+    .bind(db_name)
+    .fetch_all(pool)
+    .await?;
+    let mut columns = Vec::new();
+    for row in rows {
+        let column = ColumnCharset {
+            table: row.try_get(0)?,
+            column: row.try_get(1)?,
+            charset: row.try_get(2)?,
+            collation: row.try_get(3)?,
+        };
+        if tables.is_empty() || tables.contains(&column.table) {
+            columns.push(column);
+        }
+    }
+    Ok(columns)
+}
+
+// 各字符集的列数，例如 latin1 (12 列), utf8mb4 (30 列)
+pub fn summarize(columns: &[ColumnCharset]) -> String {
+    let mut counts: BTreeMap<&str, usize> = BTreeMap::new();
+    for column in columns {
+        *counts.entry(normalize(&column.charset)).or_default() += 1;
+    }
+    counts
+        .iter()
+        .map(|(charset, n)| format!("{} ({} 列)", charset, n))
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
+// 在源库检查不能无损转换为 charset 的列：转换为目标字符集再转换回来与原值不同的行数
+// 返回有丢失的列的说明，为空表示可以无损转换
+pub async fn check_lossless(
+    pool: &Pool<MySql>,
+    db_name: &str,
+    columns: &[ColumnCharset],
+    charset: &str,
+) -> Result<Vec<String>, BoxError> {
+    let mut lossy = Vec::new();
+    for column in columns.iter().filter(|c| !lossless(&c.charset, charset)) {
+        let ident = quote_ident(&column.column);
+        let row = sqlx::query(&format!(
+            "SELECT COUNT(*) FROM {}.{} WHERE {} IS NOT NULL \
+             AND BINARY {} <> BINARY CONVERT(CONVERT({} USING {}) USING {})",
+            quote_ident(db_name),
+            quote_ident(&column.table),
+            ident,
+            ident,
+            ident,
+            charset,
+            column.charset
+        ))
+        .fetch_one(pool)
+        .await?;
+        let count: i64 = row.try_get(0)?;
+        if count > 0 {
+            lossy.push(format!(
+                "{}.{}（{} -> {}）{} 行",
+                column.table, column.column, column.charset, charset, count
+            ));
+        }
+    }
+    Ok(lossy)
+}
+
+// 目标库中排序规则：配置的排序规则需要属于该字符集，不配置时为字符集的默认排序规则
+async fn resolve_collation(
+    pool: &Pool<MySql>,
+    target_charset: &TargetCharset,
+) -> Result<String, BoxError> {
+    let row = match &target_charset.collation {
+        Some(collation) => sqlx::query(
+            "SELECT COLLATION_NAME FROM information_schema.COLLATIONS \
+             WHERE COLLATION_NAME = ? AND CHARACTER_SET_NAME = ?",
+        )
+        .bind(collation)
+        .bind(&target_charset.charset),
+        None => sqlx::query(
+            "SELECT DEFAULT_COLLATE_NAME FROM information_schema.CHARACTER_SETS \
+             WHERE CHARACTER_SET_NAME = ?",
+        )
+        .bind(&target_charset.charset),
+    }
+    .fetch_optional(pool)
+    .await?;
+    match row {
+        Some(row) => Ok(row.try_get(0)?),
+        None => Err(format!(
+            "目标库不支持字符集 {} 或排序规则 {}",
+            target_charset.charset,
+            target_charset.collation.as_deref().unwrap_or("（默认）")
+        )
+        .into()),
+    }
+}
+
+// 把目标库 db_name 中字符集或排序规则不同的表（tables 为空时为所有表）转换为目标字符集，返回转换的表数
+// 在严格模式（STRICT_ALL_TABLES）下执行 ALTER TABLE ... CONVERT TO，无法转换的字符会使语句失败而不是写为 ?
+pub async fn convert_tables(
+    pool: &Pool<MySql>,
+    db_name: &str,
+    tables: &[String],
+    target_charset: &TargetCharset,
+) -> Result<usize, BoxError> {
+    let collation = resolve_collation(pool, target_charset).await?;
+    let rows = sqlx::query(
+        "SELECT t.TABLE_NAME FROM information_schema.TABLES t \
+         WHERE t.TABLE_SCHEMA = ? AND t.TABLE_TYPE = 'BASE TABLE' \
+           AND (t.TABLE_COLLATION <> ? OR EXISTS (\
+             SELECT 1 FROM information_schema.COLUMNS c \
+             WHERE c.TABLE_SCHEMA = t.TABLE_SCHEMA AND c.TABLE_NAME = t.TABLE_NAME \
+               AND c.COLLATION_NAME <> ?)) \
+         ORDER BY t.TABLE_NAME",
+    )
+    .bind(db_name)
+    .bind(&collation)
+    .bind(&collation)
+    .fetch_all(pool)
+    .await?;
+    let mut converting = Vec::new();
+    for row in rows {
+        let table: String = row.try_get(0)?;
+        if tables.is_empty() || tables.contains(&table) {
+            converting.push(table);
+        }
+    }
+
+    // 修改了会话的 sql_mode，用完后关闭连接，不放回连接池
+    let mut conn = pool.acquire().await?;
+    conn.close_on_drop();
+    let mut statements = vec![
+        "SET SESSION sql_mode = IF(@@SESSION.sql_mode = '', 'STRICT_ALL_TABLES', \
+         CONCAT(@@SESSION.sql_mode, ',STRICT_ALL_TABLES'))"
+            .to_string(),
+        "SET foreign_key_checks = 0".to_string(),
+    ];
+    if tables.is_empty() {
+        statements.push(format!(
+            "ALTER DATABASE {} CHARACTER SET {} COLLATE {}",
+            quote_ident(db_name),
+            target_charset.charset,
+            collation
+        ));
+    }
+    execute(&mut conn, &statements.join("; ")).await?;
+    for table in &converting {
+        execute(
+            &mut conn,
+            &format!(
+                "ALTER TABLE {}.{} CONVERT TO CHARACTER SET {} COLLATE {}",
+                quote_ident(db_name),
+                quote_ident(table),
+                target_charset.charset,
+                collation
+            ),
+        )
+        .await
+        .map_err(|e| format!("表 {}.{} 转换为 {} 失败: {}", db_name, table, collation, e))?;
+    }
+    Ok(converting.len())
+}
+
+#[cfg(test)]
+mod test_charset {
+    use super::*;
+
+    #[test]
+    fn test_lossless() {
+        assert!(lossless("latin1", "utf8mb4"));
+        assert!(lossless("gbk", "utf8mb4"));
+        assert!(lossless("gbk", "utf8"));
+        assert!(lossless("utf8", "utf8mb3"));
+        assert!(lossless("ascii", "latin1"));
+        assert!(!lossless("utf8mb4", "utf8"));
+        assert!(!lossless("utf8mb4", "gbk"));
+        assert!(!lossless("gbk", "latin1"));
+
+        let column = |table: &str, charset: &str| ColumnCharset {
+            table: table.to_string(),
+            column: "name".to_string(),
+            charset: charset.to_string(),
+            collation: format!("{}_bin", charset),
+        };
+        let columns = vec![
+            column("orders", "latin1"),
+            column("users", "utf8mb4"),
+            column("users", "latin1"),
+        ];
+        assert_eq!(summarize(&columns), "latin1 (2 列), utf8mb4 (1 列)");
+    }
+}
@@ -0,0 +1,457 @@
+// 源库和目标库的版本兼容：检查降级路径，按 mysqldump 客户端和源库版本选择参数，
+// 还原时改写目标库不支持的语句（8.0 的 utf8mb4_0900_* 排序规则、DEFINER）
+
+use std::{
+    fmt,
+    io::{self, BufRead, BufReader, Read},
+    process::Command,
+    sync::OnceLock,
+};
+
+use crate::{
+    handle::{help::query_mysql_version, mysql_schema::quote_ident},
+    model::job::Target,
+};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ServerVersion {
+    pub major: u32,
+    pub minor: u32,
+    pub patch: u32,
+    pub mariadb: bool,
+}
+
+impl ServerVersion {
+    // SELECT VERSION() 的结果，例如 8.0.36、5.7.44-log、10.11.6-MariaDB-1:10.11.6+maria~ubu2204
+    // 通过复制协议连接的 MariaDB 返回 5.5.5-10.11.6-MariaDB，跳过前缀
+    pub fn parse(version: &str) -> Option<Self> {
+        let mariadb = version.contains("MariaDB");
+        let version = match version.strip_prefix("5.5.5-") {
+            Some(rest) if mariadb => rest,
+            _ => version,
+        };
+        let numbers = version
+            .split(|c: char| !c.is_ascii_digit() && c != '.')
+            .next()?
+            .split('.')
+            .map(|n| n.parse::<u32>().ok())
+            .collect::<Option<Vec<_>>>()?;
+        let [major, minor, rest @ ..] = numbers.as_slice() else {
+            return None;
+        };
+        Some(ServerVersion {
+            major: *major,
+            minor: *minor,
+            patch: rest.first().copied().unwrap_or(0),
+            mariadb,
+        })
+    }
+
+    // mysqldump --version 的输出，例如
+    // mysqldump  Ver 8.0.36 for Linux on x86_64 (MySQL Community Server - GPL)
+    // mysqldump  Ver 10.19 Distrib 10.11.6-MariaDB, for debian-linux-gnu (x86_64)
+    pub fn parse_client(output: &str) -> Option<Self> {
+        let version = match output.split_once("Distrib ") {
+            Some((_, rest)) => rest,
+            None => output.split_once("Ver ")?.1,
+        };
+        ServerVersion::parse(version.split([',', ' ']).next()?.trim()).map(|v| ServerVersion {
+            mariadb: v.mariadb || output.contains("MariaDB"),
+            ..v
+        })
+    }
+
+    pub fn at_least(&self, major: u32, minor: u32, patch: u32) -> bool {
+        (self.major, self.minor, self.patch) >= (major, minor, patch)
+    }
+
+    // 支持 utf8mb4_0900_* 排序规则（MySQL 8.0 起，MariaDB 不支持）
+    pub fn supports_0900_collations(&self) -> bool {
+        !self.mariadb && self.major >= 8
+    }
+
+    // MySQL 的发布系列：5.5、5.6、5.7、8.0、8.x（8.1~8.4）、9.x，降级跨越多个系列时不支持
+    fn release_line(&self) -> u32 {
+        match (self.major, self.minor) {
+            (5, minor) => minor.saturating_sub(5),
+            (8, 0) => 3,
+            (8, _) => 4,
+            (major, _) => major.saturating_sub(4),
+        }
+    }
+}
+
+impl fmt::Display for ServerVersion {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}.{}.{}", self.major, self.minor, self.patch)?;
+        if self.mariadb {
+            write!(f, "-MariaDB")?;
+        }
+        Ok(())
+    }
+}
+
+// 检查源库到目标库的版本，返回警告；MySQL 降级跨越多个发布系列（例如 8.0 -> 5.6）时失败，
+// [target] allow_downgrade = true 时改为警告
+pub fn check_versions(
+    source: &ServerVersion,
+    target: &ServerVersion,
+    allow_downgrade: bool,
+) -> Result<Vec<String>, String> {
+    let mut warnings = Vec::new();
+    if source.mariadb != target.mariadb {
+        warnings.push(format!(
+            "源库 {} 与目标库 {} 不是同一种数据库，部分语法和排序规则可能不兼容",
+            source, target
+        ));
+    } else if (target.major, target.minor) < (source.major, source.minor) {
+        let unsupported = !source.mariadb && source.release_line() > target.release_line() + 1;
+        let message = format!("目标库 {} 的版本低于源库 {}", target, source);
+        if unsupported && !allow_downgrade {
+            return Err(format!(
+                "{}，不支持跨越多个版本系列的降级（确认后可配置 [target] allow_downgrade = true）",
+                message
+            ));
+        }
+        warnings.push(format!(
+            "{}，新版本的语法和功能在目标库中可能不可用",
+            message
+        ));
+    }
+    if source.supports_0900_collations() && !target.supports_0900_collations() {
+        warnings.push(format!(
+            "目标库 {} 不支持 utf8mb4_0900_* 排序规则，还原时改写为 utf8mb4_unicode_520_ci / utf8mb4_bin",
+            target
+        ));
+    }
+    Ok(warnings)
+}
+
+// mysqldump 客户端的版本，只查询一次，无法执行时为 None
+pub fn mysqldump_version() -> Option<ServerVersion> {
+    static VERSION: OnceLock<Option<ServerVersion>> = OnceLock::new();
+    *VERSION.get_or_init(|| {
+        let output = Command::new("mysqldump").arg("--version").output().ok()?;
+        ServerVersion::parse_client(&String::from_utf8_lossy(&output.stdout))
+    })
+}
+
+// 按 mysqldump 客户端和源库版本选择参数，版本未知时使用 MySQL 8.0 客户端的参数
+// MariaDB 的 mysqldump 没有 --set-gtid-purged 和 --compression-algorithms；
+// 8.0 的客户端导出 5.7 或 MariaDB 时需要 --column-statistics=0（旧版本没有 COLUMN_STATISTICS 表）
+pub fn dump_flags(
+    client: Option<ServerVersion>,
+    server: Option<ServerVersion>,
+) -> Vec<&'static str> {
+    let mut flags = Vec::new();
+    match client {
+        Some(client) if client.mariadb => flags.push("--compress"),
+        Some(client) if !client.at_least(8, 0, 18) => {
+            flags.push("--compress");
+            if client.at_least(5, 6, 9) {
+                flags.push("--set-gtid-purged=OFF");
+            }
+        }
+        _ => {
+            flags.push("--compression-algorithms=zlib");
+            flags.push("--set-gtid-purged=OFF");
+        }
+    }
+    let client_8 = client.is_some_and(|c| !c.mariadb && c.major >= 8);
+    if client_8 && server.is_some_and(|s| s.mariadb || s.major < 8) {
+        flags.push("--column-statistics=0");
+    }
+    flags
+}
+
+// 视图、触发器、存储过程和事件的 DEFINER（[target] definer）
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub enum Definer {
+    #[default]
+    Keep, // 保留源库的定义者，目标库中需要有该用户
+    Strip,           // 去掉 DEFINER，定义者为还原使用的用户
+    Rewrite(String), // 改为指定的用户，`user`@`host`
+}
+
+impl Definer {
+    // keep | strip | user@host（只写用户名时 host 为 %）
+    pub fn parse(value: Option<&str>) -> Self {
+        match value.map(str::trim) {
+            None | Some("") | Some("keep") => Definer::Keep,
+            Some("strip") => Definer::Strip,
+            Some(account) => {
+                let (user, host) = account.rsplit_once('@').unwrap_or((account, "%"));
+                let unquote = |s: &str| {
+                    s.trim_matches(|c| c == '`' || c == '\'' || c == '"')
+                        .to_string()
+                };
+                Definer::Rewrite(format!(
+                    "{}@{}",
+                    quote_ident(&unquote(user)),
+                    quote_ident(&unquote(host))
+                ))
+            }
+        }
+    }
+}
+
+// 还原时对导出文件中的语句（不包括 INSERT/REPLACE 数据行）做的改写
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct DdlRewrite {
+    pub collations: bool, // utf8mb4_0900_* 改写为目标库支持的排序规则
+    pub definer: Definer,
+}
+
+impl DdlRewrite {
+    pub fn is_empty(&self) -> bool {
+        !self.collations && self.definer == Definer::Keep
+    }
+
+    pub fn apply(&self, sql: &str) -> String {
+        let mut sql = sql.to_string();
+        if self.collations {
+            sql = rewrite_collations(&sql);
+        }
+        match &self.definer {
+            Definer::Keep => {}
+            Definer::Strip => sql = rewrite_definer(&sql, None),
+            Definer::Rewrite(account) => sql = rewrite_definer(&sql, Some(account)),
+        }
+        sql
+    }
+}
+
+// 按目标库版本和 [target] definer 配置确定改写
+pub async fn ddl_rewrite(
+    target_pool: &sqlx::Pool<sqlx::MySql>,
+    target: &Target,
+) -> Result<DdlRewrite, sqlx::Error> {
+    let version = query_mysql_version(target_pool).await?;
+    Ok(DdlRewrite {
+        collations: ServerVersion::parse(&version).is_some_and(|v| !v.supports_0900_collations()),
+        definer: Definer::parse(target.definer.as_deref()),
+    })
+}
+
+// utf8mb4_0900_ai_ci、utf8mb4_zh_0900_as_cs 等：不区分大小写的改写为 utf8mb4_unicode_520_ci，其它改写为 utf8mb4_bin
+pub fn rewrite_collations(sql: &str) -> String {
+    let mut result = String::with_capacity(sql.len());
+    let mut rest = sql;
+    while let Some(start) = rest.find("utf8mb4_") {
+        result.push_str(&rest[..start]);
+        let token_len = rest[start..]
+            .find(|c: char| !c.is_ascii_alphanumeric() && c != '_')
+            .unwrap_or(rest.len() - start);
+        let token = &rest[start..start + token_len];
+        if token.contains("_0900_") {
+            result.push_str(if token.ends_with("_ci") {
+                "utf8mb4_unicode_520_ci"
+            } else {
+                "utf8mb4_bin"
+            });
+        } else {
+            result.push_str(token);
+        }
+        rest = &rest[start + token_len..];
+    }
+    result.push_str(rest);
+    result
+}
+
+// DEFINER=`user`@`host`（也支持 'user'@'host' 和 CURRENT_USER）改为 DEFINER=account，account 为 None 时去掉，
+// 保留 SQL SECURITY DEFINER
+pub fn rewrite_definer(sql: &str, account: Option<&str>) -> String {
+    let mut result = String::with_capacity(sql.len());
+    let mut rest = sql;
+    while let Some(start) = rest.find("DEFINER=") {
+        result.push_str(&rest[..start]);
+        let after = &rest[start + "DEFINER=".len()..];
+        let len = definer_len(after);
+        match account {
+            Some(account) => {
+                result.push_str("DEFINER=");
+                result.push_str(account);
+                rest = &after[len..];
+            }
+            None => rest = after[len..].strip_prefix(' ').unwrap_or(&after[len..]),
+        }
+    }
+    result.push_str(rest);
+    result
+}
+
+// DEFINER= 之后的用户的长度：user@host，各部分可以用 ` 或 ' 引起来
+fn definer_len(sql: &str) -> usize {
+    let part = |s: &str| -> usize {
+        let mut chars = s.char_indices();
+        match chars.next() {
+            Some((_, quote @ ('`' | '\'' | '"'))) => {
+                let mut escaped = false;
+                for (i, c) in chars {
+                    if escaped {
+                        escaped = false;
+                    } else if c == quote {
+                        // 两个引号表示引号本身
+                        if s[i + 1..].starts_with(quote) {
+                            escaped = true;
+                        } else {
+                            return i + 1;
+                        }
+                    }
+                }
+                s.len()
+            }
+            _ => s
+                .find(|c: char| !c.is_ascii_alphanumeric() && !"_$.%-".contains(c))
+                .unwrap_or(s.len()),
+        }
+    };
+    let user = part(sql);
+    if sql[user..].starts_with('@') {
+        let host = part(&sql[user + 1..]);
+        user + 1 + host
+    } else {
+        // CURRENT_USER 或 CURRENT_USER()
+        user + if sql[user..].starts_with("()") { 2 } else { 0 }
+    }
+}
+
+// 逐行改写导出文件，数据行（INSERT/REPLACE）和不是UTF-8的行原样输出
+pub struct RewriteReader<R> {
+    reader: BufReader<R>,
+    rewrite: DdlRewrite,
+    line: Vec<u8>,
+    pos: usize,
+}
+
+impl<R: Read> RewriteReader<R> {
+    pub fn new(reader: R, rewrite: DdlRewrite) -> Self {
+        RewriteReader {
+            reader: BufReader::new(reader),
+            rewrite,
+            line: Vec::new(),
+            pos: 0,
+        }
+    }
+}
+
+impl<R: Read> Read for RewriteReader<R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        if self.pos >= self.line.len() {
+            self.line.clear();
+            self.pos = 0;
+            if self.reader.read_until(b'\n', &mut self.line)? == 0 {
+                return Ok(0);
+            }
+            let data = self.line.starts_with(b"INSERT ") || self.line.starts_with(b"REPLACE ");
+            if !data && let Ok(line) = std::str::from_utf8(&self.line) {
+                self.line = self.rewrite.apply(line).into_bytes();
+            }
+        }
+        let n = buf.len().min(self.line.len() - self.pos);
+        buf[..n].copy_from_slice(&self.line[self.pos..self.pos + n]);
+        self.pos += n;
+        Ok(n)
+    }
+}
+
+#[cfg(test)]
+mod test_compat {
+    use super::*;
+
+    #[test]
+    fn test_versions() {
+        let v = |s: &str| ServerVersion::parse(s).unwrap();
+        assert_eq!(v("5.7.44-log").to_string(), "5.7.44");
+        assert_eq!(
+            v("5.5.5-10.11.6-MariaDB-1:10.11.6").to_string(),
+            "10.11.6-MariaDB"
+        );
+        let client = ServerVersion::parse_client(
+            "mysqldump  Ver 10.19 Distrib 10.11.6-MariaDB, for debian-linux-gnu (x86_64)",
+        )
+        .unwrap();
+        assert_eq!(client, v("10.11.6-MariaDB"));
+        let client_8 = ServerVersion::parse_client(
+            "mysqldump  Ver 8.0.36 for Linux on x86_64 (MySQL Community Server - GPL)",
+        );
+        assert_eq!(client_8, Some(v("8.0.36")));
+
+        assert_eq!(
+            check_versions(&v("5.7.44"), &v("8.0.36"), false),
+            Ok(Vec::new())
+        );
+        assert_eq!(
+            check_versions(&v("8.0.36"), &v("5.7.44"), false)
+                .unwrap()
+                .len(),
+            2
+        );
+        assert!(check_versions(&v("8.0.36"), &v("5.6.51"), false).is_err());
+        assert!(check_versions(&v("8.0.36"), &v("5.6.51"), true).is_ok());
+
+        assert_eq!(
+            dump_flags(client_8, Some(v("5.7.44"))),
+            vec![
+                "--compression-algorithms=zlib",
+                "--set-gtid-purged=OFF",
+                "--column-statistics=0"
+            ]
+        );
+        assert_eq!(
+            dump_flags(Some(client), Some(v("10.11.6-MariaDB"))),
+            vec!["--compress"]
+        );
+        assert_eq!(dump_flags(None, None).len(), 2);
+    }
+
+    #[test]
+    fn test_rewrite() {
+        assert_eq!(
+            rewrite_collations(
+                ") ENGINE=InnoDB DEFAULT CHARSET=utf8mb4 COLLATE=utf8mb4_0900_ai_ci; \
+                 `code` varchar(8) COLLATE utf8mb4_0900_as_cs, `name` varchar(8) COLLATE utf8mb4_general_ci"
+            ),
+            ") ENGINE=InnoDB DEFAULT CHARSET=utf8mb4 COLLATE=utf8mb4_unicode_520_ci; \
+             `code` varchar(8) COLLATE utf8mb4_bin, `name` varchar(8) COLLATE utf8mb4_general_ci"
+        );
+        assert_eq!(
+            rewrite_definer(
+                "/*!50013 DEFINER=`app``x`@`%` SQL SECURITY DEFINER */",
+                None
+            ),
+            "/*!50013 SQL SECURITY DEFINER */"
+        );
+        let definer = Definer::parse(Some("'etl'@'10.0.%'"));
+        assert_eq!(definer, Definer::Rewrite("`etl`@`10.0.%`".to_string()));
+        assert_eq!(Definer::parse(Some("strip")), Definer::Strip);
+        let rewrite = DdlRewrite {
+            collations: false,
+            definer,
+        };
+        assert_eq!(
+            rewrite.apply("CREATE DEFINER=`root`@`localhost` PROCEDURE `p`()"),
+            "CREATE DEFINER=`etl`@`10.0.%` PROCEDURE `p`()"
+        );
+        assert_eq!(
+            rewrite_definer("CREATE DEFINER=CURRENT_USER TRIGGER t", None),
+            "CREATE TRIGGER t"
+        );
+
+        let dump = "CREATE TABLE `t` (`a` text) COLLATE=utf8mb4_0900_ai_ci;\n\
+                    INSERT INTO `t` VALUES ('utf8mb4_0900_ai_ci');\n";
+        let rewrite = DdlRewrite {
+            collations: true,
+            definer: Definer::Keep,
+        };
+        let mut out = String::new();
+        RewriteReader::new(dump.as_bytes(), rewrite)
+            .read_to_string(&mut out)
+            .unwrap();
+        assert_eq!(
+            out,
+            "CREATE TABLE `t` (`a` text) COLLATE=utf8mb4_unicode_520_ci;\n\
+             INSERT INTO `t` VALUES ('utf8mb4_0900_ai_ci');\n"
+        );
+    }
+}
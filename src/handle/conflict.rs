@@ -0,0 +1,190 @@
+// 目标库已存在且不为空时的处理方式（[target] conflict_policy），以及源库和目标库相同时的保护
+
+use std::io::{self, BufRead, IsTerminal, Write};
+
+use chrono::Local;
+
+use crate::model::job::Target;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ConflictPolicy {
+    Fail,     // 默认：不覆盖，任务失败
+    Skip,     // 跳过这个库
+    Recreate, // 删除目标库后重新创建
+    Merge,    // 保留目标库，按主键/唯一键覆盖同步的行（REPLACE），缺少的表自动创建
+    Suffix,   // 还原到新库 {库名}_{时间}
+}
+
+impl ConflictPolicy {
+    pub fn parse(value: Option<&str>) -> Result<Self, String> {
+        match value.unwrap_or("fail") {
+            "fail" => Ok(ConflictPolicy::Fail),
+            "skip" => Ok(ConflictPolicy::Skip),
+            "recreate" => Ok(ConflictPolicy::Recreate),
+            "merge" => Ok(ConflictPolicy::Merge),
+            "suffix" => Ok(ConflictPolicy::Suffix),
+            other => Err(format!("不支持的 conflict_policy {}", other)),
+        }
+    }
+}
+
+// 目标库的处理结果
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Resolution {
+    Restore(String),  // 写入这个库（不存在或为空，或还原到新库）
+    Recreate(String), // 先删除再写入
+    Merge(String),    // 合并到已有的库
+    Skip,
+}
+
+// 按 conflict_policy 处理目标库 db_name，existing_tables 为目标库中已有的表（库不存在时为空）
+// 删除或覆盖数据的方式需要确认：[target] confirm 中填写目标库名（多个用逗号分隔），或在终端中输入库名
+pub fn resolve_conflict(
+    target: &Target,
+    db_name: &str,
+    existing_tables: &[String],
+) -> Result<Resolution, String> {
+    if existing_tables.is_empty() {
+        return Ok(Resolution::Restore(db_name.to_string()));
+    }
+    let policy = ConflictPolicy::parse(target.conflict_policy.as_deref())?;
+    let summary = format!("目标库 {} 已有 {} 个表", db_name, existing_tables.len());
+    match policy {
+        ConflictPolicy::Fail => Err(format!(
+            "{}，conflict_policy = fail，不覆盖（可配置 skip、recreate、merge、suffix）",
+            summary
+        )),
+        ConflictPolicy::Skip => {
+            println!("[skip] {}，conflict_policy = skip", summary);
+            Ok(Resolution::Skip)
+        }
+        ConflictPolicy::Recreate => {
+            confirm_destructive(target, db_name, &format!("{}，将被删除后重新创建", summary))?;
+            Ok(Resolution::Recreate(db_name.to_string()))
+        }
+        ConflictPolicy::Merge => {
+            confirm_destructive(
+                target,
+                db_name,
+                &format!("{}，同步的行将覆盖已有的行", summary),
+            )?;
+            Ok(Resolution::Merge(db_name.to_string()))
+        }
+        ConflictPolicy::Suffix => {
+            let suffixed = format!("{}_{}", db_name, Local::now().format("%Y%m%d_%H%M%S"));
+            println!("{}，还原到 {}", summary, suffixed);
+            Ok(Resolution::Restore(suffixed))
+        }
+    }
+}
+
+// 确认删除或覆盖目标库 db_name 的数据
+pub fn confirm_destructive(target: &Target, db_name: &str, message: &str) -> Result<(), String> {
+    let confirmed = target
+        .confirm
+        .as_deref()
+        .is_some_and(|confirm| confirm.split(',').any(|c| c.trim() == db_name));
+    if confirmed {
+        println!("[confirm] {}（已在 [target] confirm 中确认）", message);
+        return Ok(());
+    }
+    if !io::stdin().is_terminal() {
+        return Err(format!(
+            "{}，需要确认：在 [target] confirm 中填写目标库名 {}",
+            message, db_name
+        ));
+    }
+    print!("{}，输入目标库名 {} 确认：", message, db_name);
+    io::stdout().flush().map_err(|e| e.to_string())?;
+    let mut input = String::new();
+    io::stdin()
+        .lock()
+        .read_line(&mut input)
+        .map_err(|e| e.to_string())?;
+    if input.trim() == db_name {
+        Ok(())
+    } else {
+        Err(format!("输入的库名与 {} 不一致，已取消", db_name))
+    }
+}
+
+// 源库和目标库是同一个服务器上的同一个库时拒绝执行，server_id 相同表示同一个服务器
+pub fn check_same_target(
+    source_server: &str,
+    target_server: &str,
+    databases: &[(String, String)],
+) -> Result<(), String> {
+    if source_server != target_server {
+        return Ok(());
+    }
+    match databases.iter().find(|(db, target_db)| db == target_db) {
+        Some((db, _)) => Err(format!(
+            "源库和目标库是同一个服务器（{}）上的同一个库 {}，拒绝执行",
+            source_server, db
+        )),
+        None => Ok(()),
+    }
+}
+
+#[cfg(test)]
+mod test_conflict {
+    use super::*;
+
+    fn target(policy: &str, confirm: Option<&str>) -> Target {
+        Target {
+            database_type: None,
+            host: "127.0.0.1".to_string(),
+            port: "3306".to_string(),
+            user: "root".to_string(),
+            password: "root".to_string(),
+            path: None,
+            db_name: None,
+            table_name: None,
+            conflict_policy: Some(policy.to_string()),
+            confirm: confirm.map(|c| c.to_string()),
+            swap: None,
+            pre_sync_backup: None,
+            load_data: None,
+            unique_checks: None,
+            foreign_key_checks: None,
+            sql_log_bin: None,
+            charset: None,
+            collation: None,
+            charset_check: None,
+            allow_downgrade: None,
+            definer: None,
+        }
+    }
+
+    #[test]
+    fn test_resolve_conflict() {
+        let tables = vec!["orders".to_string()];
+        // 空库直接写入
+        assert_eq!(
+            resolve_conflict(&target("fail", None), "shop", &[]),
+            Ok(Resolution::Restore("shop".to_string()))
+        );
+        assert!(resolve_conflict(&target("fail", None), "shop", &tables).is_err());
+        assert_eq!(
+            resolve_conflict(&target("skip", None), "shop", &tables),
+            Ok(Resolution::Skip)
+        );
+        assert_eq!(
+            resolve_conflict(&target("recreate", Some("crm, shop")), "shop", &tables),
+            Ok(Resolution::Recreate("shop".to_string()))
+        );
+        assert_eq!(
+            resolve_conflict(&target("merge", Some("shop")), "shop", &tables),
+            Ok(Resolution::Merge("shop".to_string()))
+        );
+        match resolve_conflict(&target("suffix", None), "shop", &tables) {
+            Ok(Resolution::Restore(name)) => assert!(name.starts_with("shop_20")),
+            other => panic!("unexpected {:?}", other),
+        }
+        assert!(resolve_conflict(&target("overwrite", None), "shop", &tables).is_err());
+
+        let databases = vec![("shop".to_string(), "shop".to_string())];
+        assert!(check_same_target("uuid-1", "uuid-1", &databases).is_err());
+        assert!(check_same_target("uuid-1", "uuid-2", &databases).is_ok());
+    }
+}
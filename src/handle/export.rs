@@ -0,0 +1,676 @@
+// export 任务：把源库的表导出为 CSV、JSON Lines 或 Parquet 文件（给分析使用，不是SQL备份）
+// 复用 [source] 的库、表和 filters 配置，[handler] 中的转换（脱敏等）在写入文件前执行
+
+use std::{
+    fs::{self, File},
+    io::{BufWriter, Write},
+    path::{Path, PathBuf},
+    sync::Arc,
+};
+
+use arrow_array::{
+    ArrayRef, RecordBatch,
+    builder::{
+        BinaryBuilder, BooleanBuilder, Float64Builder, Int64Builder, StringBuilder, UInt64Builder,
+    },
+};
+use arrow_schema::{DataType, Field, Schema, SchemaRef};
+use encoding::{EncoderTrap, EncodingRef, label::encoding_from_whatwg_label};
+use parquet::{arrow::ArrowWriter, basic::Compression, file::properties::WriterProperties};
+use sqlx::{MySql, Pool};
+use tokio::sync::mpsc;
+
+use crate::{
+    handle::{
+        mysql_schema::{read_rows, read_tables},
+        mysql_sync::SYSTEM_DATABASES,
+        sync::BoxError,
+        transform::TableTransform,
+    },
+    model::{
+        job::{Export, Handler, Source},
+        schema::{ColumnSchema, RowValues, TableSchema, ValueKind},
+    },
+};
+
+// 每次从源库读取的行数，也是按大小拆分文件时检查的间隔
+const EXPORT_BATCH_SIZE: usize = 1000;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ExportFormat {
+    Csv,
+    Jsonl,
+    Parquet,
+}
+
+impl ExportFormat {
+    fn extension(&self) -> &'static str {
+        match self {
+            ExportFormat::Csv => "csv",
+            ExportFormat::Jsonl => "jsonl",
+            ExportFormat::Parquet => "parquet",
+        }
+    }
+}
+
+// 校验后的 [export] 配置
+#[derive(Clone)]
+pub struct ExportOptions {
+    pub format: ExportFormat,
+    pub dir: String,
+    pub delimiter: u8,
+    pub header: bool,
+    pub null: String,
+    pub encoding: Option<EncodingRef>, // None 为 utf-8，不转换
+    pub max_rows: Option<u64>,
+    pub max_bytes: Option<u64>,
+}
+
+impl ExportOptions {
+    pub fn from_config(export: &Export) -> Result<Self, String> {
+        let format = match export.format.as_deref().unwrap_or("csv") {
+            "csv" => ExportFormat::Csv,
+            "jsonl" => ExportFormat::Jsonl,
+            "parquet" => ExportFormat::Parquet,
+            other => return Err(format!("不支持的导出格式 {}", other)),
+        };
+        let delimiter = match export.delimiter.as_deref().unwrap_or(",").as_bytes() {
+            [b] => *b,
+            _ => return Err("delimiter 需要是一个ASCII字符".to_string()),
+        };
+        let encoding = match export.encoding.as_deref() {
+            None => None,
+            Some(label) => {
+                let encoding = encoding_from_whatwg_label(label)
+                    .ok_or_else(|| format!("不支持的编码 {}", label))?;
+                (encoding.whatwg_name() != Some("utf-8")).then_some(encoding)
+            }
+        };
+        if encoding.is_some() && format != ExportFormat::Csv {
+            return Err("encoding 只对 CSV 生效，JSONL 和 Parquet 固定为 utf-8".to_string());
+        }
+        Ok(ExportOptions {
+            format,
+            dir: export.dir().to_string(),
+            delimiter,
+            header: export.header.unwrap_or(true),
+            null: export.null.clone().unwrap_or_default(),
+            encoding,
+            max_rows: export.max_rows.filter(|n| *n > 0),
+            max_bytes: export.max_bytes.filter(|n| *n > 0),
+        })
+    }
+
+    fn split(&self) -> bool {
+        self.max_rows.is_some() || self.max_bytes.is_some()
+    }
+}
+
+// 一个导出的文件
+#[derive(Debug, Clone)]
+pub struct ExportedFile {
+    pub path: String,
+    pub rows: u64,
+}
+
+// 导出 [source] 配置的库和表：未配置 db_name 时导出所有库，未配置 table_name 时导出库中所有表
+// 一个表失败时继续导出其它表，最后返回错误
+pub async fn export_job(
+    pool: &Pool<MySql>,
+    source: &Source,
+    handler: Option<&Handler>,
+    export: &Export,
+) -> Result<Vec<ExportedFile>, BoxError> {
+    let options = ExportOptions::from_config(export)?;
+    let databases = match &source.db_name {
+        Some(db_name) => vec![db_name.clone()],
+        None => sqlx::query_scalar::<_, String>(
+            "SELECT SCHEMA_NAME FROM information_schema.SCHEMATA ORDER BY SCHEMA_NAME",
+        )
+        .fetch_all(pool)
+        .await?
+        .into_iter()
+        .filter(|db| !SYSTEM_DATABASES.contains(&db.as_str()))
+        .collect(),
+    };
+    let tables = source.table_names();
+    let mut files = Vec::new();
+    let mut failed = Vec::new();
+    for db_name in &databases {
+        for table in read_tables(&mut *pool.acquire().await?, db_name, &tables).await? {
+            let filter = source.table_filter(db_name, &table.name);
+            if let Some(filter) = filter {
+                println!("[filter] {}.{} WHERE {}", db_name, table.name, filter);
+            }
+            let result = match TableTransform::build(handler, db_name, &table) {
+                Ok(transform) => {
+                    export_table(pool, db_name, &table, transform.as_ref(), filter, &options).await
+                }
+                Err(e) => Err(e.into()),
+            };
+            match result {
+                Ok(exported) => {
+                    let rows = exported.iter().map(|f| f.rows).sum::<u64>();
+                    println!(
+                        "[ok] 表 {}.{} 导出 {} 行，{} 个文件",
+                        db_name,
+                        table.name,
+                        rows,
+                        exported.len()
+                    );
+                    files.extend(exported);
+                }
+                Err(e) => {
+                    eprintln!("[error] 表 {}.{} 导出失败: {}", db_name, table.name, e);
+                    failed.push(format!("{}.{}", db_name, table.name));
+                }
+            }
+        }
+    }
+    if !failed.is_empty() {
+        return Err(format!("{} 个表导出失败: {}", failed.len(), failed.join(", ")).into());
+    }
+    Ok(files)
+}
+
+// 导出一个表：边读边写，超过 max_rows / max_bytes 时换下一个文件
+pub async fn export_table(
+    pool: &Pool<MySql>,
+    db_name: &str,
+    table: &TableSchema,
+    transform: Option<&TableTransform>,
+    filter: Option<&str>,
+    options: &ExportOptions,
+) -> Result<Vec<ExportedFile>, BoxError> {
+    let output = transform.map(|t| &t.output).unwrap_or(table);
+    let dir = Path::new(&options.dir).join(db_name);
+    fs::create_dir_all(&dir)?;
+
+    let mut conn = pool.acquire().await?;
+    let (tx, mut rx) = mpsc::channel(2);
+    let read = read_rows(&mut conn, db_name, table, filter, EXPORT_BATCH_SIZE, tx);
+    let write = async {
+        let mut files = Vec::new();
+        let mut current: Option<(Box<dyn FileWriter>, ExportedFile)> = None;
+        while let Some(rows) = rx.recv().await {
+            let rows = match transform {
+                Some(transform) => transform.apply(rows).0,
+                None => rows,
+            };
+            let mut rows = rows.as_slice();
+            while !rows.is_empty() {
+                let full = current.as_ref().is_some_and(|(writer, file)| {
+                    options.max_rows.is_some_and(|max| file.rows >= max)
+                        || options.max_bytes.is_some_and(|max| writer.size() >= max)
+                });
+                if full && let Some((writer, file)) = current.take() {
+                    writer.finish()?;
+                    files.push(file);
+                }
+                let (writer, file) = match &mut current {
+                    Some(current) => current,
+                    None => current.insert(open_part(&dir, output, options, files.len() + 1)?),
+                };
+                let n = match options.max_rows {
+                    Some(max) => rows.len().min((max - file.rows) as usize),
+                    None => rows.len(),
+                };
+                writer.write(output, &rows[..n])?;
+                file.rows += n as u64;
+                rows = &rows[n..];
+            }
+        }
+        // 空表也输出一个文件（CSV 只有表头）
+        let (writer, file) = match current {
+            Some(current) => current,
+            None => open_part(&dir, output, options, 1)?,
+        };
+        writer.finish()?;
+        files.push(file);
+        Ok::<_, BoxError>(files)
+    };
+    let (_, files) = tokio::try_join!(read, write)?;
+    Ok(files)
+}
+
+// 打开第 part 个文件：{表名}.{扩展名}，配置了拆分时为 {表名}_{序号}.{扩展名}
+fn open_part(
+    dir: &Path,
+    table: &TableSchema,
+    options: &ExportOptions,
+    part: usize,
+) -> Result<(Box<dyn FileWriter>, ExportedFile), BoxError> {
+    let extension = options.format.extension();
+    let name = if options.split() {
+        format!("{}_{:04}.{}", table.name, part, extension)
+    } else {
+        format!("{}.{}", table.name, extension)
+    };
+    let path: PathBuf = dir.join(name);
+    let file = File::create(&path)?;
+    let writer: Box<dyn FileWriter> = match options.format {
+        ExportFormat::Csv => Box::new(CsvFile::new(file, table, options)?),
+        ExportFormat::Jsonl => Box::new(JsonlFile {
+            out: BufWriter::new(file),
+            size: 0,
+        }),
+        ExportFormat::Parquet => Box::new(ParquetFile::new(file, table)?),
+    };
+    Ok((
+        writer,
+        ExportedFile {
+            path: path.to_string_lossy().into_owned(),
+            rows: 0,
+        },
+    ))
+}
+
+trait FileWriter: Send {
+    fn write(&mut self, table: &TableSchema, rows: &[RowValues]) -> Result<(), BoxError>;
+
+    // 已写入的大致字节数
+    fn size(&self) -> u64;
+
+    fn finish(self: Box<Self>) -> Result<(), BoxError>;
+}
+
+// 统计写入的字节数
+struct CountingWriter<W> {
+    inner: W,
+    count: u64,
+}
+
+impl<W: Write> Write for CountingWriter<W> {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        let n = self.inner.write(buf)?;
+        self.count += n as u64;
+        Ok(n)
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        self.inner.flush()
+    }
+}
+
+struct CsvFile {
+    writer: csv::Writer<CountingWriter<BufWriter<File>>>,
+    null: Vec<u8>,
+    encoding: Option<EncodingRef>,
+}
+
+impl CsvFile {
+    fn new(file: File, table: &TableSchema, options: &ExportOptions) -> Result<Self, BoxError> {
+        let writer = csv::WriterBuilder::new()
+            .delimiter(options.delimiter)
+            .from_writer(CountingWriter {
+                inner: BufWriter::new(file),
+                count: 0,
+            });
+        let mut csv_file = CsvFile {
+            writer,
+            null: Vec::new(),
+            encoding: options.encoding,
+        };
+        csv_file.null = csv_file.encode(options.null.as_bytes());
+        if options.header {
+            let header = table
+                .columns
+                .iter()
+                .map(|c| csv_file.encode(c.name.as_bytes()))
+                .collect::<Vec<_>>();
+            csv_file.writer.write_record(&header)?;
+        }
+        Ok(csv_file)
+    }
+
+    // 文本从utf-8转换为配置的编码，无法表示的字符写为 ?
+    fn encode(&self, value: &[u8]) -> Vec<u8> {
+        match self.encoding {
+            Some(encoding) => encoding
+                .encode(&String::from_utf8_lossy(value), EncoderTrap::Replace)
+                .unwrap_or_else(|_| value.to_vec()),
+            None => value.to_vec(),
+        }
+    }
+}
+
+impl FileWriter for CsvFile {
+    fn write(&mut self, table: &TableSchema, rows: &[RowValues]) -> Result<(), BoxError> {
+        for row in rows {
+            let record = table
+                .columns
+                .iter()
+                .zip(row)
+                .map(|(column, value)| match value {
+                    None => self.null.clone(),
+                    Some(value) => match text_value(column, value) {
+                        Some(text) => self.encode(text.as_bytes()),
+                        None => self.encode(value),
+                    },
+                })
+                .collect::<Vec<_>>();
+            self.writer.write_record(&record)?;
+        }
+        Ok(())
+    }
+
+    fn size(&self) -> u64 {
+        self.writer.get_ref().count
+    }
+
+    fn finish(mut self: Box<Self>) -> Result<(), BoxError> {
+        self.writer.flush()?;
+        Ok(())
+    }
+}
+
+struct JsonlFile {
+    out: BufWriter<File>,
+    size: u64,
+}
+
+impl FileWriter for JsonlFile {
+    fn write(&mut self, table: &TableSchema, rows: &[RowValues]) -> Result<(), BoxError> {
+        for row in rows {
+            let object = table
+                .columns
+                .iter()
+                .zip(row)
+                .map(|(column, value)| {
+                    let value = match value {
+                        None => serde_json::Value::Null,
+                        Some(value) => json_value(column, value),
+                    };
+                    (column.name.clone(), value)
+                })
+                .collect::<serde_json::Map<_, _>>();
+            let mut line = serde_json::to_vec(&object)?;
+            line.push(b'\n');
+            self.out.write_all(&line)?;
+            self.size += line.len() as u64;
+        }
+        Ok(())
+    }
+
+    fn size(&self) -> u64 {
+        self.size
+    }
+
+    fn finish(mut self: Box<Self>) -> Result<(), BoxError> {
+        self.out.flush()?;
+        Ok(())
+    }
+}
+
+struct ParquetFile {
+    writer: ArrowWriter<File>,
+    schema: SchemaRef,
+}
+
+impl ParquetFile {
+    fn new(file: File, table: &TableSchema) -> Result<Self, BoxError> {
+        let schema = Arc::new(arrow_schema(table));
+        let properties = WriterProperties::builder()
+            .set_compression(Compression::SNAPPY)
+            .build();
+        let writer = ArrowWriter::try_new(file, schema.clone(), Some(properties))?;
+        Ok(ParquetFile { writer, schema })
+    }
+}
+
+impl FileWriter for ParquetFile {
+    fn write(&mut self, table: &TableSchema, rows: &[RowValues]) -> Result<(), BoxError> {
+        let columns = table
+            .columns
+            .iter()
+            .enumerate()
+            .map(|(i, column)| arrow_column(column, rows.iter().map(|row| row[i].as_deref())))
+            .collect::<Vec<_>>();
+        let batch = RecordBatch::try_new(self.schema.clone(), columns)?;
+        self.writer.write(&batch)?;
+        Ok(())
+    }
+
+    fn size(&self) -> u64 {
+        (self.writer.bytes_written() + self.writer.in_progress_size()) as u64
+    }
+
+    fn finish(self: Box<Self>) -> Result<(), BoxError> {
+        self.writer.close()?;
+        Ok(())
+    }
+}
+
+// 需要转换写法的值：二进制写为十六进制，bit 写为数字，其它值原样写入（返回 None）
+fn text_value(column: &ColumnSchema, value: &[u8]) -> Option<String> {
+    match column.kind() {
+        ValueKind::Boolean if column.data_type == "bit" => Some(
+            if value.iter().any(|b| *b != 0) {
+                "1"
+            } else {
+                "0"
+            }
+            .to_string(),
+        ),
+        ValueKind::Bit => Some(bit_value(value).to_string()),
+        ValueKind::Binary => Some(hex::encode(value)),
+        _ => None,
+    }
+}
+
+fn bit_value(value: &[u8]) -> u64 {
+    value.iter().fold(0u64, |acc, b| (acc << 8) | *b as u64)
+}
+
+fn json_value(column: &ColumnSchema, value: &[u8]) -> serde_json::Value {
+    let text = String::from_utf8_lossy(value);
+    match column.kind() {
+        ValueKind::Integer => text
+            .parse::<i64>()
+            .map(serde_json::Value::from)
+            .or_else(|_| text.parse::<u64>().map(serde_json::Value::from))
+            .unwrap_or_else(|_| serde_json::Value::from(text.into_owned())),
+        ValueKind::Float => text
+            .parse::<f64>()
+            .ok()
+            .and_then(serde_json::Number::from_f64)
+            .map(serde_json::Value::Number)
+            .unwrap_or_else(|| serde_json::Value::from(text.into_owned())),
+        ValueKind::Boolean if column.data_type == "bit" => {
+            serde_json::Value::Bool(value.iter().any(|b| *b != 0))
+        }
+        ValueKind::Boolean => serde_json::Value::Bool(text != "0"),
+        ValueKind::Bit => serde_json::Value::from(bit_value(value)),
+        ValueKind::Binary => serde_json::Value::from(hex::encode(value)),
+        ValueKind::Json => serde_json::from_slice(value)
+            .unwrap_or_else(|_| serde_json::Value::from(text.into_owned())),
+        // decimal 写为字符串，保留精度
+        _ => serde_json::Value::from(text.into_owned()),
+    }
+}
+
+// Parquet 列类型：整数、浮点数、布尔值、二进制按类型保存，decimal、日期时间等写为字符串
+fn arrow_type(column: &ColumnSchema) -> DataType {
+    match column.kind() {
+        ValueKind::Integer if column.unsigned() && column.data_type == "bigint" => DataType::UInt64,
+        ValueKind::Integer | ValueKind::Bit => DataType::Int64,
+        ValueKind::Float => DataType::Float64,
+        ValueKind::Boolean => DataType::Boolean,
+        ValueKind::Binary => DataType::Binary,
+        _ => DataType::Utf8,
+    }
+}
+
+fn arrow_schema(table: &TableSchema) -> Schema {
+    Schema::new(
+        table
+            .columns
+            .iter()
+            .map(|c| Field::new(&c.name, arrow_type(c), true))
+            .collect::<Vec<_>>(),
+    )
+}
+
+fn arrow_column<'a>(
+    column: &ColumnSchema,
+    values: impl Iterator<Item = Option<&'a [u8]>>,
+) -> ArrayRef {
+    let text = |v: &[u8]| String::from_utf8_lossy(v).into_owned();
+    match arrow_type(column) {
+        DataType::UInt64 => {
+            let mut builder = UInt64Builder::new();
+            values.for_each(|v| builder.append_option(v.and_then(|v| text(v).parse().ok())));
+            Arc::new(builder.finish())
+        }
+        DataType::Int64 if column.kind() == ValueKind::Bit => {
+            let mut builder = Int64Builder::new();
+            values.for_each(|v| builder.append_option(v.map(|v| bit_value(v) as i64)));
+            Arc::new(builder.finish())
+        }
+        DataType::Int64 => {
+            let mut builder = Int64Builder::new();
+            values.for_each(|v| builder.append_option(v.and_then(|v| text(v).parse().ok())));
+            Arc::new(builder.finish())
+        }
+        DataType::Float64 => {
+            let mut builder = Float64Builder::new();
+            values.for_each(|v| builder.append_option(v.and_then(|v| text(v).parse().ok())));
+            Arc::new(builder.finish())
+        }
+        DataType::Boolean => {
+            let bit = column.data_type == "bit";
+            let mut builder = BooleanBuilder::new();
+            values.for_each(|v| {
+                builder.append_option(v.map(|v| {
+                    if bit {
+                        v.iter().any(|b| *b != 0)
+                    } else {
+                        v != b"0"
+                    }
+                }))
+            });
+            Arc::new(builder.finish())
+        }
+        DataType::Binary => {
+            let mut builder = BinaryBuilder::new();
+            values.for_each(|v| builder.append_option(v));
+            Arc::new(builder.finish())
+        }
+        _ => {
+            let mut builder = StringBuilder::new();
+            values.for_each(|v| builder.append_option(v.map(text)));
+            Arc::new(builder.finish())
+        }
+    }
+}
+
+#[cfg(test)]
+mod test_export {
+    use super::*;
+    use encoding::Encoding;
+    use parquet::file::reader::{FileReader, SerializedFileReader};
+
+    fn table() -> TableSchema {
+        TableSchema {
+            name: "orders".to_string(),
+            columns: vec![
+                ColumnSchema::new("id", "bigint", "bigint unsigned"),
+                ColumnSchema::new("name", "varchar", "varchar(20)"),
+                ColumnSchema::new("paid", "tinyint", "tinyint(1)"),
+                ColumnSchema::new("amount", "decimal", "decimal(10,2)"),
+                ColumnSchema::new("photo", "blob", "blob"),
+            ],
+            primary_key: vec!["id".to_string()],
+            indexes: Vec::new(),
+            foreign_keys: Vec::new(),
+            comment: None,
+            charset: None,
+            collation: None,
+        }
+    }
+
+    fn rows() -> Vec<RowValues> {
+        (1..=5)
+            .map(|i| {
+                vec![
+                    Some(i.to_string().into_bytes()),
+                    Some("食堂,一号".as_bytes().to_vec()),
+                    Some(b"1".to_vec()),
+                    None,
+                    Some(vec![0xca, 0xfe]),
+                ]
+            })
+            .collect()
+    }
+
+    fn options(format: &str, dir: &Path, encoding: Option<&str>) -> ExportOptions {
+        ExportOptions::from_config(&Export {
+            format: Some(format.to_string()),
+            dir: Some(dir.to_string_lossy().into_owned()),
+            delimiter: Some(";".to_string()),
+            null: Some("\\N".to_string()),
+            encoding: encoding.map(|e| e.to_string()),
+            max_rows: Some(2),
+            ..Default::default()
+        })
+        .unwrap()
+    }
+
+    // 按 export_table 的拆分方式写入
+    fn write_parts(options: &ExportOptions, dir: &Path) -> Vec<ExportedFile> {
+        let table = table();
+        let rows = rows();
+        let mut files = Vec::new();
+        for (i, chunk) in rows.chunks(2).enumerate() {
+            let (mut writer, mut file) = open_part(dir, &table, options, i + 1).unwrap();
+            writer.write(&table, chunk).unwrap();
+            writer.finish().unwrap();
+            file.rows = chunk.len() as u64;
+            files.push(file);
+        }
+        files
+    }
+
+    #[test]
+    fn test_export_files() {
+        let dir = std::env::temp_dir().join(format!("datasync_export_{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+
+        let csv = options("csv", &dir, Some("gbk"));
+        let files = write_parts(&csv, &dir);
+        assert_eq!(files.len(), 3);
+        assert!(files[0].path.ends_with("orders_0001.csv"));
+        let content = fs::read(&files[0].path).unwrap();
+        // 分隔符为 ; 时内容中的 , 不需要引号
+        let expected = encoding::all::GBK
+            .encode(
+                "id;name;paid;amount;photo\n1;食堂,一号;1;\\N;cafe\n2;食堂,一号;1;\\N;cafe\n",
+                EncoderTrap::Strict,
+            )
+            .unwrap();
+        assert_eq!(content, expected);
+
+        let jsonl = options("jsonl", &dir, None);
+        let files = write_parts(&jsonl, &dir);
+        let content = fs::read_to_string(&files[2].path).unwrap();
+        assert_eq!(
+            content,
+            "{\"amount\":null,\"id\":5,\"name\":\"食堂,一号\",\"paid\":true,\"photo\":\"cafe\"}\n"
+        );
+
+        let parquet = options("parquet", &dir, None);
+        let files = write_parts(&parquet, &dir);
+        let reader = SerializedFileReader::new(File::open(&files[0].path).unwrap()).unwrap();
+        assert_eq!(reader.metadata().file_metadata().num_rows(), 2);
+
+        assert!(
+            ExportOptions::from_config(&Export {
+                format: Some("jsonl".to_string()),
+                encoding: Some("gbk".to_string()),
+                ..Default::default()
+            })
+            .is_err()
+        );
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,359 @@
+// [handler] 中使用的简单表达式：列名、数字、'字符串'、NULL、+ - * / %、括号和常用函数
+// 例如 amount * 100、concat(first_name, ' ', last_name)、coalesce(remark, '')
+
+use chrono::Local;
+use sha2::{Digest, Sha256};
+
+use crate::model::schema::RowValues;
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Expr {
+    Null,
+    Number(String),
+    Text(String),
+    Column(usize), // 输入行中的列序号
+    Neg(Box<Expr>),
+    Binary(char, Box<Expr>, Box<Expr>),
+    Call(String, Vec<Expr>),
+}
+
+// 支持的函数及参数个数范围
+const FUNCTIONS: [(&str, usize, usize); 11] = [
+    ("upper", 1, 1),
+    ("lower", 1, 1),
+    ("trim", 1, 1),
+    ("length", 1, 1),
+    ("concat", 1, usize::MAX),
+    ("coalesce", 1, usize::MAX),
+    ("substr", 2, 3),
+    ("replace", 3, 3),
+    ("round", 1, 2),
+    ("now", 0, 0),
+    ("sha256", 1, 1),
+];
+
+#[derive(Debug, Clone, PartialEq)]
+enum Token {
+    Number(String),
+    Text(String),
+    Ident(String),
+    Symbol(char),
+}
+
+// 解析表达式，columns 为输入行的列名
+pub fn parse(input: &str, columns: &[String]) -> Result<Expr, String> {
+    let tokens = tokenize(input)?;
+    let mut parser = Parser {
+        tokens,
+        pos: 0,
+        columns,
+    };
+    let expr = parser.expr()?;
+    if parser.pos < parser.tokens.len() {
+        return Err(format!("表达式 {} 中有多余的内容", input));
+    }
+    Ok(expr)
+}
+
+fn tokenize(input: &str) -> Result<Vec<Token>, String> {
+    let chars = input.chars().collect::<Vec<_>>();
+    let mut tokens = Vec::new();
+    let mut i = 0;
+    while i < chars.len() {
+        let c = chars[i];
+        if c.is_whitespace() {
+            i += 1;
+        } else if c.is_ascii_digit()
+            || (c == '.' && chars.get(i + 1).is_some_and(|n| n.is_ascii_digit()))
+        {
+            let start = i;
+            while i < chars.len() && (chars[i].is_ascii_digit() || chars[i] == '.') {
+                i += 1;
+            }
+            tokens.push(Token::Number(chars[start..i].iter().collect()));
+        } else if c == '\'' {
+            // 字符串中的单引号写成 ''
+            let mut text = String::new();
+            i += 1;
+            loop {
+                match chars.get(i) {
+                    None => return Err(format!("表达式 {} 中的字符串没有结束", input)),
+                    Some('\'') if chars.get(i + 1) == Some(&'\'') => {
+                        text.push('\'');
+                        i += 2;
+                    }
+                    Some('\'') => {
+                        i += 1;
+                        break;
+                    }
+                    Some(ch) => {
+                        text.push(*ch);
+                        i += 1;
+                    }
+                }
+            }
+            tokens.push(Token::Text(text));
+        } else if c == '`' {
+            let end = chars[i + 1..]
+                .iter()
+                .position(|ch| *ch == '`')
+                .ok_or_else(|| format!("表达式 {} 中的列名没有结束", input))?;
+            tokens.push(Token::Ident(chars[i + 1..i + 1 + end].iter().collect()));
+            i += end + 2;
+        } else if c.is_alphabetic() || c == '_' {
+            let start = i;
+            while i < chars.len() && (chars[i].is_alphanumeric() || chars[i] == '_') {
+                i += 1;
+            }
+            tokens.push(Token::Ident(chars[start..i].iter().collect()));
+        } else if "+-*/%(),".contains(c) {
+            tokens.push(Token::Symbol(c));
+            i += 1;
+        } else {
+            return Err(format!("表达式 {} 中有无法识别的字符 {}", input, c));
+        }
+    }
+    Ok(tokens)
+}
+
+struct Parser<'a> {
+    tokens: Vec<Token>,
+    pos: usize,
+    columns: &'a [String],
+}
+
+impl Parser<'_> {
+    fn next_symbol(&mut self, symbols: &str) -> Option<char> {
+        match self.tokens.get(self.pos) {
+            Some(Token::Symbol(c)) if symbols.contains(*c) => {
+                self.pos += 1;
+                Some(*c)
+            }
+            _ => None,
+        }
+    }
+
+    fn expect(&mut self, symbol: char) -> Result<(), String> {
+        self.next_symbol(&symbol.to_string())
+            .map(|_| ())
+            .ok_or_else(|| format!("缺少 {}", symbol))
+    }
+
+    fn expr(&mut self) -> Result<Expr, String> {
+        let mut left = self.term()?;
+        while let Some(op) = self.next_symbol("+-") {
+            left = Expr::Binary(op, Box::new(left), Box::new(self.term()?));
+        }
+        Ok(left)
+    }
+
+    fn term(&mut self) -> Result<Expr, String> {
+        let mut left = self.factor()?;
+        while let Some(op) = self.next_symbol("*/%") {
+            left = Expr::Binary(op, Box::new(left), Box::new(self.factor()?));
+        }
+        Ok(left)
+    }
+
+    fn factor(&mut self) -> Result<Expr, String> {
+        if self.next_symbol("-").is_some() {
+            return Ok(Expr::Neg(Box::new(self.factor()?)));
+        }
+        if self.next_symbol("(").is_some() {
+            let expr = self.expr()?;
+            self.expect(')')?;
+            return Ok(expr);
+        }
+        let token = self.tokens.get(self.pos).cloned().ok_or("表达式不完整")?;
+        self.pos += 1;
+        match token {
+            Token::Number(n) => {
+                n.parse::<f64>().map_err(|_| format!("无效的数字 {}", n))?;
+                Ok(Expr::Number(n))
+            }
+            Token::Text(t) => Ok(Expr::Text(t)),
+            Token::Ident(name) if name.eq_ignore_ascii_case("null") => Ok(Expr::Null),
+            Token::Ident(name) if self.next_symbol("(").is_some() => {
+                let function = name.to_lowercase();
+                let mut args = Vec::new();
+                if self.next_symbol(")").is_none() {
+                    loop {
+                        args.push(self.expr()?);
+                        if self.next_symbol(")").is_some() {
+                            break;
+                        }
+                        self.expect(',')?;
+                    }
+                }
+                let (_, min, max) = FUNCTIONS
+                    .iter()
+                    .find(|(f, _, _)| *f == function)
+                    .ok_or_else(|| format!("不支持的函数 {}", name))?;
+                if args.len() < *min || args.len() > *max {
+                    return Err(format!("函数 {} 的参数个数不正确", name));
+                }
+                Ok(Expr::Call(function, args))
+            }
+            Token::Ident(name) => self
+                .columns
+                .iter()
+                .position(|c| *c == name)
+                .map(Expr::Column)
+                .ok_or_else(|| format!("列 {} 不存在", name)),
+            Token::Symbol(c) => Err(format!("表达式中不应出现 {}", c)),
+        }
+    }
+}
+
+impl Expr {
+    // 计算表达式，NULL 返回 None；数字运算的参数不是数字时返回错误
+    pub fn eval(&self, row: &RowValues) -> Result<Option<String>, String> {
+        let value = match self {
+            Expr::Null => None,
+            Expr::Number(n) | Expr::Text(n) => Some(n.clone()),
+            Expr::Column(i) => row[*i]
+                .as_deref()
+                .map(|v| String::from_utf8_lossy(v).into_owned()),
+            Expr::Neg(e) => match e.eval(row)? {
+                None => None,
+                Some(v) => Some(format_number(-number(&v)?)),
+            },
+            Expr::Binary(op, left, right) => {
+                let (Some(l), Some(r)) = (left.eval(row)?, right.eval(row)?) else {
+                    return Ok(None);
+                };
+                arithmetic(*op, &l, &r)?
+            }
+            Expr::Call(function, args) => call(function, args, row)?,
+        };
+        Ok(value)
+    }
+}
+
+fn number(value: &str) -> Result<f64, String> {
+    value
+        .trim()
+        .parse::<f64>()
+        .map_err(|_| format!("{} 不是数字", value))
+}
+
+fn format_number(value: f64) -> String {
+    if value.fract() == 0.0 && value.abs() < 1e15 {
+        format!("{}", value as i64)
+    } else {
+        value.to_string()
+    }
+}
+
+fn arithmetic(op: char, left: &str, right: &str) -> Result<Option<String>, String> {
+    // 整数运算保持精度
+    if let (Ok(l), Ok(r)) = (left.trim().parse::<i64>(), right.trim().parse::<i64>()) {
+        let value = match op {
+            '+' => l.checked_add(r),
+            '-' => l.checked_sub(r),
+            '*' => l.checked_mul(r),
+            '%' if r == 0 => return Ok(None),
+            '%' => l.checked_rem(r),
+            _ => None,
+        };
+        if let Some(value) = value {
+            return Ok(Some(value.to_string()));
+        }
+    }
+    let (l, r) = (number(left)?, number(right)?);
+    let value = match op {
+        '+' => l + r,
+        '-' => l - r,
+        '*' => l * r,
+        // 与MySQL相同，除以0得到NULL
+        '/' | '%' if r == 0.0 => return Ok(None),
+        '/' => l / r,
+        _ => l % r,
+    };
+    Ok(Some(format_number(value)))
+}
+
+fn call(function: &str, args: &[Expr], row: &RowValues) -> Result<Option<String>, String> {
+    if function == "coalesce" {
+        for arg in args {
+            if let Some(value) = arg.eval(row)? {
+                return Ok(Some(value));
+            }
+        }
+        return Ok(None);
+    }
+    if function == "now" {
+        return Ok(Some(Local::now().format("%Y-%m-%d %H:%M:%S").to_string()));
+    }
+    let mut values = Vec::with_capacity(args.len());
+    for arg in args {
+        // 其它函数的参数有NULL时结果为NULL
+        let Some(value) = arg.eval(row)? else {
+            return Ok(None);
+        };
+        values.push(value);
+    }
+    let value = match function {
+        "upper" => values[0].to_uppercase(),
+        "lower" => values[0].to_lowercase(),
+        "trim" => values[0].trim().to_string(),
+        "length" => values[0].chars().count().to_string(),
+        "concat" => values.concat(),
+        "substr" => {
+            // 与SQL相同，位置从1开始
+            let start = (number(&values[1])? as usize).max(1) - 1;
+            let len = match values.get(2) {
+                Some(len) => number(len)?.max(0.0) as usize,
+                None => usize::MAX,
+            };
+            values[0].chars().skip(start).take(len).collect()
+        }
+        "replace" => values[0].replace(&values[1], &values[2]),
+        "round" => {
+            let digits = match values.get(1) {
+                Some(d) => number(d)? as usize,
+                None => 0,
+            };
+            format!("{:.*}", digits, number(&values[0])?)
+        }
+        "sha256" => hex::encode(Sha256::digest(values[0].as_bytes())),
+        _ => return Err(format!("不支持的函数 {}", function)),
+    };
+    Ok(Some(value))
+}
+
+#[cfg(test)]
+mod test_expr {
+    use super::*;
+
+    #[test]
+    fn test_eval() {
+        let columns = vec!["id".to_string(), "name".to_string(), "amount".to_string()];
+        let row = vec![
+            Some(b"7".to_vec()),
+            Some(b" Tom ".to_vec()),
+            Some(b"12.5".to_vec()),
+        ];
+        let eval = |input: &str| parse(input, &columns).unwrap().eval(&row).unwrap();
+
+        assert_eq!(eval("id * 100 + 1"), Some("701".to_string()));
+        assert_eq!(eval("amount * 2"), Some("25".to_string()));
+        assert_eq!(eval("-(id - 10) / 2"), Some("1.5".to_string()));
+        assert_eq!(eval("id / 0"), None);
+        assert_eq!(
+            eval("concat(upper(trim(name)), '''s #', `id`)"),
+            Some("TOM's #7".to_string())
+        );
+        assert_eq!(
+            eval("coalesce(NULL, substr(name, 2, 2))"),
+            Some("To".to_string())
+        );
+        assert_eq!(eval("round(amount / 3, 2)"), Some("4.17".to_string()));
+        assert_eq!(eval("length(NULL)"), None);
+
+        assert!(parse("missing + 1", &columns).is_err());
+        assert!(parse("upper(name, id)", &columns).is_err());
+        assert!(parse("'open", &columns).is_err());
+        assert!(parse("name * 2", &columns).unwrap().eval(&row).is_err());
+    }
+}
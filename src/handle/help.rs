@@ -1,8 +1,6 @@
 use std::{
-    fs::{self, File},
-    io::Write,
-    path::Path,
-    process::Command,
+    io::{self, Read, Write},
+    process::{Command, Stdio},
     sync::Arc,
 };
 
@@ -11,12 +9,42 @@ use encoding::label::encoding_from_whatwg_label;
 use sqlx::Row;
 use tokio::sync::Semaphore;
 
-use crate::model::job::{Source, Target};
+use crate::{
+    handle::{
+        backup::BackupCodec,
+        charset::{TargetCharset, check_lossless, convert_tables, read_charsets, summarize},
+        compat::{
+            RewriteReader, ServerVersion, check_versions, ddl_rewrite, dump_flags,
+            mysqldump_version,
+        },
+        conflict::{Resolution, confirm_destructive, resolve_conflict},
+        mysql_schema::quote_ident,
+        mysql_sync::SYSTEM_DATABASES,
+        parallel::{execute, native_load, open_snapshot, parallel_copy, plan_copy},
+        sync::SyncOptions,
+    },
+    model::job::{Backup, Source, Target},
+    storage::{
+        backend::{BackupStorage, join_path, open_storage},
+        local::LocalStorage,
+    },
+    util::{
+        checksum::{
+            HashingWriter, MYSQLDUMP_COMPLETED, TailWriter, verify_stored, write_stored_sidecar,
+        },
+        crypto::{DecryptReader, EncryptWriter, EncryptionKey},
+        progress::{self, Estimate, ProgressReader, ProgressWriter},
+    },
+};
 
 #[derive(Clone, Debug)]
 pub struct MysqlHelp {
     pub source_pool: Arc<sqlx::Pool<sqlx::MySql>>,
     pub target_pool: Arc<sqlx::Pool<sqlx::MySql>>,
+    pub options: SyncOptions,            // 同步模式和对象开关
+    pub storage: Arc<dyn BackupStorage>, // 导出文件的存储，默认本地目录
+    pub dump_dir: String,                // 导出文件在存储中的目录，默认 sql
+    pub key: Option<EncryptionKey>,      // 配置了 [backup.encryption] 时加密导出文件
 }
 
 impl MysqlHelp {
@@ -27,34 +55,190 @@ impl MysqlHelp {
         MysqlHelp {
             source_pool,
             target_pool,
+            options: SyncOptions::default(),
+            storage: Arc::new(LocalStorage::new(".")),
+            dump_dir: "sql".to_string(),
+            key: None,
+        }
+    }
+
+    pub fn with_options(mut self, options: SyncOptions) -> Self {
+        self.options = options;
+        self
+    }
+
+    // 导出文件写入 [backup] 配置的存储和目录，配置了 [backup.encryption] 时与备份文件一样加密
+    pub fn with_backup(mut self, backup: &Backup) -> io::Result<Self> {
+        self.storage = open_storage(backup.storage.as_ref())?;
+        self.dump_dir = backup.dir().to_string();
+        self.key = BackupCodec::from_config(backup)?.key.map(EncryptionKey);
+        Ok(self)
+    }
+
+    // 还原时解密导出文件
+    fn decode(&self, reader: Box<dyn Read + Send>) -> io::Result<Box<dyn Read + Send>> {
+        match &self.key {
+            Some(key) => Ok(Box::new(DecryptReader::new(reader, &key.0)?)),
+            None => Ok(reader),
         }
     }
 
-    pub async fn get_mysql_version(&self) -> Result<Vec<String>, sqlx::Error> {
-        let source_row: (String,) = sqlx::query_as("SELECT VERSION()")
+    // 不同步视图时跳过源库中的所有视图
+    async fn ignore_views(
+        &self,
+        mut options: SyncOptions,
+        db_name: &str,
+    ) -> Result<SyncOptions, sqlx::Error> {
+        if !options.views {
+            let rows = sqlx::query(
+                "SELECT TABLE_NAME FROM information_schema.VIEWS WHERE TABLE_SCHEMA = ?",
+            )
+            .bind(db_name)
+            .fetch_all(&*self.source_pool)
+            .await?;
+            for row in rows {
+                options.ignore_tables.push(row.try_get(0)?);
+            }
+        }
+        Ok(options)
+    }
+
+    // 按 conflict_policy 处理目标库；data_only 模式写入已存在的表，清空前需要确认
+    pub async fn resolve_target(
+        &self,
+        target: &Target,
+        target_db_name: &str,
+    ) -> Result<Resolution, Box<dyn std::error::Error + Send + Sync>> {
+        let existing = query_tables(&self.target_pool, target_db_name).await?;
+        if self.options.data_only() {
+            if !existing.is_empty() {
+                confirm_destructive(
+                    target,
+                    target_db_name,
+                    &format!("data_only 模式将清空目标库 {} 中同步的表", target_db_name),
+                )?;
+            }
+            return Ok(Resolution::Restore(target_db_name.to_string()));
+        }
+        Ok(resolve_conflict(target, target_db_name, &existing)?)
+    }
+
+    // merge 模式：目标库缺少的表按源库的建表语句创建
+    async fn create_missing_tables(
+        &self,
+        target: &Target,
+        db_name: &str,
+        target_db_name: &str,
+        tables: &[String],
+    ) -> Result<(), sqlx::Error> {
+        let tables = if tables.is_empty() {
+            query_base_tables(&self.source_pool, db_name).await?
+        } else {
+            tables.to_vec()
+        };
+        let existing = query_base_tables(&self.target_pool, target_db_name).await?;
+        let rewrite = ddl_rewrite(&self.target_pool, target).await?;
+        for table in tables.iter().filter(|t| !existing.contains(t)) {
+            let row = sqlx::query(&format!(
+                "SHOW CREATE TABLE {}.{}",
+                quote_ident(db_name),
+                quote_ident(table)
+            ))
             .fetch_one(&*self.source_pool)
             .await?;
-        let target_row: (String,) = sqlx::query_as("SELECT VERSION()")
-            .fetch_one(&*self.target_pool)
+            let ddl: String = row.try_get(1)?;
+            execute_without_fk_checks(
+                &self.target_pool,
+                &format!(
+                    "USE {}; {}",
+                    quote_ident(target_db_name),
+                    rewrite.apply(&ddl)
+                ),
+            )
             .await?;
-        let source_version = source_row.0;
-        let target_version = target_row.0;
-        Ok(vec![
-            format!("source version: {}", source_version),
-            format!("target version: {}", target_version),
-        ])
+            println!("Table {}.{} created", target_db_name, table);
+        }
+        Ok(())
+    }
+
+    // data_only 模式：导入前清空目标库中已存在的表，tables 为空时为源库的所有表
+    pub async fn truncate_target_tables(
+        &self,
+        db_name: &str,
+        target_db_name: &str,
+        tables: &[String],
+    ) -> Result<(), sqlx::Error> {
+        let tables = if tables.is_empty() {
+            query_base_tables(&self.source_pool, db_name).await?
+        } else {
+            tables.to_vec()
+        };
+        let existing = query_base_tables(&self.target_pool, target_db_name).await?;
+        let (statements, missing) = truncate_statements(target_db_name, &tables, &existing);
+        for table in missing {
+            eprintln!(
+                "[warn] 目标库 {} 中没有表 {}，data_only 模式不会创建表",
+                target_db_name, table
+            );
+        }
+        if !statements.is_empty() {
+            execute_without_fk_checks(&self.target_pool, &statements.join("; ")).await?;
+        }
+        Ok(())
+    }
+
+    // 查询源库和目标库的版本，检查降级路径并输出兼容性警告，记录源库版本用于选择 mysqldump 参数
+    pub async fn check_versions(
+        mut self,
+        target: &Target,
+    ) -> Result<Self, Box<dyn std::error::Error + Send + Sync>> {
+        let source_version = query_mysql_version(&self.source_pool).await?;
+        let target_version = query_mysql_version(&self.target_pool).await?;
+        println!(
+            "数据库版本信息: source {}, target {}, mysqldump {}",
+            source_version,
+            target_version,
+            mysqldump_version().map_or("unknown".to_string(), |v| v.to_string())
+        );
+        let source_version = ServerVersion::parse(&source_version);
+        if let (Some(source), Some(target_version)) =
+            (source_version, ServerVersion::parse(&target_version))
+        {
+            let allow_downgrade = target.allow_downgrade.unwrap_or(false);
+            for warning in check_versions(&source, &target_version, allow_downgrade)? {
+                eprintln!("[warn] {}", warning);
+            }
+        }
+        self.options.source_version = source_version;
+        Ok(self)
     }
 
     pub async fn get_all_databases(&self) -> Result<Vec<String>, sqlx::Error> {
-        let rows = sqlx::query("SHOW DATABASES")
-            .fetch_all(&*self.source_pool)
+        query_all_databases(&self.source_pool).await
+    }
+
+    // 登记要同步的库和源库 information_schema.TABLES 中的估计大小，用于输出进度和剩余时间
+    pub async fn plan_progress(
+        &self,
+        databases: &[(String, String)],
+        tables: &[String],
+    ) -> Result<(), sqlx::Error> {
+        let mut planned = Vec::new();
+        for (db_name, _) in databases {
+            if SYSTEM_DATABASES.contains(&db_name.as_str()) {
+                continue;
+            }
+            let estimate = query_estimate(
+                &self.source_pool,
+                db_name,
+                tables,
+                &self.options.ignore_tables,
+            )
             .await?;
-        let mut databases = Vec::new();
-        for row in rows {
-            let db_name: String = row.get("Database");
-            databases.push(db_name);
+            planned.push((db_name.clone(), estimate));
         }
-        Ok(databases)
+        progress::plan(planned);
+        Ok(())
     }
 
     // 同步所有数据库
@@ -62,31 +246,37 @@ impl MysqlHelp {
         self.backup_all_db(source, target).await
     }
 
-    // 备份所有数据库
+    // 备份所有数据库（不包括系统库）并还原到目标库的同名库
     pub async fn backup_all_db(&self, source: &Source, target: &Target) -> Result<(), sqlx::Error> {
         let databases = self.get_all_databases().await?;
+        // 先依次处理目标库冲突（可能需要在终端中确认），再并发同步
+        let mut resolved = Vec::new();
+        for db_name in databases {
+            if SYSTEM_DATABASES.contains(&db_name.as_str()) {
+                continue;
+            }
+            match self.resolve_target(target, &db_name).await {
+                Ok(Resolution::Skip) => {}
+                Ok(resolution) => resolved.push((db_name, resolution)),
+                Err(e) => eprintln!("Database {} not synced: {}", db_name, e),
+            }
+        }
         // 设置最大并发数为 5
         let semaphore = Arc::new(Semaphore::new(5));
         let mut tasks = Vec::new();
 
-        for db_name in databases {
+        for (db_name, resolution) in resolved {
             let source_cloned = source.clone();
             let target_cloned = target.clone();
-            // let help = self.clone();
             let help_arc = Arc::new(self.clone());
 
             let permit = semaphore.clone().acquire_owned().await.unwrap();
             let task = tokio::spawn(async move {
-                let backup_file_path = help_arc
-                    .mysqldump_database_backup(&source_cloned, &db_name)
-                    .await;
-
-                // 备份成功则还原
-                if backup_file_path.is_ok() {
-                    let backup_file_path = backup_file_path.unwrap();
-                    help_arc
-                        .mysqldump_database_restore(&backup_file_path, &target_cloned, &db_name)
-                        .await;
+                if let Err(e) = help_arc
+                    .sync_database(&source_cloned, &target_cloned, &db_name, &[], resolution)
+                    .await
+                {
+                    eprintln!("Failed to sync database {}: {}", db_name, e);
                 }
                 // 释放信号量
                 drop(permit);
@@ -102,50 +292,456 @@ impl MysqlHelp {
         Ok(())
     }
 
-    // 还原数据库
-    pub async fn mysqldump_database_restore(
+    // 同步单个数据库：source.db_name -> target.db_name（未配置时同名）
+    pub async fn sync_db(
         &self,
-        backup_file_path: &str,
+        source: &Source,
+        target: &Target,
+    ) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+        let db_name = source
+            .db_name
+            .as_deref()
+            .ok_or("database_sync任务需要配置 source.db_name")?;
+        let target_db_name = target.db_name.as_deref().unwrap_or(db_name);
+        let resolution = self.resolve_target(target, target_db_name).await?;
+        self.sync_database(source, target, db_name, &[], resolution)
+            .await
+    }
+
+    // 同步数据表：source.db_name 中的 source.table_name（多个表用逗号分隔）
+    pub async fn sync_table(
+        &self,
+        source: &Source,
+        target: &Target,
+    ) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+        let db_name = source
+            .db_name
+            .as_deref()
+            .ok_or("table_sync任务需要配置 source.db_name")?;
+        let tables = source.table_names();
+        if tables.is_empty() {
+            return Err("table_sync任务需要配置 source.table_name".into());
+        }
+        let target_db_name = target.db_name.as_deref().unwrap_or(db_name);
+        let resolution = self.resolve_target(target, target_db_name).await?;
+        self.sync_database(source, target, db_name, &tables, resolution)
+            .await
+    }
+
+    // 导出 db_name（tables 为空时整库），按 resolution 还原到目标库，记录这个库的进度和结果
+    async fn sync_database(
+        &self,
+        source: &Source,
         target: &Target,
         db_name: &str,
-    ) {
-        // 判断数据库是否存在，不存在则创建
-        let db_name = db_name.trim();
-        let db_exists = sqlx::query(
-            "SELECT SCHEMA_NAME FROM information_schema.SCHEMATA WHERE SCHEMA_NAME = ?",
-        )
-        .bind(db_name)
-        .fetch_optional(&*self.target_pool)
-        .await
-        .expect("Failed to check database existence");
-
-        if db_exists.is_none() {
-            sqlx::query(format!("CREATE DATABASE IF NOT EXISTS `{}`", db_name).as_str())
-                .execute(&*self.target_pool)
-                .await
-                .expect("Failed to create database");
-            println!("Database {} created", db_name);
+        tables: &[String],
+        resolution: Resolution,
+    ) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+        let progress = match &resolution {
+            Resolution::Restore(name) | Resolution::Recreate(name) | Resolution::Merge(name) => {
+                progress::find(db_name).inspect(|p| {
+                    p.add_target(name);
+                    p.start();
+                })
+            }
+            Resolution::Skip => None,
+        };
+        let result = self
+            .sync_resolved(source, target, db_name, tables, resolution)
+            .await;
+        if let Some(progress) = progress {
+            progress.finish(result.is_ok());
+        }
+        result
+    }
+
+    async fn sync_resolved(
+        &self,
+        source: &Source,
+        target: &Target,
+        db_name: &str,
+        tables: &[String],
+        resolution: Resolution,
+    ) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+        if resolution != Resolution::Skip {
+            self.check_charset(target, db_name, tables).await?;
+        }
+        let mut options = self.options.clone();
+        let target_db_name = match resolution {
+            Resolution::Skip => return Ok(()),
+            Resolution::Restore(name) => name,
+            Resolution::Recreate(name) if target.swap.unwrap_or(false) => {
+                return self
+                    .swap_database(source, target, db_name, tables, &name)
+                    .await;
+            }
+            // 同步数据表时导出文件中有 DROP TABLE，只删除这些表
+            Resolution::Recreate(name) => {
+                if tables.is_empty() {
+                    sqlx::raw_sql(&format!("DROP DATABASE IF EXISTS {}", quote_ident(&name)))
+                        .execute(&*self.target_pool)
+                        .await?;
+                    println!("Database {} dropped", name);
+                }
+                name
+            }
+            Resolution::Merge(name) => {
+                // 只导出数据，REPLACE 覆盖主键/唯一键相同的行；目标库的视图、触发器等保持不变
+                options.schema = false;
+                options.merge = true;
+                options.triggers = false;
+                options.routines = false;
+                options.events = false;
+                options.views = false;
+                self.create_missing_tables(target, db_name, &name, tables)
+                    .await?;
+                name
+            }
+        };
+        let options = self.ignore_views(options, db_name).await?;
+        if native_load(&options, target) {
+            return self
+                .parallel_database(source, target, db_name, tables, &options, &target_db_name)
+                .await;
+        }
+        let backup_file_path = if tables.is_empty() {
+            self.mysqldump_database_backup(source, db_name, &options)
+                .await?
         } else {
-            println!("Database {} already exists", db_name);
+            self.mysqldump_tables_backup(source, db_name, tables, &options)
+                .await?
+        };
+        if options.data_only() && !options.merge {
+            self.truncate_target_tables(db_name, &target_db_name, tables)
+                .await?;
         }
+        self.mysqldump_database_restore(&backup_file_path, target, &target_db_name)
+            .await?;
+        self.convert_charset(target, &target_db_name, tables).await
+    }
 
-        // 执行mysql命令，还原数据库
-        let output = execute_mysql_restore(
-            backup_file_path,
-            &target.host,
-            &target.port,
-            &target.user,
-            &target.password,
+    // 配置了 [target] charset 时，在修改目标库之前检测源库的字符集，并检查数据能否无损转换
+    async fn check_charset(
+        &self,
+        target: &Target,
+        db_name: &str,
+        tables: &[String],
+    ) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+        let Some(target_charset) = TargetCharset::from_target(target) else {
+            return Ok(());
+        };
+        let columns = read_charsets(&self.source_pool, db_name, tables).await?;
+        println!(
+            "[charset] {} 的文本列：{}，转换为 {}",
+            db_name,
+            summarize(&columns),
+            target_charset.charset
+        );
+        if !target.charset_check.unwrap_or(true) {
+            return Ok(());
+        }
+        let lossy = check_lossless(
+            &self.source_pool,
             db_name,
+            &columns,
+            &target_charset.charset,
+        )
+        .await?;
+        if !lossy.is_empty() {
+            return Err(format!(
+                "{} 中以下列的数据不能无损转换为 {}，目标库没有修改：{}",
+                db_name,
+                target_charset.charset,
+                lossy.join("；")
+            )
+            .into());
+        }
+        Ok(())
+    }
+
+    // 还原后把目标库中的表转换为 [target] charset/collation
+    async fn convert_charset(
+        &self,
+        target: &Target,
+        target_db_name: &str,
+        tables: &[String],
+    ) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+        let Some(target_charset) = TargetCharset::from_target(target) else {
+            return Ok(());
+        };
+        let converted =
+            convert_tables(&self.target_pool, target_db_name, tables, &target_charset).await?;
+        println!(
+            "[charset] {} 中 {} 个表已转换为 {}",
+            target_db_name, converted, target_charset.charset
+        );
+        Ok(())
+    }
+
+    // swap 方式：先还原到 {库名}_datasync_staging，校验后用一条 RENAME TABLE 把表换入目标库，
+    // 目标库原来的表移到 {库名}_datasync_old，视图、触发器、存储过程和事件在换入后重建
+    async fn swap_database(
+        &self,
+        source: &Source,
+        target: &Target,
+        db_name: &str,
+        tables: &[String],
+        target_db_name: &str,
+    ) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+        let staging = format!("{}_datasync_staging", target_db_name);
+        let old = format!("{}_datasync_old", target_db_name);
+        if let Some(progress) = progress::find(db_name) {
+            progress.add_target(&staging);
+        }
+        sqlx::raw_sql(&format!(
+            "DROP DATABASE IF EXISTS {}",
+            quote_ident(&staging)
+        ))
+        .execute(&*self.target_pool)
+        .await?;
+
+        // 只导出表，其它对象跨库移动时会出错或仍然引用 staging 库
+        let mut options = self.options.clone();
+        options.triggers = false;
+        options.routines = false;
+        options.events = false;
+        options.views = false;
+        let options = self.ignore_views(options, db_name).await?;
+        if native_load(&options, target) {
+            self.parallel_database(source, target, db_name, tables, &options, &staging)
+                .await?;
+        } else {
+            let path = self
+                .dump_part(source, db_name, tables, &options, "staging")
+                .await?;
+            self.mysqldump_database_restore(&path, target, &staging)
+                .await?;
+            self.convert_charset(target, &staging, tables).await?;
+        }
+
+        // 校验：同步的表都已还原到 staging 库
+        let expected = if tables.is_empty() {
+            query_base_tables(&self.source_pool, db_name).await?
+        } else {
+            tables.to_vec()
+        };
+        let staged = query_base_tables(&self.target_pool, &staging).await?;
+        let missing = expected
+            .iter()
+            .filter(|t| !staged.contains(t))
+            .collect::<Vec<_>>();
+        if !missing.is_empty() {
+            return Err(format!(
+                "{} 中缺少表 {:?}，目标库 {} 没有修改",
+                staging, missing, target_db_name
+            )
+            .into());
+        }
+
+        // 换入：目标库中同步的表（整库同步时为所有表）移到 old 库，staging 库的表移到目标库
+        let live = query_base_tables(&self.target_pool, target_db_name).await?;
+        let moved_out = live
+            .into_iter()
+            .filter(|t| tables.is_empty() || tables.contains(t))
+            .collect::<Vec<_>>();
+        // 换出的表上的触发器先保存建触发器语句，换入失败时重建，换入后在 old 库中重建
+        let rows = sqlx::query(
+            "SELECT TRIGGER_NAME, EVENT_OBJECT_TABLE FROM information_schema.TRIGGERS \
+             WHERE TRIGGER_SCHEMA = ?",
         )
-        .expect("Failed to execute mysql restore command");
+        .bind(target_db_name)
+        .fetch_all(&*self.target_pool)
+        .await?;
+        let mut triggers = Vec::new();
+        for row in rows {
+            let (trigger, table): (String, String) = (row.try_get(0)?, row.try_get(1)?);
+            if !moved_out.contains(&table) {
+                continue;
+            }
+            let ddl: String = sqlx::query(&format!(
+                "SHOW CREATE TRIGGER {}.{}",
+                quote_ident(target_db_name),
+                quote_ident(&trigger)
+            ))
+            .fetch_one(&*self.target_pool)
+            .await?
+            .try_get(2)?;
+            triggers.push((trigger, table, ddl));
+        }
+        let plan = swap_plan(target_db_name, &moved_out, &staged, &triggers);
+        let mut conn = self.target_pool.acquire().await?;
+        execute(&mut conn, "SET FOREIGN_KEY_CHECKS = 0").await?;
+        let swapped = run_swap(&mut conn, &plan, target_db_name).await;
+        if execute(&mut conn, "SET FOREIGN_KEY_CHECKS = 1")
+            .await
+            .is_err()
+        {
+            conn.close_on_drop();
+        }
+        swapped?;
+        println!(
+            "[ok] {} 的表已换入 {}，原来的表保留在 {}",
+            staging, target_db_name, old
+        );
 
-        if !output.status.success() {
-            let decoded_stderr = decode_stderr(&output.stderr);
-            eprintln!("mysql restored failed: {}", decoded_stderr,);
+        // 重建视图（整库同步时），以及触发器、存储过程和事件
+        if tables.is_empty() && self.options.views {
+            let views = sqlx::query(
+                "SELECT TABLE_NAME FROM information_schema.VIEWS WHERE TABLE_SCHEMA = ?",
+            )
+            .bind(db_name)
+            .fetch_all(&*self.source_pool)
+            .await?
+            .iter()
+            .map(|row| row.try_get(0))
+            .collect::<Result<Vec<String>, _>>()?;
+            if !views.is_empty() {
+                let mut options = self.options.clone();
+                options.data = false;
+                options.triggers = false;
+                let path = self
+                    .dump_part(source, db_name, &views, &options, "views")
+                    .await?;
+                self.mysqldump_database_restore(&path, target, target_db_name)
+                    .await?;
+            }
+        }
+        let options = &self.options;
+        if options.triggers || (tables.is_empty() && (options.routines || options.events)) {
+            let mut options = options.clone();
+            options.schema = false;
+            options.data = false;
+            let path = self
+                .dump_part(source, db_name, tables, &options, "objects")
+                .await?;
+            self.mysqldump_database_restore(&path, target, target_db_name)
+                .await?;
+        }
+        Ok(())
+    }
+
+    // 库内并行：mysqldump 只导出表结构，表数据由 parallel 个连接在一致性快照中按表、按主键分块并行导入
+    // （load_data 时用 LOAD DATA LOCAL INFILE），触发器、存储过程和事件在数据导入后创建
+    async fn parallel_database(
+        &self,
+        source: &Source,
+        target: &Target,
+        db_name: &str,
+        tables: &[String],
+        options: &SyncOptions,
+        target_db_name: &str,
+    ) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+        // 全局读锁只在工作连接开始快照事务时短暂持有，open_snapshot 返回前已解锁
+        let mut connections =
+            open_snapshot(source, options.parallel, options.unlocked_snapshot).await?;
+        // 表定义和分块边界在快照连接上读取
+        let plan = plan_copy(
+            &self.source_pool,
+            source,
+            db_name,
+            tables,
+            options,
+            &mut connections[0],
+        )
+        .await?;
+        // 解锁后用 mysqldump --single-transaction 导出表结构，同步期间源库不应修改表结构
+        let schema_path = if options.schema {
+            let mut schema_options = options.clone();
+            schema_options.data = false;
+            schema_options.triggers = false;
+            schema_options.routines = false;
+            schema_options.events = false;
+            Some(
+                self.dump_part(source, db_name, tables, &schema_options, "schema")
+                    .await?,
+            )
         } else {
-            println!("[ok] Database restored from {}", backup_file_path);
+            None
+        };
+
+        if let Some(path) = schema_path {
+            self.mysqldump_database_restore(&path, target, target_db_name)
+                .await?;
+        } else if !options.merge {
+            self.truncate_target_tables(db_name, target_db_name, tables)
+                .await?;
+        }
+        // 导入数据前转换，按行写入的值为utf8mb4
+        self.convert_charset(target, target_db_name, tables).await?;
+        let rows =
+            parallel_copy(plan, connections, target, db_name, target_db_name, options).await?;
+        println!(
+            "[ok] {} -> {} 并行导入完成，{} 行",
+            db_name, target_db_name, rows
+        );
+        if options.triggers || (tables.is_empty() && (options.routines || options.events)) {
+            let mut object_options = options.clone();
+            object_options.schema = false;
+            object_options.data = false;
+            let path = self
+                .dump_part(source, db_name, tables, &object_options, "objects")
+                .await?;
+            self.mysqldump_database_restore(&path, target, target_db_name)
+                .await?;
         }
+        Ok(())
+    }
+
+    // 分几次导出时（swap、库内并行），文件名中带 part 区分
+    async fn dump_part(
+        &self,
+        source: &Source,
+        db_name: &str,
+        tables: &[String],
+        options: &SyncOptions,
+        part: &str,
+    ) -> Result<String, Box<dyn std::error::Error + Send + Sync>> {
+        let time_str = Local::now().format("%Y%m%d_%H%M%S").to_string();
+        let output_file_path = self.dump_path(&format!("backup_{}_{}_{}", db_name, part, time_str));
+        self.mysqldump_to_storage(source, db_name, tables, options, &output_file_path)
+            .await?;
+        Ok(output_file_path)
+    }
+
+    // 导出文件在存储中的路径，加密的文件扩展名为 .sql.enc
+    fn dump_path(&self, name: &str) -> String {
+        let extension = if self.key.is_some() { "sql.enc" } else { "sql" };
+        join_path(&self.dump_dir, &format!("{}.{}", name, extension))
+    }
+
+    // 还原数据库
+    pub async fn mysqldump_database_restore(
+        &self,
+        backup_file_path: &str,
+        target: &Target,
+        db_name: &str,
+    ) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+        // 校验sha256和结束标记，不完整的备份文件不还原；读取存储是阻塞调用
+        let help = self.clone();
+        let path = backup_file_path.to_string();
+        let reader = tokio::task::spawn_blocking(
+            move || -> Result<_, Box<dyn std::error::Error + Send + Sync>> {
+                let storage = &*help.storage;
+                verify_stored(storage, &path, MYSQLDUMP_COMPLETED, |r| help.decode(r))?;
+                let reader = storage.open(&path).map_err(|e| {
+                    format!(
+                        "Failed to open backup file {}: {}",
+                        storage.location(&path),
+                        e
+                    )
+                })?;
+                Ok(help.decode(reader)?)
+            },
+        )
+        .await??;
+        restore_database(
+            &self.target_pool,
+            reader,
+            &self.storage.location(backup_file_path),
+            target,
+            db_name,
+        )
+        .await
     }
 
     // 备份数据库
@@ -153,102 +749,690 @@ impl MysqlHelp {
         &self,
         source: &Source,
         db_name: &str,
+        options: &SyncOptions,
     ) -> Result<String, Box<dyn std::error::Error + Send + Sync>> {
-        // mysqldump
         // 构造备份文件路径
         let time_str = Local::now().format("%Y%m%d_%H%M%S").to_string();
-        let output_file_path = format!("sql/backup_{}_{}.sql", db_name, time_str);
+        let output_file_path = self.dump_path(&format!("backup_{}_{}", db_name, time_str));
+        self.mysqldump_to_storage(source, db_name, &[], options, &output_file_path)
+            .await?;
+        Ok(output_file_path)
+    }
+
+    // 备份数据表
+    pub async fn mysqldump_tables_backup(
+        &self,
+        source: &Source,
+        db_name: &str,
+        tables: &[String],
+        options: &SyncOptions,
+    ) -> Result<String, Box<dyn std::error::Error + Send + Sync>> {
+        let time_str = Local::now().format("%Y%m%d_%H%M%S").to_string();
+        let output_file_path = self.dump_path(&format!(
+            "backup_{}_{}_{}",
+            db_name,
+            tables.join("_"),
+            time_str
+        ));
+        self.mysqldump_to_storage(source, db_name, tables, options, &output_file_path)
+            .await?;
+        Ok(output_file_path)
+    }
+
+    // 执行mysqldump，将指定数据库（tables不为空时只导出这些表）流式写入存储
+    // mysqldump 和存储写入（S3、SFTP为网络I/O）是阻塞调用，在 spawn_blocking 中执行
+    async fn mysqldump_to_storage(
+        &self,
+        source: &Source,
+        db_name: &str,
+        tables: &[String],
+        options: &SyncOptions,
+        output_file_path: &str,
+    ) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+        let help = self.clone();
+        let (source, db_name, tables, options, path) = (
+            source.clone(),
+            db_name.to_string(),
+            tables.to_vec(),
+            options.clone(),
+            output_file_path.to_string(),
+        );
+        tokio::task::spawn_blocking(move || {
+            help.write_dump(&source, &db_name, &tables, &options, &path)
+        })
+        .await?
+    }
 
-        if let Some(parent) = Path::new(&output_file_path).parent() {
-            fs::create_dir_all(parent).expect("Failed to create directory");
+    fn write_dump(
+        &self,
+        source: &Source,
+        db_name: &str,
+        tables: &[String],
+        options: &SyncOptions,
+        output_file_path: &str,
+    ) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+        // 边写边计算（加密后的）sha256，写入旁路文件，还原前校验
+        let output_file = HashingWriter::new(self.storage.create(output_file_path)?);
+        let output_file = match &self.key {
+            Some(key) => {
+                let mut writer = ProgressWriter::new(
+                    EncryptWriter::new(output_file, &key.0)?,
+                    progress::find(db_name),
+                );
+                mysqldump_to_writer(source, db_name, tables, options, &mut writer)?;
+                writer.into_inner().finish()?
+            }
+            None => {
+                let mut writer = ProgressWriter::new(output_file, progress::find(db_name));
+                mysqldump_to_writer(source, db_name, tables, options, &mut writer)?;
+                writer.into_inner()
+            }
+        };
+        let (output_file, _, sha256) = output_file.into_parts();
+        output_file.finish()?;
+        write_stored_sidecar(&*self.storage, output_file_path, &sha256)?;
+        println!(
+            "[ok] Database {} dumped to {}",
+            db_name,
+            self.storage.location(output_file_path)
+        );
+        Ok(())
+    }
+}
+
+// 关闭外键检查执行 sql，执行后恢复；出错时同样在这个连接上恢复，恢复失败则关闭连接，不放回连接池
+pub async fn execute_without_fk_checks(
+    pool: &sqlx::Pool<sqlx::MySql>,
+    sql: &str,
+) -> Result<(), sqlx::Error> {
+    let mut conn = pool.acquire().await?;
+    let result = execute(&mut conn, &format!("SET FOREIGN_KEY_CHECKS = 0; {}", sql)).await;
+    if execute(&mut conn, "SET FOREIGN_KEY_CHECKS = 1")
+        .await
+        .is_err()
+    {
+        conn.close_on_drop();
+    }
+    result
+}
+
+// 查询数据库版本
+pub async fn query_mysql_version(pool: &sqlx::Pool<sqlx::MySql>) -> Result<String, sqlx::Error> {
+    let row: (String,) = sqlx::query_as("SELECT VERSION()").fetch_one(pool).await?;
+    Ok(row.0)
+}
+
+// 查询所有数据库
+pub async fn query_all_databases(
+    pool: &sqlx::Pool<sqlx::MySql>,
+) -> Result<Vec<String>, sqlx::Error> {
+    let rows = sqlx::query("SHOW DATABASES").fetch_all(pool).await?;
+    let mut databases = Vec::new();
+    for row in rows {
+        let db_name: String = row.get("Database");
+        databases.push(db_name);
+    }
+    Ok(databases)
+}
+
+// 查询服务器标识，用于判断源库和目标库是否为同一个服务器（MariaDB 没有 server_uuid）
+pub async fn query_server_id(pool: &sqlx::Pool<sqlx::MySql>) -> Result<String, sqlx::Error> {
+    if let Ok(row) = sqlx::query_as::<_, (String,)>("SELECT @@server_uuid")
+        .fetch_one(pool)
+        .await
+    {
+        return Ok(row.0);
+    }
+    let row: (String,) = sqlx::query_as("SELECT CAST(CONCAT(@@hostname, ':', @@port) AS CHAR)")
+        .fetch_one(pool)
+        .await?;
+    Ok(row.0)
+}
+
+// 查询库中的表和视图，库不存在时为空
+pub(crate) async fn query_tables(
+    pool: &sqlx::Pool<sqlx::MySql>,
+    db_name: &str,
+) -> Result<Vec<String>, sqlx::Error> {
+    let rows =
+        sqlx::query("SELECT TABLE_NAME FROM information_schema.TABLES WHERE TABLE_SCHEMA = ?")
+            .bind(db_name)
+            .fetch_all(pool)
+            .await?;
+    rows.iter().map(|row| row.try_get(0)).collect()
+}
+
+// 源库中表的估计大小（information_schema.TABLES，InnoDB 的行数是估计值），tables 为空时为所有表
+pub async fn query_estimate(
+    pool: &sqlx::Pool<sqlx::MySql>,
+    db_name: &str,
+    tables: &[String],
+    ignore_tables: &[String],
+) -> Result<Estimate, sqlx::Error> {
+    let rows = sqlx::query(
+        "SELECT TABLE_NAME, CAST(IFNULL(TABLE_ROWS, 0) AS UNSIGNED), \
+         CAST(IFNULL(DATA_LENGTH, 0) AS UNSIGNED) FROM information_schema.TABLES \
+         WHERE TABLE_SCHEMA = ? AND TABLE_TYPE = 'BASE TABLE'",
+    )
+    .bind(db_name)
+    .fetch_all(pool)
+    .await?;
+    let mut estimate = Estimate::default();
+    for row in rows {
+        let table: String = row.try_get(0)?;
+        if (!tables.is_empty() && !tables.contains(&table)) || ignore_tables.contains(&table) {
+            continue;
         }
+        estimate.tables += 1;
+        estimate.rows += row.try_get::<u64, _>(1)?;
+        estimate.bytes += row.try_get::<u64, _>(2)?;
+    }
+    Ok(estimate)
+}
 
-        let mut output_file = File::create(&output_file_path).expect("Failed to create file");
+// 查询库中的表（不包括视图）
+pub async fn query_base_tables(
+    pool: &sqlx::Pool<sqlx::MySql>,
+    db_name: &str,
+) -> Result<Vec<String>, sqlx::Error> {
+    let rows = sqlx::query(
+        "SELECT TABLE_NAME FROM information_schema.TABLES \
+         WHERE TABLE_SCHEMA = ? AND TABLE_TYPE = 'BASE TABLE' ORDER BY TABLE_NAME",
+    )
+    .bind(db_name)
+    .fetch_all(pool)
+    .await?;
+    rows.iter().map(|row| row.try_get(0)).collect()
+}
 
-        // 执行mysqldump命令
-        let output = Command::new("mysqldump")
-            .arg(format!("--user={}", source.user))
-            .arg(format!("--password={}", source.password))
-            .arg(format!("--host={}", source.host))
-            .arg(format!("--port={}", source.port))
-            .arg("--compression-algorithms=zlib") // 压缩输出
-            .arg("--single-transaction") // 一致性事务快照
-            .arg("--set-gtid-purged=OFF")
-            .arg("--triggers") // 备份触发器
-            .arg("--routines") // 备份存储过程和函数
-            .arg("--events") // 备份事件
-            .arg(db_name)
-            .output()?;
+// data_only 模式清空目标库中已存在的表，返回 (TRUNCATE 语句, 目标库中没有的表)
+fn truncate_statements<'a>(
+    target_db_name: &str,
+    tables: &'a [String],
+    existing: &[String],
+) -> (Vec<String>, Vec<&'a String>) {
+    let (present, missing): (Vec<_>, Vec<_>) =
+        tables.iter().partition(|table| existing.contains(table));
+    let statements = present
+        .into_iter()
+        .map(|table| {
+            format!(
+                "TRUNCATE TABLE {}.{}",
+                quote_ident(target_db_name),
+                quote_ident(table)
+            )
+        })
+        .collect();
+    (statements, missing)
+}
+
+// swap 换入的语句，按执行顺序分为几步（DDL 自动提交，不能用事务回滚）
+#[derive(Debug, PartialEq)]
+struct SwapPlan {
+    // 换入前：创建空的 {库名}_datasync_old_next，删除换出的表上的触发器（有触发器的表不能移到其它库）
+    prepare: Vec<String>,
+    // 一条 RENAME TABLE：目标库的表移到 old_next，staging 库的表移到目标库
+    rename: String,
+    // prepare 或 rename 失败时：在目标库中重建删除的触发器，删除 old_next，原来的 _datasync_old 不变
+    rollback: Vec<String>,
+    // 换入成功后：old_next 的表替换 _datasync_old，删除 staging 和 old_next
+    finish: Vec<String>,
+    // 在 _datasync_old 中重建换出的表的触发器，失败时只给出警告
+    old_triggers: Vec<String>,
+}
 
-        // 写入到文件
-        output_file.write_all(&output.stdout)?;
+// 目标库中的 moved_out 表移到 {库名}_datasync_old，staging 库的 staged 表移到目标库
+// triggers 为换出的表上的 (触发器, 表, SHOW CREATE TRIGGER 的建触发器语句)
+fn swap_plan(
+    target_db_name: &str,
+    moved_out: &[String],
+    staged: &[String],
+    triggers: &[(String, String, String)],
+) -> SwapPlan {
+    let staging = format!("{}_datasync_staging", target_db_name);
+    let old = format!("{}_datasync_old", target_db_name);
+    let next = format!("{}_datasync_old_next", target_db_name);
+    let triggers = triggers
+        .iter()
+        .filter(|(_, table, _)| moved_out.contains(table))
+        .collect::<Vec<_>>();
+    let rename = |from: &str, to: &str, table: &str| {
+        format!(
+            "{}.{} TO {}.{}",
+            quote_ident(from),
+            quote_ident(table),
+            quote_ident(to),
+            quote_ident(table)
+        )
+    };
+    // 建触发器语句中的表名不带库名，先切换到触发器所在的库
+    let create_triggers = |db: &str| {
+        let mut statements = vec![format!("USE {}", quote_ident(db))];
+        statements.extend(triggers.iter().map(|(_, _, ddl)| ddl.clone()));
+        statements
+    };
 
-        if !output.status.success() {
+    let mut prepare = vec![
+        format!("DROP DATABASE IF EXISTS {}", quote_ident(&next)),
+        format!("CREATE DATABASE {}", quote_ident(&next)),
+    ];
+    prepare.extend(triggers.iter().map(|(trigger, _, _)| {
+        format!(
+            "DROP TRIGGER IF EXISTS {}.{}",
+            quote_ident(target_db_name),
+            quote_ident(trigger)
+        )
+    }));
+    let renames = moved_out
+        .iter()
+        .map(|t| rename(target_db_name, &next, t))
+        .chain(staged.iter().map(|t| rename(&staging, target_db_name, t)))
+        .collect::<Vec<_>>();
+
+    let mut rollback = if triggers.is_empty() {
+        Vec::new()
+    } else {
+        create_triggers(target_db_name)
+    };
+    rollback.push(format!("DROP DATABASE IF EXISTS {}", quote_ident(&next)));
+
+    let mut finish = vec![
+        format!("DROP DATABASE IF EXISTS {}", quote_ident(&old)),
+        format!("CREATE DATABASE {}", quote_ident(&old)),
+    ];
+    if !moved_out.is_empty() {
+        let renames = moved_out
+            .iter()
+            .map(|t| rename(&next, &old, t))
+            .collect::<Vec<_>>();
+        finish.push(format!("RENAME TABLE {}", renames.join(", ")));
+    }
+    finish.push(format!("DROP DATABASE {}", quote_ident(&next)));
+    finish.push(format!("DROP DATABASE {}", quote_ident(&staging)));
+
+    SwapPlan {
+        prepare,
+        rename: format!("RENAME TABLE {}", renames.join(", ")),
+        rollback,
+        finish,
+        old_triggers: if triggers.is_empty() {
+            Vec::new()
+        } else {
+            create_triggers(&old)
+        },
+    }
+}
+
+// 执行 swap：rename 失败时按 rollback 恢复，目标库和原来的 _datasync_old 不变
+async fn run_swap(
+    conn: &mut sqlx::MySqlConnection,
+    plan: &SwapPlan,
+    target_db_name: &str,
+) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+    for sql in plan.prepare.iter().chain(std::iter::once(&plan.rename)) {
+        if let Err(e) = execute(conn, sql).await {
+            for sql in &plan.rollback {
+                if let Err(e) = execute(conn, sql).await {
+                    eprintln!("[warn] swap 恢复失败 {}: {}", sql, e);
+                }
+            }
+            return Err(format!("换入失败，目标库 {} 没有修改: {}", target_db_name, e).into());
+        }
+    }
+    for sql in &plan.finish {
+        execute(conn, sql).await.map_err(|e| {
+            format!(
+                "已换入 {}，但整理 {}_datasync_old 失败（原来的表在 {}_datasync_old_next 中）: {}",
+                target_db_name, target_db_name, target_db_name, e
+            )
+        })?;
+    }
+    for sql in &plan.old_triggers {
+        if let Err(e) = execute(conn, sql).await {
             eprintln!(
-                "mysqldump failed: {}",
-                String::from_utf8_lossy(&output.stderr)
+                "[warn] {}_datasync_old 中的触发器没有重建 {}: {}",
+                target_db_name, sql, e
             );
-            return Err("mysqldump failed".into());
-        } else {
-            println!("[ok] Database {} dumped to {}", db_name, output_file_path);
         }
+    }
+    Ok(())
+}
+
+// 执行mysqldump，输出流式写入writer，返回写入的字节数
+// 配置了过滤条件（source.filters）的表单独执行一次mysqldump --where，先于其它表写入
+pub fn mysqldump_to_writer(
+    source: &Source,
+    db_name: &str,
+    tables: &[String],
+    options: &SyncOptions,
+    writer: &mut dyn Write,
+) -> Result<u64, Box<dyn std::error::Error + Send + Sync>> {
+    let filters = source
+        .table_filters(db_name)
+        .into_iter()
+        .filter(|(table, _)| tables.is_empty() || tables.iter().any(|t| t == table))
+        .collect::<Vec<_>>();
+    let mut size = 0;
+    for (table, where_clause) in &filters {
+        println!("[filter] {}.{} WHERE {}", db_name, table, where_clause);
+        size += run_mysqldump(
+            source,
+            db_name,
+            &[table.to_string()],
+            Some(where_clause),
+            &[],
+            options,
+            writer,
+        )?;
+    }
+    if filters.is_empty() {
+        return run_mysqldump(source, db_name, tables, None, &[], options, writer);
+    }
+
+    let filtered = filters
+        .iter()
+        .map(|(t, _)| t.to_string())
+        .collect::<Vec<_>>();
+    if tables.is_empty() {
+        // 整库：跳过已导出的表，存储过程、事件、视图在这里导出
+        size += run_mysqldump(source, db_name, &[], None, &filtered, options, writer)?;
+    } else {
+        let rest = tables
+            .iter()
+            .filter(|t| !filtered.contains(t))
+            .cloned()
+            .collect::<Vec<_>>();
+        if !rest.is_empty() {
+            size += run_mysqldump(source, db_name, &rest, None, &[], options, writer)?;
+        }
+    }
+    Ok(size)
+}
+
+fn run_mysqldump(
+    source: &Source,
+    db_name: &str,
+    tables: &[String],
+    where_clause: Option<&str>,
+    ignore_tables: &[String],
+    options: &SyncOptions,
+    writer: &mut dyn Write,
+) -> Result<u64, Box<dyn std::error::Error + Send + Sync>> {
+    // 执行mysqldump命令
+    let mut command = Command::new("mysqldump");
+    command
+        .arg(format!("--user={}", source.user))
+        .arg(format!("--password={}", source.password))
+        .arg(format!("--host={}", source.host))
+        .arg(format!("--port={}", source.port))
+        .arg("--single-transaction") // 一致性事务快照
+        .arg("--default-character-set=utf8mb4") // 与还原时一致，4字节字符（emoji）不丢失
+        .args(dump_flags(mysqldump_version(), options.source_version)); // 压缩传输、GTID等按版本选择
+    if !options.schema {
+        // 只导出数据，写入目标库已存在的表，列顺序可能不同
+        command.arg("--no-create-info").arg("--complete-insert");
+    }
+    if !options.data {
+        command.arg("--no-data");
+    }
+    if options.merge {
+        command.arg("--replace"); // 主键/唯一键相同的行覆盖
+    }
+    // 备份触发器
+    command.arg(if options.triggers {
+        "--triggers"
+    } else {
+        "--skip-triggers"
+    });
+    if tables.is_empty() {
+        if options.routines {
+            command.arg("--routines"); // 备份存储过程和函数
+        }
+        if options.events {
+            command.arg("--events"); // 备份事件
+        }
+    }
+    if let Some(where_clause) = where_clause {
+        command.arg(format!("--where={}", where_clause));
+    }
+    for table in ignore_tables.iter().chain(&options.ignore_tables) {
+        command.arg(format!("--ignore-table={}.{}", db_name, table));
+    }
+    let mut child = command
+        .arg(db_name)
+        .args(tables)
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()?;
+
+    // 单独的线程读取错误输出，避免管道写满阻塞mysqldump
+    let mut stderr = child.stderr.take().unwrap();
+    let stderr_reader = std::thread::spawn(move || {
+        let mut buf = Vec::new();
+        let _ = stderr.read_to_end(&mut buf);
+        buf
+    });
+
+    // 写入到writer
+    let mut stdout = child.stdout.take().unwrap();
+    let mut writer = TailWriter::new(writer);
+    let copy_result = io::copy(&mut stdout, &mut writer);
+    let status = child.wait()?;
+    let stderr = stderr_reader.join().unwrap_or_default();
+    let size = copy_result?;
+
+    if !status.success() {
+        eprintln!("mysqldump failed: {}", decode_stderr(&stderr));
+        return Err("mysqldump failed".into());
+    }
+    if !writer.dump_completed(MYSQLDUMP_COMPLETED) {
+        return Err(format!("mysqldump output of {} is incomplete", db_name).into());
+    }
+    Ok(size)
+}
+
+// 还原数据库：目标库不存在则创建，然后执行mysql命令导入备份文件
+// reader 为备份数据流，label 用于日志输出
+pub async fn restore_database(
+    target_pool: &sqlx::Pool<sqlx::MySql>,
+    reader: Box<dyn Read + Send>,
+    label: &str,
+    target: &Target,
+    db_name: &str,
+) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+    // 判断数据库是否存在，不存在则创建
+    let db_name = db_name.trim();
+    let db_exists =
+        sqlx::query("SELECT SCHEMA_NAME FROM information_schema.SCHEMATA WHERE SCHEMA_NAME = ?")
+            .bind(db_name)
+            .fetch_optional(target_pool)
+            .await?;
+
+    if db_exists.is_none() {
+        // 配置了 [target] charset 时新建的库使用该字符集
+        let charset = match TargetCharset::from_target(target) {
+            Some(TargetCharset {
+                charset,
+                collation: Some(collation),
+            }) => format!(" CHARACTER SET {} COLLATE {}", charset, collation),
+            Some(TargetCharset { charset, .. }) => format!(" CHARACTER SET {}", charset),
+            None => String::new(),
+        };
+        sqlx::query(format!("CREATE DATABASE IF NOT EXISTS `{}`{}", db_name, charset).as_str())
+            .execute(target_pool)
+            .await?;
+        println!("Database {} created", db_name);
+    } else {
+        println!("Database {} already exists", db_name);
+    }
+
+    // 同步时统计还原的字节数和表数
+    let reader: Box<dyn Read + Send> =
+        Box::new(ProgressReader::new(reader, progress::find(db_name)));
+
+    // 改写目标库版本不支持的排序规则，按 [target] definer 去掉 DEFINER
+    let rewrite = ddl_rewrite(target_pool, target).await?;
+    let reader: Box<dyn Read + Send> = if rewrite.is_empty() {
+        reader
+    } else {
+        Box::new(RewriteReader::new(reader, rewrite))
+    };
+
+    // 执行mysql命令，还原数据库；从存储读取并写入 mysql 是阻塞调用
+    let (target, db) = (target.clone(), db_name.to_string());
+    let output = tokio::task::spawn_blocking(move || {
+        execute_mysql_restore(
+            reader,
+            &target.host,
+            &target.port,
+            &target.user,
+            &target.password,
+            &db,
+        )
+    })
+    .await??;
 
-        return Ok(output_file_path);
+    if !output.status.success() {
+        let decoded_stderr = decode_stderr(&output.stderr);
+        return Err(format!("mysql restored failed: {}", decoded_stderr).into());
     }
+    println!("[ok] Database restored from {}", label);
+    Ok(())
 }
 
-#[cfg(target_family = "unix")]
-fn execute_mysql_restore<P: AsRef<Path>>(
-    backup_file: P,
+fn execute_mysql_restore(
+    mut reader: Box<dyn Read + Send>,
     host: &str,
     port: &str,
     user: &str,
     password: &str,
     db_name: &str,
 ) -> std::io::Result<std::process::Output> {
-    Command::new("mysql")
-        .arg("--default-character-set=utf8")
+    let mut child = Command::new("mysql")
+        .arg("--default-character-set=utf8mb4")
         .arg(format!("-h{}", host))
         .arg(format!("-P{}", port))
         .arg(format!("-u{}", user))
         .arg(format!("-p{}", password))
         .arg(db_name)
-        .arg("<")
-        .arg(backup_file.as_ref())
-        .output()
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()?;
+
+    // 单独的线程写入标准输入，写完后关闭管道
+    let mut stdin = child.stdin.take().unwrap();
+    let writer = std::thread::spawn(move || io::copy(&mut reader, &mut stdin).map(|_| ()));
+    let output = child.wait_with_output()?;
+    match writer.join() {
+        Ok(Ok(())) => Ok(output),
+        // mysql提前退出时写入会失败，以mysql的错误输出为准
+        Ok(Err(_)) if !output.status.success() => Ok(output),
+        Ok(Err(e)) => Err(e),
+        Err(_) => Err(io::Error::other("restore writer thread panicked")),
+    }
 }
 
-#[cfg(target_family = "windows")]
-fn execute_mysql_restore<P: AsRef<Path>>(
-    backup_file: P,
-    host: &str,
-    port: &str,
-    user: &str,
-    password: &str,
-    db_name: &str,
-) -> std::io::Result<std::process::Output> {
-    let cmd_str = format!(
-        "mysql --default-character-set=utf8 -h{} -P{} -u{} -p{} {} < {}",
-        host,
-        port,
-        user,
-        password,
-        db_name,
-        backup_file.as_ref().to_str().unwrap()
-    );
-    // println!("Executing command: {}", cmd_str);
-
-    Command::new("cmd")
-        .arg("/c")
-        .arg(format!("{}", cmd_str))
-        .output()
-}
-
-// 解码错误的输出
-// 解决中文乱码问题（windows下）
+// 解码错误的输出：有效的UTF-8直接使用，否则按GBK解码（中文Windows下客户端的输出为GBK）
 pub fn decode_stderr(data: &[u8]) -> String {
-    let encoding = encoding_from_whatwg_label("gbk").unwrap_or_else(|| encoding::all::UTF_8);
+    if let Ok(text) = std::str::from_utf8(data) {
+        return text.to_string();
+    }
+    let encoding = encoding_from_whatwg_label("gbk").unwrap_or(encoding::all::UTF_8);
     encoding
-        .decode(data, encoding::DecoderTrap::Replace)
+        .decode(data, encoding::DecoderTrap::Strict)
         .unwrap_or_else(|_| String::from_utf8_lossy(data).to_string())
 }
+
+#[cfg(test)]
+mod test_help {
+    use super::*;
+
+    #[test]
+    fn test_swap_plan() {
+        let strings = |items: &[&str]| items.iter().map(|s| s.to_string()).collect::<Vec<_>>();
+        let triggers = vec![
+            (
+                "trg_order".to_string(),
+                "order".to_string(),
+                "CREATE TRIGGER trg_order BEFORE INSERT ON `order` FOR EACH ROW SET NEW.n = 1"
+                    .to_string(),
+            ),
+            (
+                "trg_log".to_string(),
+                "log".to_string(),
+                "CREATE TRIGGER trg_log BEFORE INSERT ON `log` FOR EACH ROW SET NEW.n = 1"
+                    .to_string(),
+            ),
+        ];
+        let plan = swap_plan(
+            "canteen",
+            &strings(&["order", "user"]),
+            &strings(&["order", "user", "menu"]),
+            &triggers,
+        );
+        // 换入前不删除原来的 _datasync_old，只删除换出的表上的触发器
+        assert_eq!(
+            plan.prepare,
+            vec![
+                "DROP DATABASE IF EXISTS `canteen_datasync_old_next`",
+                "CREATE DATABASE `canteen_datasync_old_next`",
+                "DROP TRIGGER IF EXISTS `canteen`.`trg_order`",
+            ]
+        );
+        assert_eq!(
+            plan.rename,
+            "RENAME TABLE `canteen`.`order` TO `canteen_datasync_old_next`.`order`, \
+             `canteen`.`user` TO `canteen_datasync_old_next`.`user`, \
+             `canteen_datasync_staging`.`order` TO `canteen`.`order`, \
+             `canteen_datasync_staging`.`user` TO `canteen`.`user`, \
+             `canteen_datasync_staging`.`menu` TO `canteen`.`menu`"
+        );
+        // 换入失败时重建目标库的触发器，不修改 _datasync_old
+        assert_eq!(
+            plan.rollback,
+            vec![
+                "USE `canteen`",
+                "CREATE TRIGGER trg_order BEFORE INSERT ON `order` FOR EACH ROW SET NEW.n = 1",
+                "DROP DATABASE IF EXISTS `canteen_datasync_old_next`",
+            ]
+        );
+        assert!(
+            plan.prepare
+                .iter()
+                .chain(&plan.rollback)
+                .all(|sql| !sql.contains("`canteen_datasync_old`"))
+        );
+        // 换入成功后才替换 _datasync_old
+        assert_eq!(
+            plan.finish,
+            vec![
+                "DROP DATABASE IF EXISTS `canteen_datasync_old`",
+                "CREATE DATABASE `canteen_datasync_old`",
+                "RENAME TABLE `canteen_datasync_old_next`.`order` TO `canteen_datasync_old`.`order`, \
+                 `canteen_datasync_old_next`.`user` TO `canteen_datasync_old`.`user`",
+                "DROP DATABASE `canteen_datasync_old_next`",
+                "DROP DATABASE `canteen_datasync_staging`",
+            ]
+        );
+        assert_eq!(
+            plan.old_triggers,
+            vec![
+                "USE `canteen_datasync_old`",
+                "CREATE TRIGGER trg_order BEFORE INSERT ON `order` FOR EACH ROW SET NEW.n = 1",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_truncate_statements() {
+        let tables = vec!["order".to_string(), "menu".to_string()];
+        let existing = vec!["order".to_string(), "user".to_string()];
+        let (statements, missing) = truncate_statements("canteen", &tables, &existing);
+        assert_eq!(statements, vec!["TRUNCATE TABLE `canteen`.`order`"]);
+        assert_eq!(missing, vec!["menu"]);
+        // 目标库中没有同步的表时不执行语句
+        let (statements, _) = truncate_statements("canteen", &tables, &[]);
+        assert!(statements.is_empty());
+    }
+}
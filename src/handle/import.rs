@@ -0,0 +1,1060 @@
+// import 任务：把 CSV / JSON Lines / Parquet 文件导入目标库的一个表（export 的反向操作）
+// 文件字段按 [import.columns] 映射到表的列，按列类型转换，无法转换或写入失败的行写入隔离文件
+
+use std::{
+    collections::{BTreeMap, VecDeque},
+    fs::{self, File},
+    io::{BufRead, BufReader, BufWriter, Write},
+    path::{Path, PathBuf},
+};
+
+use arrow_cast::display::{ArrayFormatter, FormatOptions};
+use chrono::{DateTime, Local, NaiveDate, NaiveDateTime};
+use encoding::{DecoderTrap, EncodingRef, label::encoding_from_whatwg_label};
+use parquet::arrow::arrow_reader::{ParquetRecordBatchReader, ParquetRecordBatchReaderBuilder};
+use sqlx::{MySql, Pool};
+
+use crate::{
+    handle::{
+        mysql_schema::{quote_ident, read_tables},
+        sync::BoxError,
+        type_map::mysql_row_values,
+    },
+    model::{
+        job::{Import, Target},
+        schema::{ColumnSchema, RowValues, TableSchema, ValueKind},
+    },
+};
+
+// 每条INSERT语句的行数
+const INSERT_BATCH_SIZE: usize = 500;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum ImportFormat {
+    Csv,
+    Jsonl,
+    Parquet,
+}
+
+impl ImportFormat {
+    fn parse(format: &str) -> Result<Self, String> {
+        match format {
+            "csv" | "tsv" => Ok(ImportFormat::Csv),
+            "jsonl" | "ndjson" => Ok(ImportFormat::Jsonl),
+            "parquet" => Ok(ImportFormat::Parquet),
+            other => Err(format!("不支持的导入格式 {}", other)),
+        }
+    }
+
+    fn matches(&self, path: &Path) -> bool {
+        let extension = path.extension().and_then(|e| e.to_str()).unwrap_or("");
+        ImportFormat::parse(&extension.to_lowercase()).is_ok_and(|f| f == *self)
+    }
+}
+
+// 文件中的一行：行号和按文件字段顺序的值，None 为 NULL
+#[derive(Debug, Clone)]
+struct Record {
+    line: u64,
+    values: Vec<Option<String>>,
+}
+
+// 无法解析或导入的行
+struct Rejected {
+    line: u64,
+    error: String,
+    record: serde_json::Value,
+}
+
+trait RecordReader {
+    // 文件字段名
+    fn columns(&self) -> &[String];
+
+    fn next_record(&mut self) -> Option<Result<Record, Rejected>>;
+}
+
+struct CsvRecords {
+    reader: csv::Reader<File>,
+    columns: Vec<String>,
+    null: String,
+    encoding: Option<EncodingRef>,
+}
+
+impl CsvRecords {
+    fn open(path: &Path, import: &Import) -> Result<Self, BoxError> {
+        let tsv = path.extension().is_some_and(|e| e == "tsv");
+        let delimiter = match import.delimiter.as_deref() {
+            Some(delimiter) => match delimiter.as_bytes() {
+                [b] => *b,
+                _ => return Err("delimiter 需要是一个ASCII字符".into()),
+            },
+            None if tsv => b'\t',
+            None => b',',
+        };
+        let header = import.header.unwrap_or(true);
+        let encoding = match import.encoding.as_deref() {
+            None => None,
+            Some(label) => {
+                let encoding = encoding_from_whatwg_label(label)
+                    .ok_or_else(|| format!("不支持的编码 {}", label))?;
+                (encoding.whatwg_name() != Some("utf-8")).then_some(encoding)
+            }
+        };
+        let mut reader = csv::ReaderBuilder::new()
+            .delimiter(delimiter)
+            .has_headers(header)
+            .flexible(true)
+            .from_path(path)?;
+        // 没有表头时 byte_headers 返回第一行（不会跳过），字段名为序号
+        let first = reader.byte_headers()?.clone();
+        let mut records = CsvRecords {
+            reader,
+            columns: Vec::new(),
+            null: import.null.clone().unwrap_or_default(),
+            encoding,
+        };
+        records.columns = if header {
+            first
+                .iter()
+                .enumerate()
+                .map(|(i, name)| {
+                    let name = records.decode(name).unwrap_or_default();
+                    // Excel 导出的UTF-8文件带BOM
+                    let name = if i == 0 {
+                        name.trim_start_matches('\u{feff}').to_string()
+                    } else {
+                        name
+                    };
+                    name.trim().to_string()
+                })
+                .collect()
+        } else {
+            (1..=first.len()).map(|i| i.to_string()).collect()
+        };
+        Ok(records)
+    }
+
+    fn decode(&self, value: &[u8]) -> Result<String, String> {
+        match self.encoding {
+            Some(encoding) => encoding
+                .decode(value, DecoderTrap::Strict)
+                .map_err(|e| format!("编码错误: {}", e)),
+            None => String::from_utf8(value.to_vec()).map_err(|_| "不是有效的UTF-8".to_string()),
+        }
+    }
+}
+
+impl RecordReader for CsvRecords {
+    fn columns(&self) -> &[String] {
+        &self.columns
+    }
+
+    fn next_record(&mut self) -> Option<Result<Record, Rejected>> {
+        let mut record = csv::ByteRecord::new();
+        let line = self.reader.position().line();
+        match self.reader.read_byte_record(&mut record) {
+            Ok(false) => None,
+            Ok(true) => {
+                let line = record.position().map(|p| p.line()).unwrap_or(line);
+                let raw = || {
+                    serde_json::Value::from(
+                        record
+                            .iter()
+                            .map(|v| String::from_utf8_lossy(v).into_owned())
+                            .collect::<Vec<_>>(),
+                    )
+                };
+                let mut values = Vec::with_capacity(record.len());
+                for value in record.iter() {
+                    match self.decode(value) {
+                        Ok(value) if value == self.null => values.push(None),
+                        Ok(value) => values.push(Some(value)),
+                        Err(error) => {
+                            return Some(Err(Rejected {
+                                line,
+                                error,
+                                record: raw(),
+                            }));
+                        }
+                    }
+                }
+                Some(Ok(Record { line, values }))
+            }
+            Err(e) => Some(Err(Rejected {
+                line,
+                error: e.to_string(),
+                record: serde_json::Value::Null,
+            })),
+        }
+    }
+}
+
+// JSON Lines：每行一个对象，字段以第一行为准，之后的行缺少的字段为 NULL
+struct JsonlRecords {
+    lines: std::io::Lines<BufReader<File>>,
+    columns: Vec<String>,
+    line: u64,
+    first: Option<Result<Record, Rejected>>,
+}
+
+impl JsonlRecords {
+    fn open(path: &Path) -> Result<Self, BoxError> {
+        let mut records = JsonlRecords {
+            lines: BufReader::new(File::open(path)?).lines(),
+            columns: Vec::new(),
+            line: 0,
+            first: None,
+        };
+        // 读取第一个对象确定字段
+        while let Some(line) = records.lines.next() {
+            records.line += 1;
+            let line = line?;
+            if line.trim().is_empty() {
+                continue;
+            }
+            if let Ok(serde_json::Value::Object(object)) = serde_json::from_str(&line) {
+                records.columns = object.keys().cloned().collect();
+            }
+            records.first = Some(records.parse(&line));
+            break;
+        }
+        Ok(records)
+    }
+
+    fn parse(&self, line: &str) -> Result<Record, Rejected> {
+        let object = match serde_json::from_str(line) {
+            Ok(serde_json::Value::Object(object)) => object,
+            Ok(_) => {
+                return Err(Rejected {
+                    line: self.line,
+                    error: "不是JSON对象".to_string(),
+                    record: serde_json::Value::from(line),
+                });
+            }
+            Err(e) => {
+                return Err(Rejected {
+                    line: self.line,
+                    error: e.to_string(),
+                    record: serde_json::Value::from(line),
+                });
+            }
+        };
+        let values = self
+            .columns
+            .iter()
+            .map(|name| match object.get(name) {
+                None | Some(serde_json::Value::Null) => None,
+                Some(serde_json::Value::String(s)) => Some(s.clone()),
+                Some(value) => Some(value.to_string()),
+            })
+            .collect();
+        Ok(Record {
+            line: self.line,
+            values,
+        })
+    }
+}
+
+impl RecordReader for JsonlRecords {
+    fn columns(&self) -> &[String] {
+        &self.columns
+    }
+
+    fn next_record(&mut self) -> Option<Result<Record, Rejected>> {
+        if let Some(first) = self.first.take() {
+            return Some(first);
+        }
+        loop {
+            let line = self.lines.next()?;
+            self.line += 1;
+            return Some(match line {
+                Ok(line) if line.trim().is_empty() => continue,
+                Ok(line) => self.parse(&line),
+                Err(e) => Err(Rejected {
+                    line: self.line,
+                    error: e.to_string(),
+                    record: serde_json::Value::Null,
+                }),
+            });
+        }
+    }
+}
+
+// Parquet：值按 arrow 的文本格式读取（二进制为十六进制，时间为 ISO 8601）
+struct ParquetRecords {
+    reader: ParquetRecordBatchReader,
+    columns: Vec<String>,
+    rows: VecDeque<Record>,
+    line: u64,
+}
+
+impl ParquetRecords {
+    fn open(path: &Path) -> Result<Self, BoxError> {
+        let builder = ParquetRecordBatchReaderBuilder::try_new(File::open(path)?)?;
+        let columns = builder
+            .schema()
+            .fields()
+            .iter()
+            .map(|f| f.name().clone())
+            .collect();
+        Ok(ParquetRecords {
+            reader: builder.with_batch_size(INSERT_BATCH_SIZE).build()?,
+            columns,
+            rows: VecDeque::new(),
+            line: 0,
+        })
+    }
+}
+
+impl RecordReader for ParquetRecords {
+    fn columns(&self) -> &[String] {
+        &self.columns
+    }
+
+    fn next_record(&mut self) -> Option<Result<Record, Rejected>> {
+        while self.rows.is_empty() {
+            let batch = match self.reader.next()? {
+                Ok(batch) => batch,
+                Err(e) => {
+                    return Some(Err(Rejected {
+                        line: self.line + 1,
+                        error: e.to_string(),
+                        record: serde_json::Value::Null,
+                    }));
+                }
+            };
+            let options = FormatOptions::default();
+            let formatters = match batch
+                .columns()
+                .iter()
+                .map(|array| ArrayFormatter::try_new(array.as_ref(), &options))
+                .collect::<Result<Vec<_>, _>>()
+            {
+                Ok(formatters) => formatters,
+                Err(e) => {
+                    return Some(Err(Rejected {
+                        line: self.line + 1,
+                        error: e.to_string(),
+                        record: serde_json::Value::Null,
+                    }));
+                }
+            };
+            for row in 0..batch.num_rows() {
+                self.line += 1;
+                let values = batch
+                    .columns()
+                    .iter()
+                    .zip(&formatters)
+                    .map(|(array, formatter)| {
+                        (!array.is_null(row)).then(|| formatter.value(row).to_string())
+                    })
+                    .collect();
+                self.rows.push_back(Record {
+                    line: self.line,
+                    values,
+                });
+            }
+        }
+        self.rows.pop_front().map(Ok)
+    }
+}
+
+// 文件字段到表的列的对应关系
+#[derive(Debug, Clone)]
+struct ImportPlan {
+    columns: Vec<ColumnSchema>, // 导入的列，按表中的顺序
+    sources: Vec<usize>,        // 每一列对应的文件字段序号
+}
+
+// 按映射确定导入的列：映射中的字段导入到映射的列，其它字段导入到同名的列（没有同名列时忽略）
+// 没有表头且没有映射时按位置对应；缺少不能为 NULL 且没有默认值的列时报错
+fn plan_import(
+    table: &TableSchema,
+    file_columns: &[String],
+    mapping: &BTreeMap<String, String>,
+    header: bool,
+) -> Result<ImportPlan, String> {
+    let insertable = table
+        .columns
+        .iter()
+        .filter(|c| !c.extra.contains("GENERATED") || c.extra.contains("DEFAULT_GENERATED"))
+        .collect::<Vec<_>>();
+    for field in mapping.keys() {
+        if !file_columns.contains(field) {
+            eprintln!("[warn] 文件中没有字段 {}，映射被忽略", field);
+        }
+    }
+    let mut targets: Vec<(usize, &str)> = Vec::new(); // (文件字段序号, 列名)
+    for (i, field) in file_columns.iter().enumerate() {
+        let (column, mapped) = match mapping.get(field) {
+            Some(column) => (column.as_str(), true),
+            None if !header && mapping.is_empty() => match insertable.get(i) {
+                Some(column) => (column.name.as_str(), false),
+                None => continue,
+            },
+            None => (field.as_str(), false),
+        };
+        if !insertable.iter().any(|c| c.name == column) {
+            if mapped {
+                return Err(format!("字段 {} 映射的列 {} 不存在", field, column));
+            }
+            eprintln!("[warn] 表 {} 没有列 {}，字段被忽略", table.name, column);
+            continue;
+        }
+        if targets.iter().any(|(_, c)| *c == column) {
+            return Err(format!("多个字段导入到列 {}", column));
+        }
+        targets.push((i, column));
+    }
+    let mut plan = ImportPlan {
+        columns: Vec::new(),
+        sources: Vec::new(),
+    };
+    for column in insertable {
+        match targets.iter().find(|(_, c)| *c == column.name) {
+            Some((i, _)) => {
+                plan.columns.push(column.clone());
+                plan.sources.push(*i);
+            }
+            None if !column.nullable && column.default.is_none() && !column.auto_increment => {
+                return Err(format!(
+                    "列 {} 不能为NULL且没有默认值，文件中没有对应的字段",
+                    column.name
+                ));
+            }
+            None => {}
+        }
+    }
+    if plan.columns.is_empty() {
+        return Err("文件字段没有对应到表的任何列".to_string());
+    }
+    Ok(plan)
+}
+
+// 按列类型转换文件中的值，返回文本协议形式的值（与按行同步相同，由 mysql_row_values 写成字面量）
+// 非文本列的空字符串作为 NULL
+fn coerce(column: &ColumnSchema, value: Option<&str>) -> Result<Option<Vec<u8>>, String> {
+    let kind = column.kind();
+    let value = match value {
+        Some(v) if v.trim().is_empty() && !matches!(kind, ValueKind::Text | ValueKind::Binary) => {
+            None
+        }
+        other => other,
+    };
+    let Some(value) = value else {
+        if !column.nullable && !column.auto_increment {
+            return Err(format!("列 {} 不能为NULL", column.name));
+        }
+        return Ok(None);
+    };
+    let invalid = || {
+        format!(
+            "列 {} 的值 {:?} 不是有效的 {}",
+            column.name, value, column.column_type
+        )
+    };
+    let trimmed = value.trim();
+    let coerced = match kind {
+        ValueKind::Integer => match trimmed.parse::<i128>() {
+            Ok(n) => n.to_string().into_bytes(),
+            // 表格软件导出的整数可能带 .0
+            Err(_) => match trimmed.parse::<f64>() {
+                Ok(f) if f.fract() == 0.0 && f.abs() < 9.0e15 => {
+                    (f as i64).to_string().into_bytes()
+                }
+                _ => return Err(invalid()),
+            },
+        },
+        ValueKind::Float | ValueKind::Decimal => {
+            trimmed.parse::<f64>().map_err(|_| invalid())?;
+            trimmed.as_bytes().to_vec()
+        }
+        ValueKind::Boolean => {
+            let b = match trimmed.to_lowercase().as_str() {
+                "1" | "true" | "t" | "yes" | "y" => true,
+                "0" | "false" | "f" | "no" | "n" => false,
+                _ => return Err(invalid()),
+            };
+            if column.data_type == "bit" {
+                vec![b as u8]
+            } else {
+                (b as u8).to_string().into_bytes()
+            }
+        }
+        ValueKind::Bit => {
+            let n = trimmed.parse::<u64>().map_err(|_| invalid())?;
+            let bytes = n.to_be_bytes();
+            let start = bytes.iter().position(|b| *b != 0).unwrap_or(7);
+            bytes[start..].to_vec()
+        }
+        ValueKind::Binary => {
+            let hex_value = value.strip_prefix("0x").unwrap_or(value);
+            hex::decode(hex_value).map_err(|_| invalid())?
+        }
+        ValueKind::Date => parse_datetime(trimmed)
+            .map(|dt| dt.date())
+            .or_else(|| parse_date(trimmed))
+            .ok_or_else(invalid)?
+            .format("%Y-%m-%d")
+            .to_string()
+            .into_bytes(),
+        ValueKind::DateTime => parse_datetime(trimmed)
+            .or_else(|| parse_date(trimmed).and_then(|d| d.and_hms_opt(0, 0, 0)))
+            .ok_or_else(invalid)?
+            .format("%Y-%m-%d %H:%M:%S%.f")
+            .to_string()
+            .into_bytes(),
+        ValueKind::Json => {
+            serde_json::from_str::<serde_json::Value>(value).map_err(|_| invalid())?;
+            value.as_bytes().to_vec()
+        }
+        ValueKind::Time | ValueKind::Text => {
+            if column.data_type == "enum" && !column.enum_values().iter().any(|v| v == value) {
+                return Err(invalid());
+            }
+            let limited = matches!(column.data_type.as_str(), "char" | "varchar");
+            if limited
+                && column
+                    .char_length
+                    .is_some_and(|n| value.chars().count() as u64 > n)
+            {
+                return Err(format!(
+                    "列 {} 的值超过长度 {}",
+                    column.name,
+                    column.char_length.unwrap_or_default()
+                ));
+            }
+            value.as_bytes().to_vec()
+        }
+    };
+    Ok(Some(coerced))
+}
+
+fn parse_date(value: &str) -> Option<NaiveDate> {
+    ["%Y-%m-%d", "%Y/%m/%d", "%Y%m%d"]
+        .iter()
+        .find_map(|f| NaiveDate::parse_from_str(value, f).ok())
+}
+
+// 带时区的时间（RFC 3339）按文件中的本地时间导入
+fn parse_datetime(value: &str) -> Option<NaiveDateTime> {
+    if let Ok(dt) = DateTime::parse_from_rfc3339(value) {
+        return Some(dt.naive_local());
+    }
+    [
+        "%Y-%m-%d %H:%M:%S%.f",
+        "%Y-%m-%dT%H:%M:%S%.f",
+        "%Y/%m/%d %H:%M:%S%.f",
+        "%Y-%m-%d %H:%M",
+        "%Y/%m/%d %H:%M",
+    ]
+    .iter()
+    .find_map(|f| NaiveDateTime::parse_from_str(value, f).ok())
+}
+
+// 导入结果
+#[derive(Debug, Clone, Default)]
+pub struct ImportReport {
+    pub files: usize,
+    pub rows: u64,
+    pub rejected: u64,
+    pub quarantine: Option<String>, // 有错误行时为隔离文件路径
+}
+
+// 错误行写入隔离文件（JSON Lines：文件、行号、错误和原始内容），第一次写入时创建
+struct Quarantine {
+    path: String,
+    out: Option<BufWriter<File>>,
+    count: u64,
+    max_errors: Option<u64>,
+}
+
+impl Quarantine {
+    fn write(&mut self, file: &Path, rejected: Rejected) -> Result<(), BoxError> {
+        if self.out.is_none() {
+            if let Some(parent) = Path::new(&self.path).parent() {
+                fs::create_dir_all(parent)?;
+            }
+            self.out = Some(BufWriter::new(File::create(&self.path)?));
+        }
+        let entry = serde_json::json!({
+            "file": file.to_string_lossy(),
+            "line": rejected.line,
+            "error": rejected.error,
+            "record": rejected.record,
+        });
+        if let Some(out) = &mut self.out {
+            serde_json::to_writer(&mut *out, &entry)?;
+            out.write_all(b"\n")?;
+        }
+        self.count += 1;
+        if let Some(max) = self.max_errors
+            && self.count > max
+        {
+            self.finish()?;
+            return Err(format!(
+                "错误行超过 max_errors = {}，停止导入，错误行见 {}",
+                max, self.path
+            )
+            .into());
+        }
+        Ok(())
+    }
+
+    fn finish(&mut self) -> Result<(), BoxError> {
+        if let Some(out) = &mut self.out {
+            out.flush()?;
+        }
+        Ok(())
+    }
+}
+
+// 导入 [import] path 中的文件到 [target] db_name.table_name
+pub async fn import_job(
+    pool: &Pool<MySql>,
+    target: &Target,
+    import: &Import,
+) -> Result<ImportReport, BoxError> {
+    let db_name = target
+        .db_name
+        .as_deref()
+        .ok_or("import任务需要配置 target.db_name")?;
+    let table_name = target
+        .table_name
+        .as_deref()
+        .ok_or("import任务需要配置 target.table_name")?;
+    let table = read_tables(
+        &mut *pool.acquire().await?,
+        db_name,
+        &[table_name.to_string()],
+    )
+    .await?
+    .into_iter()
+    .next()
+    .ok_or_else(|| format!("目标库中没有表 {}.{}", db_name, table_name))?;
+    let upsert = match import.mode.as_deref().unwrap_or("insert") {
+        "insert" => false,
+        "upsert" => true,
+        other => return Err(format!("不支持的导入方式 {}", other).into()),
+    };
+    let key = if upsert {
+        upsert_key(&table, &import.key)?
+    } else {
+        Vec::new()
+    };
+
+    let files = import_files(import)?;
+    let mut quarantine = Quarantine {
+        path: import.quarantine.clone().unwrap_or_else(|| {
+            format!(
+                "sql/import_rejected_{}_{}_{}.jsonl",
+                db_name,
+                table_name,
+                Local::now().format("%Y%m%d_%H%M%S")
+            )
+        }),
+        out: None,
+        count: 0,
+        max_errors: import.max_errors,
+    };
+    let mut report = ImportReport {
+        files: files.len(),
+        ..Default::default()
+    };
+    for (path, format) in &files {
+        let mut reader: Box<dyn RecordReader> = match format {
+            ImportFormat::Csv => Box::new(CsvRecords::open(path, import)?),
+            ImportFormat::Jsonl => Box::new(JsonlRecords::open(path)?),
+            ImportFormat::Parquet => Box::new(ParquetRecords::open(path)?),
+        };
+        let plan = plan_import(
+            &table,
+            reader.columns(),
+            &import.columns,
+            import.header.unwrap_or(true) || *format != ImportFormat::Csv,
+        )
+        .map_err(|e| format!("{}: {}", path.display(), e))?;
+        let rows = import_file(
+            pool,
+            db_name,
+            &table.name,
+            &plan,
+            &key,
+            reader.as_mut(),
+            path,
+            &mut quarantine,
+        )
+        .await;
+        let rows = match rows {
+            Ok(rows) => rows,
+            Err(e) => {
+                quarantine.finish()?;
+                return Err(e);
+            }
+        };
+        println!("[ok] {} 导入 {} 行", path.display(), rows);
+        report.rows += rows;
+    }
+    quarantine.finish()?;
+    report.rejected = quarantine.count;
+    if quarantine.count > 0 {
+        report.quarantine = Some(quarantine.path);
+    }
+    Ok(report)
+}
+
+// upsert 的键：配置的列（默认主键）需要是主键或唯一索引的列
+fn upsert_key(table: &TableSchema, key: &[String]) -> Result<Vec<String>, String> {
+    let key = if key.is_empty() {
+        table.primary_key.clone()
+    } else {
+        key.to_vec()
+    };
+    if key.is_empty() {
+        return Err(format!("表 {} 没有主键，upsert 需要配置 key", table.name));
+    }
+    let same =
+        |columns: &[String]| columns.len() == key.len() && columns.iter().all(|c| key.contains(c));
+    let unique = same(&table.primary_key)
+        || table
+            .indexes
+            .iter()
+            .any(|index| index.unique && same(&index.columns));
+    if !unique {
+        return Err(format!(
+            "upsert 的 key {:?} 不是表 {} 的主键或唯一索引",
+            key, table.name
+        ));
+    }
+    Ok(key)
+}
+
+// path 为目录时导入目录中所有该格式的文件（按文件名排序）
+fn import_files(import: &Import) -> Result<Vec<(PathBuf, ImportFormat)>, BoxError> {
+    let path = Path::new(&import.path);
+    let configured = import
+        .format
+        .as_deref()
+        .map(ImportFormat::parse)
+        .transpose()?;
+    if path.is_dir() {
+        let format = configured.unwrap_or(ImportFormat::Csv);
+        let mut files = fs::read_dir(path)?
+            .map(|entry| entry.map(|e| e.path()))
+            .collect::<Result<Vec<_>, _>>()?
+            .into_iter()
+            .filter(|p| p.is_file() && format.matches(p))
+            .collect::<Vec<_>>();
+        files.sort();
+        if files.is_empty() {
+            return Err(format!("目录 {} 中没有 {:?} 文件", import.path, format).into());
+        }
+        return Ok(files.into_iter().map(|p| (p, format)).collect());
+    }
+    let format = match configured {
+        Some(format) => format,
+        None => {
+            let extension = path.extension().and_then(|e| e.to_str()).unwrap_or("csv");
+            ImportFormat::parse(&extension.to_lowercase())?
+        }
+    };
+    if !path.is_file() {
+        return Err(format!("导入文件 {} 不存在", import.path).into());
+    }
+    Ok(vec![(path.to_path_buf(), format)])
+}
+
+// 导入一个文件，返回导入的行数；转换失败的行和写入失败的行写入隔离文件
+#[allow(clippy::too_many_arguments)]
+async fn import_file(
+    pool: &Pool<MySql>,
+    db_name: &str,
+    table_name: &str,
+    plan: &ImportPlan,
+    key: &[String],
+    reader: &mut dyn RecordReader,
+    path: &Path,
+    quarantine: &mut Quarantine,
+) -> Result<u64, BoxError> {
+    let table = TableSchema {
+        name: table_name.to_string(),
+        columns: plan.columns.clone(),
+        primary_key: Vec::new(),
+        indexes: Vec::new(),
+        foreign_keys: Vec::new(),
+        comment: None,
+        charset: None,
+        collation: None,
+    };
+    let file_columns = reader.columns().to_vec();
+    let raw = |record: &Record| {
+        file_columns
+            .iter()
+            .zip(&record.values)
+            .map(|(name, value)| (name.clone(), serde_json::Value::from(value.clone())))
+            .collect::<serde_json::Map<_, _>>()
+            .into()
+    };
+    let mut imported = 0;
+    let mut batch: Vec<(Record, RowValues)> = Vec::new();
+    while let Some(record) = reader.next_record() {
+        let record = match record {
+            Ok(record) => record,
+            Err(rejected) => {
+                quarantine.write(path, rejected)?;
+                continue;
+            }
+        };
+        let row = plan
+            .columns
+            .iter()
+            .zip(&plan.sources)
+            .map(|(column, i)| coerce(column, record.values.get(*i).and_then(|v| v.as_deref())))
+            .collect::<Result<RowValues, String>>();
+        match row {
+            Ok(row) => batch.push((record, row)),
+            Err(error) => quarantine.write(
+                path,
+                Rejected {
+                    line: record.line,
+                    error,
+                    record: raw(&record),
+                },
+            )?,
+        }
+        if batch.len() >= INSERT_BATCH_SIZE {
+            imported += flush(
+                pool, db_name, &table, key, &mut batch, path, quarantine, &raw,
+            )
+            .await?;
+        }
+    }
+    if !batch.is_empty() {
+        imported += flush(
+            pool, db_name, &table, key, &mut batch, path, quarantine, &raw,
+        )
+        .await?;
+    }
+    Ok(imported)
+}
+
+// 写入一批；失败时逐行写入，找出失败的行写入隔离文件
+#[allow(clippy::too_many_arguments)]
+async fn flush(
+    pool: &Pool<MySql>,
+    db_name: &str,
+    table: &TableSchema,
+    key: &[String],
+    batch: &mut Vec<(Record, RowValues)>,
+    path: &Path,
+    quarantine: &mut Quarantine,
+    raw: &dyn Fn(&Record) -> serde_json::Value,
+) -> Result<u64, BoxError> {
+    let rows = batch.iter().map(|(_, row)| row.clone()).collect::<Vec<_>>();
+    let mut imported = 0;
+    if sqlx::raw_sql(&insert_sql(db_name, table, key, &rows))
+        .execute(pool)
+        .await
+        .is_ok()
+    {
+        imported = rows.len() as u64;
+    } else {
+        for (record, row) in batch.iter() {
+            let sql = insert_sql(db_name, table, key, std::slice::from_ref(row));
+            match sqlx::raw_sql(&sql).execute(pool).await {
+                Ok(_) => imported += 1,
+                Err(e) => quarantine.write(
+                    path,
+                    Rejected {
+                        line: record.line,
+                        error: e.to_string(),
+                        record: raw(record),
+                    },
+                )?,
+            }
+        }
+    }
+    batch.clear();
+    Ok(imported)
+}
+
+// key 不为空时 upsert：主键/唯一键相同的行更新键以外的列
+fn insert_sql(db_name: &str, table: &TableSchema, key: &[String], rows: &[RowValues]) -> String {
+    let columns = table
+        .columns
+        .iter()
+        .map(|c| quote_ident(&c.name))
+        .collect::<Vec<_>>()
+        .join(", ");
+    let values = rows
+        .iter()
+        .map(|row| mysql_row_values(table, row))
+        .collect::<Vec<_>>()
+        .join(",\n");
+    let mut sql = format!(
+        "INSERT INTO {}.{} ({}) VALUES {}",
+        quote_ident(db_name),
+        quote_ident(&table.name),
+        columns,
+        values
+    );
+    if !key.is_empty() {
+        let mut updates = table
+            .columns
+            .iter()
+            .filter(|c| !key.contains(&c.name))
+            .map(|c| format!("{0} = VALUES({0})", quote_ident(&c.name)))
+            .collect::<Vec<_>>();
+        if updates.is_empty() {
+            updates.push(format!("{0} = {0}", quote_ident(&key[0])));
+        }
+        sql.push_str(&format!(" ON DUPLICATE KEY UPDATE {}", updates.join(", ")));
+    }
+    sql
+}
+
+#[cfg(test)]
+mod test_import {
+    use super::*;
+
+    fn column(name: &str, data_type: &str, column_type: &str, nullable: bool) -> ColumnSchema {
+        ColumnSchema {
+            nullable,
+            char_length: (data_type == "varchar").then_some(4),
+            ..ColumnSchema::new(name, data_type, column_type)
+        }
+    }
+
+    fn table() -> TableSchema {
+        TableSchema {
+            name: "orders".to_string(),
+            columns: vec![
+                column("id", "int", "int", false),
+                column("code", "varchar", "varchar(4)", true),
+                column("paid", "tinyint", "tinyint(1)", true),
+                column("created_at", "datetime", "datetime", true),
+                column("status", "enum", "enum('new','done')", true),
+            ],
+            primary_key: vec!["id".to_string()],
+            indexes: Vec::new(),
+            foreign_keys: Vec::new(),
+            comment: None,
+            charset: None,
+            collation: None,
+        }
+    }
+
+    #[test]
+    fn test_plan_import() {
+        let table = table();
+        let fields = vec![
+            "Order ID".to_string(),
+            "code".to_string(),
+            "remark".to_string(),
+        ];
+        let mapping = BTreeMap::from([("Order ID".to_string(), "id".to_string())]);
+        let plan = plan_import(&table, &fields, &mapping, true).unwrap();
+        let names = plan
+            .columns
+            .iter()
+            .map(|c| c.name.as_str())
+            .collect::<Vec<_>>();
+        assert_eq!(names, vec!["id", "code"]);
+        assert_eq!(plan.sources, vec![0, 1]);
+
+        // 缺少不能为NULL的列
+        assert!(plan_import(&table, &fields, &BTreeMap::new(), true).is_err());
+        // 映射到不存在的列
+        let mapping = BTreeMap::from([("Order ID".to_string(), "order_id".to_string())]);
+        assert!(plan_import(&table, &fields, &mapping, true).is_err());
+
+        // 没有表头时按位置对应
+        let fields = vec!["1".to_string(), "2".to_string()];
+        let plan = plan_import(&table, &fields, &BTreeMap::new(), false).unwrap();
+        assert_eq!(plan.columns[1].name, "code");
+    }
+
+    #[test]
+    fn test_coerce() {
+        let table = table();
+        let [id, code, paid, created_at, status] = &table.columns[..] else {
+            panic!()
+        };
+        assert_eq!(coerce(id, Some(" 42 ")), Ok(Some(b"42".to_vec())));
+        assert_eq!(coerce(id, Some("42.0")), Ok(Some(b"42".to_vec())));
+        assert!(coerce(id, Some("4x")).is_err());
+        assert!(coerce(id, Some("")).is_err()); // NOT NULL
+        assert_eq!(coerce(paid, Some("Yes")), Ok(Some(b"1".to_vec())));
+        assert_eq!(coerce(paid, Some("")), Ok(None));
+        assert_eq!(
+            coerce(created_at, Some("2024/03/01 08:30:00")),
+            Ok(Some(b"2024-03-01 08:30:00".to_vec()))
+        );
+        assert_eq!(
+            coerce(created_at, Some("2024-03-01T08:30:00.5+08:00")),
+            Ok(Some(b"2024-03-01 08:30:00.500".to_vec()))
+        );
+        assert_eq!(
+            coerce(created_at, Some("2024-03-01")),
+            Ok(Some(b"2024-03-01 00:00:00".to_vec()))
+        );
+        assert!(coerce(created_at, Some("03/01/2024")).is_err());
+        assert!(coerce(code, Some("ABCDE")).is_err());
+        assert_eq!(coerce(code, Some("")), Ok(Some(Vec::new())));
+        assert!(coerce(status, Some("cancelled")).is_err());
+
+        let key = upsert_key(&table, &[]).unwrap();
+        assert!(upsert_key(&table, &["code".to_string()]).is_err());
+        let plan_table = TableSchema {
+            columns: vec![id.clone(), code.clone()],
+            ..table.clone()
+        };
+        assert_eq!(
+            insert_sql(
+                "shop",
+                &plan_table,
+                &key,
+                &[vec![Some(b"1".to_vec()), None]]
+            ),
+            "INSERT INTO `shop`.`orders` (`id`, `code`) VALUES ('1', NULL) \
+             ON DUPLICATE KEY UPDATE `code` = VALUES(`code`)"
+        );
+    }
+
+    #[test]
+    fn test_read_records() {
+        let dir = std::env::temp_dir().join(format!("datasync_import_{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let csv_path = dir.join("orders.csv");
+        fs::write(&csv_path, "\u{feff}id,code\n1,A1\n2,\n").unwrap();
+        let import = Import {
+            path: csv_path.to_string_lossy().into_owned(),
+            ..Default::default()
+        };
+        let mut reader = CsvRecords::open(&csv_path, &import).unwrap();
+        assert_eq!(reader.columns(), &["id".to_string(), "code".to_string()]);
+        let first = reader.next_record().unwrap().ok().unwrap();
+        assert_eq!(
+            first.values,
+            vec![Some("1".to_string()), Some("A1".to_string())]
+        );
+        let second = reader.next_record().unwrap().ok().unwrap();
+        assert_eq!(second.line, 3);
+        assert_eq!(second.values[1], None);
+        assert!(reader.next_record().is_none());
+
+        let jsonl_path = dir.join("orders.jsonl");
+        fs::write(
+            &jsonl_path,
+            "{\"id\":1,\"code\":\"A1\"}\nnot json\n{\"id\":3}\n",
+        )
+        .unwrap();
+        let mut reader = JsonlRecords::open(&jsonl_path).unwrap();
+        assert_eq!(reader.columns(), &["code".to_string(), "id".to_string()]);
+        assert!(reader.next_record().unwrap().is_ok());
+        assert!(reader.next_record().unwrap().is_err());
+        let third = reader.next_record().unwrap().ok().unwrap();
+        assert_eq!(third.values, vec![None, Some("3".to_string())]);
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
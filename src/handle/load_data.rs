@@ -0,0 +1,262 @@
+// LOAD DATA LOCAL INFILE 导入（库内并行导入时）：工作连接把读取的行写成TSV，
+// 通过标准输入交给 mysql 客户端（--local-infile=1）导入目标库，不写入磁盘，sqlx 不支持 LOCAL INFILE 请求
+// 目标库禁用了 local_infile 时改用多行 INSERT
+// LOCAL 导入时出错的行只产生警告（相当于 IGNORE），导入后检查 SHOW WARNINGS，有警告时与 INSERT 一样失败
+
+use std::process::Stdio;
+
+use tokio::{io::AsyncWriteExt, process::Command};
+
+use crate::{
+    handle::{
+        help::decode_stderr, mysql_schema::quote_ident, sync::BoxError, type_map::mysql_quote,
+    },
+    model::{
+        job::Target,
+        schema::{RowValues, TableSchema, ValueKind},
+    },
+};
+
+// 每次 LOAD DATA 导入的行数
+pub const LOAD_DATA_ROWS: usize = 50_000;
+
+// mysql 客户端从标准输入读取 LOCAL INFILE 的数据
+const LOAD_DATA_STDIN: &str = "/dev/stdin";
+
+// 导入会话的设置，来自 [target] 配置
+#[derive(Debug, Clone, Copy)]
+pub struct LoadOptions {
+    pub load_data: bool,          // 使用 LOAD DATA LOCAL INFILE
+    pub unique_checks: bool,      // false 时导入期间 SET unique_checks = 0
+    pub foreign_key_checks: bool, // false 时导入期间 SET foreign_key_checks = 0
+    pub sql_log_bin: bool, // false 时导入不写 binlog（需要 SUPER 或 SYSTEM_VARIABLES_ADMIN 权限）
+}
+
+impl LoadOptions {
+    pub fn from_target(target: &Target) -> Self {
+        LoadOptions {
+            load_data: target.load_data.unwrap_or(false),
+            unique_checks: target.unique_checks.unwrap_or(true),
+            foreign_key_checks: target.foreign_key_checks.unwrap_or(true),
+            sql_log_bin: target.sql_log_bin.unwrap_or(true),
+        }
+    }
+
+    // 导入会话的设置，都不关闭时为空
+    pub fn session_sql(&self) -> String {
+        let mut statements = Vec::new();
+        if !self.foreign_key_checks {
+            statements.push("SET foreign_key_checks = 0");
+        }
+        if !self.unique_checks {
+            statements.push("SET unique_checks = 0");
+        }
+        if !self.sql_log_bin {
+            statements.push("SET sql_log_bin = 0");
+        }
+        statements.join("; ")
+    }
+}
+
+// 一行写成 LOAD DATA 的默认格式：字段用 \t 分隔，行以 \n 结束，\ 转义，NULL 为 \N
+pub fn write_tsv_row(buf: &mut Vec<u8>, row: &RowValues) {
+    for (i, value) in row.iter().enumerate() {
+        if i > 0 {
+            buf.push(b'\t');
+        }
+        let Some(value) = value else {
+            buf.extend_from_slice(b"\\N");
+            continue;
+        };
+        for b in value {
+            match b {
+                b'\\' => buf.extend_from_slice(b"\\\\"),
+                b'\t' => buf.extend_from_slice(b"\\t"),
+                b'\n' => buf.extend_from_slice(b"\\n"),
+                b'\r' => buf.extend_from_slice(b"\\r"),
+                0 => buf.extend_from_slice(b"\\0"),
+                _ => buf.push(*b),
+            }
+        }
+    }
+    buf.push(b'\n');
+}
+
+// LOAD DATA 语句：从标准输入按 binary 读取到变量，文本列（源库连接返回utf8mb4）转换为utf8mb4，其它列原样写入
+// merge 模式用 REPLACE 覆盖主键/唯一键相同的行
+pub fn load_data_sql(db_name: &str, table: &TableSchema, replace: bool) -> String {
+    let variables = (0..table.columns.len())
+        .map(|i| format!("@c{}", i))
+        .collect::<Vec<_>>()
+        .join(", ");
+    let assignments = table
+        .columns
+        .iter()
+        .enumerate()
+        .map(|(i, column)| match column.kind() {
+            ValueKind::Integer
+            | ValueKind::Float
+            | ValueKind::Decimal
+            | ValueKind::Boolean
+            | ValueKind::Bit
+            | ValueKind::Binary => format!("{} = @c{}", quote_ident(&column.name), i),
+            _ => format!(
+                "{} = CONVERT(@c{} USING utf8mb4)",
+                quote_ident(&column.name),
+                i
+            ),
+        })
+        .collect::<Vec<_>>()
+        .join(", ");
+    format!(
+        "LOAD DATA LOCAL INFILE {} {}INTO TABLE {}.{} CHARACTER SET binary \
+         FIELDS TERMINATED BY '\\t' ESCAPED BY '\\\\' LINES TERMINATED BY '\\n' ({}) SET {}",
+        mysql_quote(LOAD_DATA_STDIN),
+        if replace { "REPLACE " } else { "" },
+        quote_ident(db_name),
+        quote_ident(&table.name),
+        variables,
+        assignments
+    )
+}
+
+// 用 mysql 客户端执行 sql（会话设置 + LOAD DATA），data 为TSV数据，写入客户端的标准输入
+// 返回 false 表示客户端或服务器禁用了 LOCAL INFILE
+// LOAD DATA 产生警告（主键重复、值被截断或转换）时返回错误
+pub async fn load_file(target: &Target, sql: &str, data: Vec<u8>) -> Result<bool, BoxError> {
+    let mut child = Command::new("mysql")
+        .arg("--local-infile=1")
+        .arg("--default-character-set=utf8mb4")
+        .arg("--batch")
+        .arg("--skip-column-names")
+        .arg(format!("-h{}", target.host))
+        .arg(format!("-P{}", target.port))
+        .arg(format!("-u{}", target.user))
+        .arg(format!("-p{}", target.password))
+        .arg("-e")
+        .arg(format!("{}; SHOW WARNINGS", sql))
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .kill_on_drop(true)
+        .spawn()?;
+    let mut stdin = child.stdin.take().ok_or("mysql 客户端没有标准输入")?;
+    // 与读取输出同时写入，客户端出错提前退出时写入失败（管道关闭），以退出状态为准
+    let writer = tokio::spawn(async move {
+        let _ = stdin.write_all(&data).await;
+    });
+    let output = child.wait_with_output().await?;
+    writer.await?;
+    if output.status.success() {
+        let warnings = load_warnings(&String::from_utf8_lossy(&output.stdout));
+        if !warnings.is_empty() {
+            return Err(format!("LOAD DATA failed: {}", warnings.join("; ")).into());
+        }
+        return Ok(true);
+    }
+    let stderr = decode_stderr(&output.stderr);
+    if local_infile_disabled(&stderr) {
+        eprintln!("[warn] LOAD DATA LOCAL INFILE 不可用：{}", stderr.trim());
+        return Ok(false);
+    }
+    Err(format!("LOAD DATA failed: {}", stderr).into())
+}
+
+// SHOW WARNINGS 的输出（--batch：Level\tCode\tMessage），返回 Warning 和 Error，忽略 Note
+fn load_warnings(stdout: &str) -> Vec<String> {
+    stdout
+        .lines()
+        .filter_map(|line| {
+            let mut fields = line.splitn(3, '\t');
+            let level = fields.next()?;
+            let code = fields.next()?;
+            let message = fields.next().unwrap_or_default();
+            (level == "Warning" || level == "Error").then(|| format!("{} {}", code, message))
+        })
+        .collect()
+}
+
+// ERROR 3948：服务器 local_infile=OFF；1148：旧版本服务器不允许；2068：客户端拒绝读取
+fn local_infile_disabled(stderr: &str) -> bool {
+    ["ERROR 3948", "ERROR 1148", "ERROR 2068"]
+        .iter()
+        .any(|code| stderr.contains(code))
+}
+
+#[cfg(test)]
+mod test_load_data {
+    use super::*;
+    use crate::model::schema::ColumnSchema;
+
+    #[test]
+    fn test_load_data() {
+        let mut buf = Vec::new();
+        write_tsv_row(
+            &mut buf,
+            &vec![
+                Some(b"1".to_vec()),
+                None,
+                Some(b"a\tb\\c\nd".to_vec()),
+                Some(vec![0, 255]),
+            ],
+        );
+        assert_eq!(buf, b"1\t\\N\ta\\tb\\\\c\\nd\t\\0\xff\n");
+
+        let table = TableSchema {
+            name: "orders".to_string(),
+            columns: vec![
+                ColumnSchema::new("id", "int", "int"),
+                ColumnSchema::new("note", "varchar", "varchar(255)"),
+            ],
+            primary_key: vec!["id".to_string()],
+            indexes: Vec::new(),
+            foreign_keys: Vec::new(),
+            comment: None,
+            charset: None,
+            collation: None,
+        };
+        assert_eq!(
+            load_data_sql("shop", &table, true),
+            "LOAD DATA LOCAL INFILE '/dev/stdin' REPLACE INTO TABLE `shop`.`orders` CHARACTER SET binary \
+             FIELDS TERMINATED BY '\\t' ESCAPED BY '\\\\' LINES TERMINATED BY '\\n' (@c0, @c1) \
+             SET `id` = @c0, `note` = CONVERT(@c1 USING utf8mb4)"
+        );
+
+        let options = LoadOptions {
+            load_data: true,
+            unique_checks: false,
+            foreign_key_checks: false,
+            sql_log_bin: false,
+        };
+        assert_eq!(
+            options.session_sql(),
+            "SET foreign_key_checks = 0; SET unique_checks = 0; SET sql_log_bin = 0"
+        );
+        // 默认不关闭任何检查
+        let options = LoadOptions {
+            unique_checks: true,
+            foreign_key_checks: true,
+            sql_log_bin: true,
+            ..options
+        };
+        assert_eq!(options.session_sql(), "");
+        assert!(local_infile_disabled(
+            "ERROR 3948 (42000) at line 1: Loading local data is disabled"
+        ));
+    }
+
+    #[test]
+    fn test_load_warnings() {
+        let stdout = "Warning\t1062\tDuplicate entry '1' for key 'orders.PRIMARY'\n\
+                      Note\t1265\tData truncated for column 'note' at row 2\n\
+                      Warning\t1366\tIncorrect integer value: 'x' for column 'id' at row 3\n";
+        assert_eq!(
+            load_warnings(stdout),
+            vec![
+                "1062 Duplicate entry '1' for key 'orders.PRIMARY'",
+                "1366 Incorrect integer value: 'x' for column 'id' at row 3"
+            ]
+        );
+        assert!(load_warnings("").is_empty());
+    }
+}
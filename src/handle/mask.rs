@@ -0,0 +1,210 @@
+// 数据脱敏：生产数据同步到测试环境时替换手机号、姓名、身份证号等敏感列
+// 除 null、partial 外的方式都以 [handler] salt 为密钥计算 HMAC-SHA256，相同的值得到相同的结果，跨表关联不受影响
+
+use hmac::{Hmac, Mac};
+use sha2::Sha256;
+
+use crate::model::schema::{ColumnSchema, ValueKind};
+
+type HmacSha256 = Hmac<Sha256>;
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Mask {
+    Hash,                  // 加盐哈希（十六进制）
+    Token,                 // 保留格式的确定性替换：数字换数字、字母换字母，长度不变
+    Phone,                 // 保留前3位的手机号
+    Email,                 // u_xxxxxxxxxx@example.com
+    Name,                  // 假姓名（中文名换中文名）
+    Null,                  // 置为NULL
+    Partial(usize, usize), // 保留前后若干个字符，中间替换为 *，例如 138****1234
+}
+
+const SURNAMES: [&str; 24] = [
+    "王", "李", "张", "刘", "陈", "杨", "黄", "赵", "吴", "周", "徐", "孙", "马", "朱", "胡", "郭",
+    "何", "林", "高", "罗", "郑", "梁", "谢", "宋",
+];
+const GIVEN_NAMES: [&str; 32] = [
+    "伟", "芳", "娜", "敏", "静", "丽", "强", "磊", "军", "洋", "勇", "艳", "杰", "娟", "涛", "明",
+    "超", "秀", "霞", "平", "刚", "桂", "英", "华", "玉", "兰", "飞", "鹏", "宇", "欣", "晨", "琳",
+];
+const FIRST_NAMES: [&str; 16] = [
+    "Alex", "Blake", "Casey", "Drew", "Emery", "Finley", "Gray", "Harper", "Jamie", "Jordan",
+    "Kai", "Logan", "Morgan", "Quinn", "Riley", "Taylor",
+];
+const LAST_NAMES: [&str; 16] = [
+    "Smith", "Brown", "Clark", "Davis", "Evans", "Foster", "Green", "Hill", "King", "Lee", "Moore",
+    "Parker", "Reed", "Scott", "Turner", "Young",
+];
+
+impl Mask {
+    // hash | token | phone | email | name | null | partial | partial(前, 后)
+    pub fn parse(spec: &str) -> Result<Self, String> {
+        let spec = spec.trim().to_lowercase();
+        let mask = match spec.as_str() {
+            "hash" => Mask::Hash,
+            "token" => Mask::Token,
+            "phone" => Mask::Phone,
+            "email" => Mask::Email,
+            "name" => Mask::Name,
+            "null" => Mask::Null,
+            "partial" => Mask::Partial(3, 4),
+            _ => {
+                let params = spec
+                    .strip_prefix("partial(")
+                    .and_then(|p| p.strip_suffix(')'))
+                    .and_then(|p| p.split_once(','))
+                    .and_then(|(a, b)| Some((a.trim().parse().ok()?, b.trim().parse().ok()?)))
+                    .ok_or_else(|| format!("不支持的脱敏方式 {}", spec))?;
+                Mask::Partial(params.0, params.1)
+            }
+        };
+        Ok(mask)
+    }
+
+    // 是否需要 salt
+    pub fn salted(&self) -> bool {
+        !matches!(self, Mask::Null | Mask::Partial(..))
+    }
+
+    // 结果是否只能写入字符串列
+    pub fn text_only(&self) -> bool {
+        !matches!(self, Mask::Null | Mask::Token | Mask::Phone)
+    }
+
+    // 脱敏一个值，结果超过列的字符长度时截断
+    pub fn apply(&self, salt: &[u8], column: &ColumnSchema, value: &str) -> Option<String> {
+        let masked = match self {
+            Mask::Null => return None,
+            Mask::Hash => hex::encode(keystream(salt, value, 32)),
+            Mask::Token => token(salt, value, 0),
+            Mask::Phone => token(salt, value, 3),
+            Mask::Email => format!("u_{}@example.com", hex::encode(keystream(salt, value, 5))),
+            Mask::Name => fake_name(salt, value),
+            Mask::Partial(head, tail) => {
+                let chars = value.chars().collect::<Vec<_>>();
+                if chars.len() <= head + tail {
+                    "*".repeat(chars.len())
+                } else {
+                    let mut masked = chars[..*head].iter().collect::<String>();
+                    masked.push_str(&"*".repeat(chars.len() - head - tail));
+                    masked.extend(&chars[chars.len() - tail..]);
+                    masked
+                }
+            }
+        };
+        match column.char_length {
+            Some(len) if column.kind() == ValueKind::Text => {
+                Some(masked.chars().take(len as usize).collect())
+            }
+            _ => Some(masked),
+        }
+    }
+}
+
+// HMAC-SHA256(salt, value || 计数器) 拼接得到的 len 字节
+fn keystream(salt: &[u8], value: &str, len: usize) -> Vec<u8> {
+    let mut stream = Vec::with_capacity(len + 32);
+    let mut counter = 0u32;
+    while stream.len() < len {
+        let mut mac = HmacSha256::new_from_slice(salt).expect("HMAC accepts any key length");
+        mac.update(value.as_bytes());
+        mac.update(&counter.to_be_bytes());
+        stream.extend_from_slice(&mac.finalize().into_bytes());
+        counter += 1;
+    }
+    stream.truncate(len);
+    stream
+}
+
+// 保留前 keep 个字符和所有非字母数字字符，其余数字换成数字、字母换成同样大小写的字母
+fn token(salt: &[u8], value: &str, keep: usize) -> String {
+    let chars = value.chars().collect::<Vec<_>>();
+    let stream = keystream(salt, value, chars.len());
+    chars
+        .iter()
+        .zip(stream)
+        .enumerate()
+        .map(|(i, (c, k))| match c {
+            _ if i < keep => *c,
+            '0'..='9' => (b'0' + k % 10) as char,
+            'a'..='z' => (b'a' + k % 26) as char,
+            'A'..='Z' => (b'A' + k % 26) as char,
+            _ => *c,
+        })
+        .collect()
+}
+
+fn fake_name(salt: &[u8], value: &str) -> String {
+    let stream = keystream(salt, value, 8);
+    if value.is_ascii() {
+        return format!(
+            "{} {}",
+            FIRST_NAMES[stream[0] as usize % FIRST_NAMES.len()],
+            LAST_NAMES[stream[1] as usize % LAST_NAMES.len()]
+        );
+    }
+    // 中文名保持字数（2~4个字）
+    let given = value.chars().count().clamp(2, 4) - 1;
+    let mut name = SURNAMES[stream[0] as usize % SURNAMES.len()].to_string();
+    for k in &stream[1..=given] {
+        name.push_str(GIVEN_NAMES[*k as usize % GIVEN_NAMES.len()]);
+    }
+    name
+}
+
+#[cfg(test)]
+mod test_mask {
+    use super::*;
+
+    fn text_column(len: Option<u64>) -> ColumnSchema {
+        ColumnSchema {
+            char_length: len,
+            ..ColumnSchema::new("c", "varchar", "varchar(64)")
+        }
+    }
+
+    #[test]
+    fn test_masks() {
+        let salt = b"salt";
+        let column = text_column(Some(64));
+        let apply = |spec: &str, value: &str| {
+            Mask::parse(spec)
+                .unwrap()
+                .apply(salt, &column, value)
+                .unwrap()
+        };
+
+        assert_eq!(apply("partial", "13812341234"), "138****1234");
+        assert_eq!(apply("partial(1,0)", "张三"), "张*");
+        assert_eq!(apply("partial", "abc"), "***");
+
+        let phone = apply("phone", "13812341234");
+        assert!(phone.starts_with("138") && phone.len() == 11 && phone != "13812341234");
+        assert!(phone.chars().all(|c| c.is_ascii_digit()));
+
+        // 确定性：相同的值结果相同，不同的 salt 结果不同
+        let token = apply("token", "11010519491231002X");
+        assert_eq!(token, apply("token", "11010519491231002X"));
+        assert_eq!(token.len(), 18);
+        assert_ne!(
+            token,
+            Mask::Token
+                .apply(b"other", &column, "11010519491231002X")
+                .unwrap()
+        );
+
+        assert_eq!(apply("hash", "a").len(), 64);
+        assert_eq!(
+            Mask::Hash
+                .apply(salt, &text_column(Some(16)), "a")
+                .unwrap()
+                .len(),
+            16
+        );
+        assert!(apply("email", "tom@corp.com").ends_with("@example.com"));
+        assert_eq!(apply("name", "欧阳娜娜").chars().count(), 4);
+        assert!(apply("name", "Tom Hanks").is_ascii());
+        assert_eq!(Mask::Null.apply(salt, &column, "x"), None);
+        assert!(Mask::parse("shuffle").is_err());
+    }
+}
@@ -0,0 +1,621 @@
+// 按行同步（跨数据库迁移）：MySQL / PostgreSQL -> MySQL / PostgreSQL / SQLite
+// 读取源库表结构转换为目标库建表语句，逐批导入数据，最后创建外键，并输出转换报告
+
+use std::{any::Any, collections::HashMap, fs, path::Path, sync::Arc};
+
+use async_trait::async_trait;
+use chrono::Local;
+use sqlx::{
+    Pool, Postgres, Row, SqlitePool,
+    sqlite::{SqliteConnectOptions, SqliteJournalMode, SqlitePoolOptions, SqliteSynchronous},
+};
+use tokio::sync::{Mutex, mpsc};
+
+use crate::{
+    db::postgres_db::get_postgres_db_pool,
+    handle::{
+        pg_help::{ensure_database, query_all_databases, query_postgres_version, quote_ident},
+        pg_schema::{read_other_objects, read_rows, read_tables},
+        sync::{
+            ApplyResult, BoxError, SyncOptions, SyncSource, SyncTarget, TargetTable, copy_table,
+            pool_name,
+        },
+        transform::TableTransform,
+        type_map::{
+            TableReport, TranslationReport, pg_row_values, sqlite_row_values,
+            translate_table_to_pg, translate_table_to_sqlite,
+        },
+    },
+    model::{
+        job::{Handler, Source, Target},
+        schema::{RowValues, TableSchema},
+    },
+};
+
+// 每条INSERT语句的行数
+const INSERT_BATCH_SIZE: usize = 500;
+
+// 迁移一个数据库：schema 中的表（tables 为空时迁移所有表）迁移到目标库的 target_schema
+// 先建表导入数据，再执行索引等 post-data 语句，所有表导入完成后创建外键
+// handler 中配置的转换在读取之后、写入之前执行
+// schema_only 模式只建表；data_only 模式不建表，清空目标库已存在的表后导入
+pub async fn migrate_database(
+    source: &dyn SyncSource,
+    target: &dyn SyncTarget,
+    handler: Option<&Handler>,
+    options: &SyncOptions,
+    schema: &str,
+    target_schema: &str,
+    tables: &[String],
+) -> Result<TranslationReport, BoxError> {
+    let mut report = TranslationReport {
+        source: source.describe(schema),
+        target: target.describe(target_schema),
+        ..Default::default()
+    };
+    // data_only 模式写入已存在的表
+    if options.schema {
+        target.prepare_schema(target_schema).await?;
+    }
+
+    let source_tables = source.read_tables(schema, tables).await?;
+    if tables.is_empty() && options.schema {
+        for (object_type, name) in source.read_other_objects(schema).await? {
+            report.untranslated(name, format!("{} 需要人工转换", object_type));
+        }
+    }
+
+    let same_engine = source.engine() == target.engine();
+    let mut foreign_keys = Vec::new();
+    for table in &source_tables {
+        let transform = TableTransform::build(handler, schema, table)?;
+        // 表结构经过转换时由目标库按转换后的表结构建表
+        let source_ddl = if same_engine && transform.is_none() && options.schema {
+            Some(source.read_ddl(schema, &table.name).await?)
+        } else {
+            None
+        };
+        let output = transform.as_ref().map(|t| &t.output).unwrap_or(table);
+        let created = if options.schema {
+            target
+                .create_table(target_schema, output, source_ddl.as_deref(), &mut report)
+                .await?
+        } else {
+            target.truncate_table(target_schema, output).await?
+        };
+        if !options.data {
+            println!("[ok] 表 {} 结构创建完成", table.name);
+        }
+        let applied = if options.data {
+            copy_table(
+                source,
+                target,
+                schema,
+                target_schema,
+                table,
+                transform.as_ref(),
+                &created.table,
+                INSERT_BATCH_SIZE,
+            )
+            .await?
+        } else {
+            ApplyResult::default()
+        };
+
+        for sql in &created.post_data {
+            if let Err(e) = target.execute(target_schema, sql).await {
+                report.untranslated(&table.name, format!("{} 执行失败: {}", sql, e));
+            }
+        }
+        if applied.null_values > 0 {
+            report.warn(
+                &table.name,
+                format!(
+                    "{} 个值在{}中无效（例如零日期）或转换失败，写入了NULL",
+                    applied.null_values,
+                    target.engine()
+                ),
+            );
+        }
+        if options.data {
+            println!("[ok] 表 {} 迁移完成，{} 行", table.name, applied.rows);
+        }
+        report.tables.push(TableReport {
+            name: table.name.clone(),
+            rows: applied.rows,
+            null_values: applied.null_values,
+        });
+        foreign_keys.extend(created.foreign_keys);
+    }
+
+    for sql in &foreign_keys {
+        if let Err(e) = target.execute(target_schema, sql).await {
+            report.untranslated("FOREIGN KEY", format!("{} 执行失败: {}", sql, e));
+        }
+    }
+    target.finish_schema(target_schema).await?;
+    Ok(report)
+}
+
+fn insert_prefix(table: &TableSchema) -> String {
+    let columns = table
+        .columns
+        .iter()
+        .map(|c| quote_ident(&c.name))
+        .collect::<Vec<_>>()
+        .join(", ");
+    format!(
+        "INSERT INTO {} ({}) VALUES ",
+        quote_ident(&table.name),
+        columns
+    )
+}
+
+async fn list_pg_tables(pool: &Pool<Postgres>) -> Result<Vec<String>, BoxError> {
+    let rows = sqlx::query(
+        "SELECT table_name::text FROM information_schema.tables \
+         WHERE table_schema = 'public' AND table_type = 'BASE TABLE' ORDER BY table_name",
+    )
+    .fetch_all(pool)
+    .await?;
+    Ok(rows.iter().map(|row| row.get(0)).collect())
+}
+
+// PostgreSQL源：每个源库一个连接池，列出数据库时使用 postgres 库的连接
+#[derive(Clone, Debug)]
+pub struct PgSource {
+    pub admin_pool: Arc<Pool<Postgres>>,
+    pub source: Source, // 连接信息和按表过滤条件
+    pub job_name: String,
+}
+
+impl PgSource {
+    pub async fn connect(job_name: &str, source: &Source) -> Result<Self, String> {
+        let admin_pool = get_postgres_db_pool(
+            &source.dsn("postgres", None),
+            &pool_name("source", job_name, None),
+        )
+        .await?;
+        Ok(PgSource {
+            admin_pool,
+            source: source.clone(),
+            job_name: job_name.to_string(),
+        })
+    }
+
+    // 连接到源库
+    async fn pool(&self, schema: &str) -> Result<Arc<Pool<Postgres>>, BoxError> {
+        Ok(get_postgres_db_pool(
+            &self.source.dsn("postgres", Some(schema)),
+            &pool_name("source", &self.job_name, Some(schema)),
+        )
+        .await?)
+    }
+}
+
+#[async_trait]
+impl SyncSource for PgSource {
+    fn engine(&self) -> &'static str {
+        "postgres"
+    }
+
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+
+    fn describe(&self, schema: &str) -> String {
+        format!(
+            "postgres://{}:{}/{}",
+            self.source.host, self.source.port, schema
+        )
+    }
+
+    async fn version(&self) -> Result<String, BoxError> {
+        Ok(query_postgres_version(&self.admin_pool).await?)
+    }
+
+    async fn list_schemas(&self) -> Result<Vec<String>, BoxError> {
+        Ok(query_all_databases(&self.admin_pool).await?)
+    }
+
+    async fn list_tables(&self, schema: &str) -> Result<Vec<String>, BoxError> {
+        list_pg_tables(&*self.pool(schema).await?).await
+    }
+
+    async fn read_tables(
+        &self,
+        schema: &str,
+        tables: &[String],
+    ) -> Result<Vec<TableSchema>, BoxError> {
+        Ok(read_tables(&*self.pool(schema).await?, tables).await?)
+    }
+
+    // PostgreSQL没有 SHOW CREATE TABLE，按读取的表结构生成建表语句
+    async fn read_ddl(&self, schema: &str, table: &str) -> Result<String, BoxError> {
+        let tables = self.read_tables(schema, &[table.to_string()]).await?;
+        let table = tables
+            .first()
+            .ok_or_else(|| format!("表 {}.{} 不存在", schema, table))?;
+        Ok(translate_table_to_pg(table, &mut TranslationReport::default()).create_table)
+    }
+
+    async fn read_other_objects(&self, schema: &str) -> Result<Vec<(String, String)>, BoxError> {
+        Ok(read_other_objects(&*self.pool(schema).await?).await?)
+    }
+
+    async fn stream_rows(
+        &self,
+        schema: &str,
+        table: &TableSchema,
+        batch_size: usize,
+        tx: mpsc::Sender<Vec<RowValues>>,
+    ) -> Result<u64, BoxError> {
+        let where_clause = self.source.table_filter(schema, &table.name);
+        if let Some(where_clause) = where_clause {
+            println!("[filter] {}.{} WHERE {}", schema, table.name, where_clause);
+        }
+        let mut conn = self.pool(schema).await?.acquire().await?;
+        read_rows(&mut conn, table, where_clause, batch_size, tx).await
+    }
+}
+
+// PostgreSQL目标：每个目标库一个连接池，建库时使用 postgres 库的连接
+#[derive(Clone, Debug)]
+pub struct PgTarget {
+    pub admin_pool: Arc<Pool<Postgres>>,
+    pub target: Target,
+    pub job_name: String,
+}
+
+impl PgTarget {
+    pub async fn connect(job_name: &str, target: &Target) -> Result<Self, String> {
+        let admin_pool = get_postgres_db_pool(
+            &target.dsn("postgres", None),
+            &pool_name("target", job_name, None),
+        )
+        .await?;
+        Ok(PgTarget {
+            admin_pool,
+            target: target.clone(),
+            job_name: job_name.to_string(),
+        })
+    }
+
+    // 连接到目标库
+    async fn pool(&self, schema: &str) -> Result<Arc<Pool<Postgres>>, BoxError> {
+        Ok(get_postgres_db_pool(
+            &self.target.dsn("postgres", Some(schema)),
+            &pool_name("target", &self.job_name, Some(schema)),
+        )
+        .await?)
+    }
+}
+
+#[async_trait]
+impl SyncTarget for PgTarget {
+    fn engine(&self) -> &'static str {
+        "postgres"
+    }
+
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+
+    fn describe(&self, schema: &str) -> String {
+        format!(
+            "postgres://{}:{}/{}",
+            self.target.host, self.target.port, schema
+        )
+    }
+
+    async fn version(&self) -> Result<String, BoxError> {
+        Ok(query_postgres_version(&self.admin_pool).await?)
+    }
+
+    async fn list_schemas(&self) -> Result<Vec<String>, BoxError> {
+        Ok(query_all_databases(&self.admin_pool).await?)
+    }
+
+    async fn list_tables(&self, schema: &str) -> Result<Vec<String>, BoxError> {
+        list_pg_tables(&*self.pool(schema).await?).await
+    }
+
+    // 库中可能还有连接，重建 public 模式而不是删除数据库
+    async fn drop_schema(&self, schema: &str) -> Result<(), BoxError> {
+        sqlx::raw_sql("DROP SCHEMA public CASCADE; CREATE SCHEMA public")
+            .execute(&*self.pool(schema).await?)
+            .await?;
+        Ok(())
+    }
+
+    async fn prepare_schema(&self, schema: &str) -> Result<(), BoxError> {
+        Ok(ensure_database(&self.admin_pool, schema).await?)
+    }
+
+    async fn create_table(
+        &self,
+        schema: &str,
+        table: &TableSchema,
+        _source_ddl: Option<&str>,
+        report: &mut TranslationReport,
+    ) -> Result<TargetTable, BoxError> {
+        let ddl = translate_table_to_pg(table, report);
+        let pool = self.pool(schema).await?;
+        sqlx::raw_sql(&format!(
+            "DROP TABLE IF EXISTS {} CASCADE",
+            quote_ident(&table.name)
+        ))
+        .execute(&*pool)
+        .await?;
+        sqlx::raw_sql(&ddl.create_table).execute(&*pool).await?;
+        Ok(TargetTable {
+            table: ddl.table,
+            post_data: ddl.post_data,
+            foreign_keys: ddl.foreign_keys,
+        })
+    }
+
+    async fn apply_rows(
+        &self,
+        schema: &str,
+        table: &TableSchema,
+        rows: Vec<RowValues>,
+    ) -> Result<ApplyResult, BoxError> {
+        let mut result = ApplyResult::default();
+        let mut values = Vec::with_capacity(rows.len());
+        for row in &rows {
+            let (row_sql, nulls) = pg_row_values(table, row);
+            result.null_values += nulls;
+            values.push(row_sql);
+        }
+        let sql = format!("{}{}", insert_prefix(table), values.join(",\n"));
+        sqlx::raw_sql(&sql)
+            .execute(&*self.pool(schema).await?)
+            .await?;
+        result.rows = rows.len() as u64;
+        Ok(result)
+    }
+
+    async fn execute(&self, schema: &str, sql: &str) -> Result<(), BoxError> {
+        sqlx::raw_sql(sql)
+            .execute(&*self.pool(schema).await?)
+            .await?;
+        Ok(())
+    }
+
+    // 被其它表的外键引用时不能 TRUNCATE，使用 DELETE
+    async fn truncate_table(
+        &self,
+        schema: &str,
+        table: &TableSchema,
+    ) -> Result<TargetTable, BoxError> {
+        let ddl = translate_table_to_pg(table, &mut TranslationReport::default());
+        sqlx::raw_sql(&format!("DELETE FROM {}", quote_ident(&table.name)))
+            .execute(&*self.pool(schema).await?)
+            .await?;
+        // 只需要调整自增序列，索引和注释已存在
+        let mut created = TargetTable::new(ddl.table);
+        created.post_data = ddl
+            .post_data
+            .into_iter()
+            .filter(|sql| sql.starts_with("SELECT setval"))
+            .collect();
+        Ok(created)
+    }
+}
+
+// SQLite目标：每个数据库一个 {target.path}/{库名}.db，已存在的文件会被重新生成（data_only 模式除外）
+#[derive(Debug)]
+pub struct SqliteTarget {
+    pub dir: String,
+    pools: Mutex<HashMap<String, SqlitePool>>,
+}
+
+impl SqliteTarget {
+    pub fn new(target: &Target) -> Self {
+        SqliteTarget {
+            dir: target.sqlite_dir().to_string(),
+            pools: Mutex::new(HashMap::new()),
+        }
+    }
+
+    fn db_path(&self, schema: &str) -> std::path::PathBuf {
+        Path::new(&self.dir).join(format!("{}.db", schema))
+    }
+
+    async fn pool(&self, schema: &str) -> Result<SqlitePool, BoxError> {
+        if let Some(pool) = self.pools.lock().await.get(schema) {
+            return Ok(pool.clone());
+        }
+        // 已有的文件（例如 list_tables）直接打开
+        let options = SqliteConnectOptions::new().filename(self.db_path(schema));
+        let pool = SqlitePoolOptions::new()
+            .max_connections(1)
+            .connect_with(options)
+            .await?;
+        self.pools
+            .lock()
+            .await
+            .insert(schema.to_string(), pool.clone());
+        Ok(pool)
+    }
+}
+
+#[async_trait]
+impl SyncTarget for SqliteTarget {
+    fn engine(&self) -> &'static str {
+        "sqlite"
+    }
+
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+
+    fn describe(&self, schema: &str) -> String {
+        format!("sqlite://{}", self.db_path(schema).display())
+    }
+
+    async fn version(&self) -> Result<String, BoxError> {
+        let pool = SqlitePool::connect("sqlite::memory:").await?;
+        let row: (String,) = sqlx::query_as("SELECT sqlite_version()")
+            .fetch_one(&pool)
+            .await?;
+        pool.close().await;
+        Ok(row.0)
+    }
+
+    async fn list_schemas(&self) -> Result<Vec<String>, BoxError> {
+        let mut schemas = Vec::new();
+        if let Ok(entries) = fs::read_dir(&self.dir) {
+            for entry in entries {
+                let path = entry?.path();
+                if path.extension().is_some_and(|ext| ext == "db")
+                    && let Some(stem) = path.file_stem()
+                {
+                    schemas.push(stem.to_string_lossy().into_owned());
+                }
+            }
+        }
+        schemas.sort();
+        Ok(schemas)
+    }
+
+    async fn list_tables(&self, schema: &str) -> Result<Vec<String>, BoxError> {
+        let rows = sqlx::query(
+            "SELECT name FROM sqlite_master WHERE type = 'table' AND name NOT LIKE 'sqlite_%' ORDER BY name",
+        )
+        .fetch_all(&self.pool(schema).await?)
+        .await?;
+        Ok(rows.iter().map(|row| row.get(0)).collect())
+    }
+
+    async fn drop_schema(&self, schema: &str) -> Result<(), BoxError> {
+        if let Some(pool) = self.pools.lock().await.remove(schema) {
+            pool.close().await;
+        }
+        let db_path = self.db_path(schema);
+        if db_path.exists() {
+            fs::remove_file(&db_path)?;
+        }
+        Ok(())
+    }
+
+    // 重新生成 .db 文件
+    async fn prepare_schema(&self, schema: &str) -> Result<(), BoxError> {
+        if let Some(pool) = self.pools.lock().await.remove(schema) {
+            pool.close().await;
+        }
+        let db_path = self.db_path(schema);
+        fs::create_dir_all(&self.dir)?;
+        if db_path.exists() {
+            fs::remove_file(&db_path)?;
+        }
+        let options = SqliteConnectOptions::new()
+            .filename(&db_path)
+            .create_if_missing(true)
+            // 生成快照，不需要回滚日志
+            .journal_mode(SqliteJournalMode::Off)
+            .synchronous(SqliteSynchronous::Off);
+        let pool = SqlitePoolOptions::new()
+            .max_connections(1)
+            .connect_with(options)
+            .await?;
+        self.pools.lock().await.insert(schema.to_string(), pool);
+        Ok(())
+    }
+
+    async fn create_table(
+        &self,
+        schema: &str,
+        table: &TableSchema,
+        _source_ddl: Option<&str>,
+        report: &mut TranslationReport,
+    ) -> Result<TargetTable, BoxError> {
+        let ddl = translate_table_to_sqlite(table, report);
+        let pool = self.pool(schema).await?;
+        sqlx::raw_sql(&format!(
+            "DROP TABLE IF EXISTS {}",
+            quote_ident(&table.name)
+        ))
+        .execute(&pool)
+        .await?;
+        sqlx::raw_sql(&ddl.create_table).execute(&pool).await?;
+        Ok(TargetTable {
+            table: ddl.table,
+            post_data: ddl.post_data,
+            foreign_keys: Vec::new(), // 外键在建表语句中
+        })
+    }
+
+    async fn apply_rows(
+        &self,
+        schema: &str,
+        table: &TableSchema,
+        rows: Vec<RowValues>,
+    ) -> Result<ApplyResult, BoxError> {
+        let values = rows
+            .iter()
+            .map(|row| sqlite_row_values(table, row))
+            .collect::<Vec<_>>()
+            .join(",\n");
+        sqlx::raw_sql(&format!("{}{}", insert_prefix(table), values))
+            .execute(&self.pool(schema).await?)
+            .await?;
+        Ok(ApplyResult {
+            rows: rows.len() as u64,
+            null_values: 0,
+        })
+    }
+
+    async fn execute(&self, schema: &str, sql: &str) -> Result<(), BoxError> {
+        sqlx::raw_sql(sql)
+            .execute(&self.pool(schema).await?)
+            .await?;
+        Ok(())
+    }
+
+    async fn truncate_table(
+        &self,
+        schema: &str,
+        table: &TableSchema,
+    ) -> Result<TargetTable, BoxError> {
+        let ddl = translate_table_to_sqlite(table, &mut TranslationReport::default());
+        sqlx::raw_sql(&format!("DELETE FROM {}", quote_ident(&table.name)))
+            .execute(&self.pool(schema).await?)
+            .await?;
+        Ok(TargetTable::new(ddl.table))
+    }
+
+    async fn finish_schema(&self, schema: &str) -> Result<(), BoxError> {
+        if let Some(pool) = self.pools.lock().await.remove(schema) {
+            pool.close().await;
+        }
+        Ok(())
+    }
+}
+
+// 输出转换报告到 sql/migration_report_{库名}_{时间}.json，返回文件路径
+pub fn write_report(report: &TranslationReport, db_name: &str) -> Result<String, BoxError> {
+    let time_str = Local::now().format("%Y%m%d_%H%M%S").to_string();
+    let path = format!("sql/migration_report_{}_{}.json", db_name, time_str);
+    fs::create_dir_all("sql")?;
+    fs::write(&path, serde_json::to_string_pretty(report)?)?;
+    Ok(path)
+}
+
+// 打印报告摘要
+pub fn print_report(report: &TranslationReport) {
+    println!(
+        "迁移 {} -> {}：{} 个表，{} 行",
+        report.source,
+        report.target,
+        report.tables.len(),
+        report.tables.iter().map(|t| t.rows).sum::<u64>()
+    );
+    for item in &report.warnings {
+        println!("[warn] {}: {}", item.object, item.message);
+    }
+    for item in &report.untranslated {
+        println!("[未转换] {}: {}", item.object, item.message);
+    }
+}
@@ -1 +1,22 @@
+pub mod backup;
+pub mod catalog;
+pub mod charset;
+pub mod compat;
+pub mod conflict;
+pub mod export;
+pub mod expr;
 pub mod help;
+pub mod import;
+pub mod load_data;
+pub mod mask;
+pub mod migrate;
+pub mod mysql_schema;
+pub mod mysql_sync;
+pub mod parallel;
+pub mod pg_help;
+pub mod pg_schema;
+pub mod schema_diff;
+pub mod sync;
+pub mod transform;
+pub mod type_map;
+pub mod users;
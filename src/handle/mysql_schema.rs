@@ -0,0 +1,296 @@
+// 读取MySQL表结构和表数据（跨数据库迁移使用）
+
+use std::collections::BTreeMap;
+
+use futures_util::TryStreamExt;
+use sqlx::{MySql, MySqlConnection, Pool, Row};
+use tokio::sync::mpsc;
+
+use crate::model::schema::{ColumnSchema, ForeignKeySchema, IndexSchema, RowValues, TableSchema};
+
+type BoxError = Box<dyn std::error::Error + Send + Sync>;
+
+// 写入数据时跳过生成列（VIRTUAL/STORED），由目标库计算，写入会报错（3105）
+pub fn stored_columns(mut table: TableSchema) -> TableSchema {
+    table
+        .columns
+        .retain(|c| !c.extra.contains("GENERATED") || c.extra.contains("DEFAULT_GENERATED"));
+    table
+}
+
+// 读取数据库中的表结构，tables 为空时读取所有表；在 conn 上查询，可以是快照事务中的连接
+pub async fn read_tables(
+    conn: &mut MySqlConnection,
+    db_name: &str,
+    tables: &[String],
+) -> Result<Vec<TableSchema>, sqlx::Error> {
+    let rows = sqlx::query(
+        "SELECT t.TABLE_NAME, t.TABLE_COMMENT, t.TABLE_COLLATION, c.CHARACTER_SET_NAME \
+         FROM information_schema.TABLES t \
+         LEFT JOIN information_schema.COLLATION_CHARACTER_SET_APPLICABILITY c \
+           ON c.COLLATION_NAME = t.TABLE_COLLATION \
+         WHERE t.TABLE_SCHEMA = ? AND t.TABLE_TYPE = 'BASE TABLE' ORDER BY t.TABLE_NAME",
+    )
+    .bind(db_name)
+    .fetch_all(&mut *conn)
+    .await?;
+
+    let mut result = Vec::new();
+    for row in rows {
+        let name: String = row.try_get(0)?;
+        if !tables.is_empty() && !tables.contains(&name) {
+            continue;
+        }
+        let comment: Option<String> = row.try_get(1)?;
+        let mut table = TableSchema {
+            columns: read_columns(conn, db_name, &name).await?,
+            primary_key: Vec::new(),
+            indexes: Vec::new(),
+            foreign_keys: read_foreign_keys(conn, db_name, &name).await?,
+            comment: comment.filter(|c| !c.is_empty()),
+            collation: row.try_get(2)?,
+            charset: row.try_get(3)?,
+            name,
+        };
+        for index in read_indexes(conn, db_name, &table.name).await? {
+            if index.name == "PRIMARY" {
+                table.primary_key = index.columns;
+            } else {
+                table.indexes.push(index);
+            }
+        }
+        result.push(table);
+    }
+    Ok(result)
+}
+
+async fn read_columns(
+    conn: &mut MySqlConnection,
+    db_name: &str,
+    table_name: &str,
+) -> Result<Vec<ColumnSchema>, sqlx::Error> {
+    let rows = sqlx::query(
+        "SELECT COLUMN_NAME, DATA_TYPE, COLUMN_TYPE, IS_NULLABLE, COLUMN_DEFAULT, EXTRA, \
+                CHARACTER_SET_NAME, COLLATION_NAME, COLUMN_COMMENT, \
+                CAST(CHARACTER_MAXIMUM_LENGTH AS UNSIGNED), CAST(NUMERIC_PRECISION AS UNSIGNED), \
+                CAST(NUMERIC_SCALE AS UNSIGNED), CAST(DATETIME_PRECISION AS UNSIGNED) \
+         FROM information_schema.COLUMNS \
+         WHERE TABLE_SCHEMA = ? AND TABLE_NAME = ? ORDER BY ORDINAL_POSITION",
+    )
+    .bind(db_name)
+    .bind(table_name)
+    .fetch_all(&mut *conn)
+    .await?;
+
+    let mut columns = Vec::new();
+    for row in rows {
+        let extra: String = row.try_get(5)?;
+        let comment: Option<String> = row.try_get(8)?;
+        columns.push(ColumnSchema {
+            name: row.try_get(0)?,
+            data_type: row.try_get::<String, _>(1)?.to_lowercase(),
+            column_type: row.try_get::<String, _>(2)?.to_lowercase(),
+            nullable: row.try_get::<String, _>(3)? == "YES",
+            default: row.try_get(4)?,
+            auto_increment: extra.contains("auto_increment"),
+            extra,
+            charset: row.try_get(6)?,
+            collation: row.try_get(7)?,
+            comment: comment.filter(|c| !c.is_empty()),
+            char_length: row.try_get(9)?,
+            numeric_precision: row.try_get(10)?,
+            numeric_scale: row.try_get(11)?,
+            datetime_precision: row.try_get(12)?,
+        });
+    }
+    Ok(columns)
+}
+
+async fn read_indexes(
+    conn: &mut MySqlConnection,
+    db_name: &str,
+    table_name: &str,
+) -> Result<Vec<IndexSchema>, sqlx::Error> {
+    let rows = sqlx::query(
+        "SELECT INDEX_NAME, COLUMN_NAME, CAST(NON_UNIQUE AS SIGNED), INDEX_TYPE, \
+                CAST(SUB_PART AS UNSIGNED) \
+         FROM information_schema.STATISTICS \
+         WHERE TABLE_SCHEMA = ? AND TABLE_NAME = ? ORDER BY INDEX_NAME, SEQ_IN_INDEX",
+    )
+    .bind(db_name)
+    .bind(table_name)
+    .fetch_all(&mut *conn)
+    .await?;
+
+    let mut indexes: BTreeMap<String, IndexSchema> = BTreeMap::new();
+    for row in rows {
+        let name: String = row.try_get(0)?;
+        // 函数索引没有列名
+        let column: Option<String> = row.try_get(1)?;
+        let non_unique: i64 = row.try_get(2)?;
+        let index_type: String = row.try_get(3)?;
+        let sub_part: Option<u64> = row.try_get(4)?;
+        let index = indexes.entry(name.clone()).or_insert_with(|| IndexSchema {
+            name,
+            columns: Vec::new(),
+            unique: non_unique == 0,
+            index_type,
+            sub_parts: Vec::new(),
+        });
+        index.columns.push(column.unwrap_or_default());
+        index.sub_parts.push(sub_part);
+    }
+    Ok(indexes.into_values().collect())
+}
+
+async fn read_foreign_keys(
+    conn: &mut MySqlConnection,
+    db_name: &str,
+    table_name: &str,
+) -> Result<Vec<ForeignKeySchema>, sqlx::Error> {
+    let rows = sqlx::query(
+        "SELECT k.CONSTRAINT_NAME, k.COLUMN_NAME, k.REFERENCED_TABLE_NAME, k.REFERENCED_COLUMN_NAME, \
+                r.UPDATE_RULE, r.DELETE_RULE \
+         FROM information_schema.KEY_COLUMN_USAGE k \
+         JOIN information_schema.REFERENTIAL_CONSTRAINTS r \
+           ON r.CONSTRAINT_SCHEMA = k.CONSTRAINT_SCHEMA AND r.CONSTRAINT_NAME = k.CONSTRAINT_NAME \
+          AND r.TABLE_NAME = k.TABLE_NAME \
+         WHERE k.TABLE_SCHEMA = ? AND k.TABLE_NAME = ? AND k.REFERENCED_TABLE_NAME IS NOT NULL \
+         ORDER BY k.CONSTRAINT_NAME, k.ORDINAL_POSITION",
+    )
+    .bind(db_name)
+    .bind(table_name)
+    .fetch_all(&mut *conn)
+    .await?;
+
+    let mut foreign_keys: BTreeMap<String, ForeignKeySchema> = BTreeMap::new();
+    for row in rows {
+        let name: String = row.try_get(0)?;
+        let fk = foreign_keys
+            .entry(name.clone())
+            .or_insert(ForeignKeySchema {
+                name,
+                columns: Vec::new(),
+                ref_table: row.try_get(2)?,
+                ref_columns: Vec::new(),
+                on_update: row.try_get(4)?,
+                on_delete: row.try_get(5)?,
+            });
+        fk.columns.push(row.try_get(1)?);
+        fk.ref_columns.push(row.try_get(3)?);
+    }
+    Ok(foreign_keys.into_values().collect())
+}
+
+// 视图、触发器、存储过程/函数、事件，返回 (类型, 名称)
+pub async fn read_other_objects(
+    pool: &Pool<MySql>,
+    db_name: &str,
+) -> Result<Vec<(String, String)>, sqlx::Error> {
+    let rows = sqlx::query(
+        "SELECT 'VIEW', TABLE_NAME FROM information_schema.VIEWS WHERE TABLE_SCHEMA = ? \
+         UNION ALL SELECT 'TRIGGER', TRIGGER_NAME FROM information_schema.TRIGGERS WHERE TRIGGER_SCHEMA = ? \
+         UNION ALL SELECT ROUTINE_TYPE, ROUTINE_NAME FROM information_schema.ROUTINES WHERE ROUTINE_SCHEMA = ? \
+         UNION ALL SELECT 'EVENT', EVENT_NAME FROM information_schema.EVENTS WHERE EVENT_SCHEMA = ?",
+    )
+    .bind(db_name)
+    .bind(db_name)
+    .bind(db_name)
+    .bind(db_name)
+    .fetch_all(pool)
+    .await?;
+    rows.iter()
+        .map(|row| Ok((row.try_get(0)?, row.try_get(1)?)))
+        .collect()
+}
+
+// 逐批读取表数据（where_clause 为过滤条件），每读满 batch_size 行发送到 tx，返回总行数
+// 使用文本协议（raw_sql）读取，所有类型的值都是文本形式，由目标库按列类型转换
+// conn 为连接池中的连接，或一致性快照中的连接（库内并行导出）
+pub async fn read_rows(
+    conn: &mut MySqlConnection,
+    db_name: &str,
+    table: &TableSchema,
+    where_clause: Option<&str>,
+    batch_size: usize,
+    tx: mpsc::Sender<Vec<RowValues>>,
+) -> Result<u64, BoxError> {
+    let columns = table
+        .columns
+        .iter()
+        .map(|c| quote_ident(&c.name))
+        .collect::<Vec<_>>()
+        .join(", ");
+    let mut sql = format!(
+        "SELECT {} FROM {}.{}",
+        columns,
+        quote_ident(db_name),
+        quote_ident(&table.name)
+    );
+    if let Some(where_clause) = where_clause {
+        sql.push_str(&format!(" WHERE ({})", where_clause));
+    }
+
+    let mut stream = sqlx::raw_sql(&sql).fetch(&mut *conn);
+    let mut batch = Vec::with_capacity(batch_size);
+    let mut total = 0u64;
+    while let Some(row) = stream.try_next().await? {
+        let mut values = Vec::with_capacity(table.columns.len());
+        for i in 0..table.columns.len() {
+            values.push(row.try_get_unchecked::<Option<Vec<u8>>, _>(i)?);
+        }
+        batch.push(values);
+        if batch.len() >= batch_size {
+            total += batch.len() as u64;
+            let full = std::mem::replace(&mut batch, Vec::with_capacity(batch_size));
+            tx.send(full).await.map_err(|_| "目标库写入已停止")?;
+        }
+    }
+    if !batch.is_empty() {
+        total += batch.len() as u64;
+        tx.send(batch).await.map_err(|_| "目标库写入已停止")?;
+    }
+    Ok(total)
+}
+
+// 标识符加反引号
+pub fn quote_ident(name: &str) -> String {
+    format!("`{}`", name.replace('`', "``"))
+}
+
+#[cfg(test)]
+mod test_mysql_schema {
+    use super::*;
+
+    #[test]
+    fn test_stored_columns() {
+        let mut total = ColumnSchema::new("total", "int", "int");
+        total.extra = "STORED GENERATED".to_string();
+        let mut label = ColumnSchema::new("label", "varchar", "varchar(20)");
+        label.extra = "VIRTUAL GENERATED".to_string();
+        let mut created_at = ColumnSchema::new("created_at", "datetime", "datetime");
+        created_at.extra = "DEFAULT_GENERATED".to_string();
+        let table = TableSchema {
+            name: "orders".to_string(),
+            columns: vec![
+                ColumnSchema::new("id", "int", "int"),
+                total,
+                label,
+                created_at,
+            ],
+            primary_key: vec!["id".to_string()],
+            indexes: Vec::new(),
+            foreign_keys: Vec::new(),
+            comment: None,
+            charset: None,
+            collation: None,
+        };
+        let names = stored_columns(table)
+            .columns
+            .into_iter()
+            .map(|c| c.name)
+            .collect::<Vec<_>>();
+        // 默认值为表达式的列仍然写入
+        assert_eq!(names, vec!["id", "created_at"]);
+    }
+}
@@ -0,0 +1,323 @@
+// MySQL的同步源/目标实现，连接池与 MysqlHelp 共用
+
+use std::{any::Any, sync::Arc};
+
+use async_trait::async_trait;
+use sqlx::{MySql, Pool, Row};
+use tokio::sync::mpsc;
+
+use crate::{
+    db::mysql_db::get_mysql_db_pool,
+    handle::{
+        charset::{TargetCharset, convert_tables},
+        compat::{DdlRewrite, ddl_rewrite},
+        help::{
+            MysqlHelp, execute_without_fk_checks, query_all_databases, query_mysql_version,
+            query_server_id,
+        },
+        mysql_schema::{quote_ident, read_other_objects, read_rows, read_tables, stored_columns},
+        sync::{ApplyResult, BoxError, SyncSource, SyncTarget, TargetTable, pool_name},
+        type_map::{TranslationReport, mysql_row_values, translate_table_to_mysql},
+    },
+    model::{
+        job::{Source, Target},
+        schema::{RowValues, TableSchema},
+    },
+};
+
+// MySQL系统库，整库同步时跳过
+pub const SYSTEM_DATABASES: [&str; 4] =
+    ["information_schema", "mysql", "performance_schema", "sys"];
+
+#[derive(Clone, Debug)]
+pub struct MysqlSource {
+    pub pool: Arc<Pool<MySql>>,
+    pub source: Source, // 连接信息和按表过滤条件
+}
+
+impl MysqlSource {
+    pub fn new(pool: Arc<Pool<MySql>>, source: Source) -> Self {
+        MysqlSource { pool, source }
+    }
+
+    // 创建（或复用）源数据库连接池
+    pub async fn connect(job_name: &str, source: &Source) -> Result<Self, String> {
+        let pool = get_mysql_db_pool(
+            &source.dsn("mysql", None),
+            &pool_name("source", job_name, source.db_name.as_deref()),
+        )
+        .await?;
+        Ok(MysqlSource::new(pool, source.clone()))
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct MysqlTarget {
+    pub pool: Arc<Pool<MySql>>,
+    pub address: String,
+    pub charset: Option<TargetCharset>, // 建表后转换为 [target] charset/collation
+    pub rewrite: DdlRewrite,            // 源库建表语句中目标库不支持的部分
+}
+
+impl MysqlTarget {
+    pub fn new(pool: Arc<Pool<MySql>>, address: String) -> Self {
+        MysqlTarget {
+            pool,
+            address,
+            charset: None,
+            rewrite: DdlRewrite::default(),
+        }
+    }
+
+    // 创建（或复用）目标数据库连接池
+    pub async fn connect(job_name: &str, target: &Target) -> Result<Self, String> {
+        let pool = get_mysql_db_pool(
+            &target.dsn("mysql", None),
+            &pool_name("target", job_name, target.db_name.as_deref()),
+        )
+        .await?;
+        let mut mysql_target = MysqlTarget::new(pool, format!("{}:{}", target.host, target.port));
+        mysql_target.charset = TargetCharset::from_target(target);
+        mysql_target.rewrite = ddl_rewrite(&mysql_target.pool, target)
+            .await
+            .map_err(|e| e.to_string())?;
+        Ok(mysql_target)
+    }
+}
+
+impl MysqlHelp {
+    // 由同步源/目标的连接池创建（mysqldump 方式同步）
+    pub fn from_endpoints(source: &MysqlSource, target: &MysqlTarget) -> Self {
+        MysqlHelp::new(source.pool.clone(), target.pool.clone())
+    }
+}
+
+async fn list_schemas(pool: &Pool<MySql>) -> Result<Vec<String>, BoxError> {
+    Ok(query_all_databases(pool)
+        .await?
+        .into_iter()
+        .filter(|db| !SYSTEM_DATABASES.contains(&db.as_str()))
+        .collect())
+}
+
+async fn list_tables(pool: &Pool<MySql>, schema: &str) -> Result<Vec<String>, BoxError> {
+    let rows = sqlx::query(
+        "SELECT TABLE_NAME FROM information_schema.TABLES \
+         WHERE TABLE_SCHEMA = ? AND TABLE_TYPE = 'BASE TABLE' ORDER BY TABLE_NAME",
+    )
+    .bind(schema)
+    .fetch_all(pool)
+    .await?;
+    Ok(rows
+        .iter()
+        .map(|row| row.try_get(0))
+        .collect::<Result<_, _>>()?)
+}
+
+#[async_trait]
+impl SyncSource for MysqlSource {
+    fn engine(&self) -> &'static str {
+        "mysql"
+    }
+
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+
+    fn describe(&self, schema: &str) -> String {
+        format!(
+            "mysql://{}:{}/{}",
+            self.source.host, self.source.port, schema
+        )
+    }
+
+    async fn version(&self) -> Result<String, BoxError> {
+        Ok(query_mysql_version(&self.pool).await?)
+    }
+
+    async fn server_id(&self) -> Result<String, BoxError> {
+        Ok(query_server_id(&self.pool).await?)
+    }
+
+    async fn list_schemas(&self) -> Result<Vec<String>, BoxError> {
+        list_schemas(&self.pool).await
+    }
+
+    async fn list_tables(&self, schema: &str) -> Result<Vec<String>, BoxError> {
+        list_tables(&self.pool, schema).await
+    }
+
+    async fn read_tables(
+        &self,
+        schema: &str,
+        tables: &[String],
+    ) -> Result<Vec<TableSchema>, BoxError> {
+        Ok(read_tables(&mut *self.pool.acquire().await?, schema, tables).await?)
+    }
+
+    async fn read_ddl(&self, schema: &str, table: &str) -> Result<String, BoxError> {
+        let row = sqlx::query(&format!(
+            "SHOW CREATE TABLE {}.{}",
+            quote_ident(schema),
+            quote_ident(table)
+        ))
+        .fetch_one(&*self.pool)
+        .await?;
+        Ok(row.try_get(1)?)
+    }
+
+    async fn read_other_objects(&self, schema: &str) -> Result<Vec<(String, String)>, BoxError> {
+        Ok(read_other_objects(&self.pool, schema).await?)
+    }
+
+    async fn stream_rows(
+        &self,
+        schema: &str,
+        table: &TableSchema,
+        batch_size: usize,
+        tx: mpsc::Sender<Vec<RowValues>>,
+    ) -> Result<u64, BoxError> {
+        let where_clause = self.source.table_filter(schema, &table.name);
+        if let Some(where_clause) = where_clause {
+            println!("[filter] {}.{} WHERE {}", schema, table.name, where_clause);
+        }
+        let mut conn = self.pool.acquire().await?;
+        read_rows(&mut conn, schema, table, where_clause, batch_size, tx).await
+    }
+}
+
+#[async_trait]
+impl SyncTarget for MysqlTarget {
+    fn engine(&self) -> &'static str {
+        "mysql"
+    }
+
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+
+    fn describe(&self, schema: &str) -> String {
+        format!("mysql://{}/{}", self.address, schema)
+    }
+
+    async fn version(&self) -> Result<String, BoxError> {
+        Ok(query_mysql_version(&self.pool).await?)
+    }
+
+    async fn server_id(&self) -> Result<String, BoxError> {
+        Ok(query_server_id(&self.pool).await?)
+    }
+
+    async fn list_schemas(&self) -> Result<Vec<String>, BoxError> {
+        list_schemas(&self.pool).await
+    }
+
+    async fn list_tables(&self, schema: &str) -> Result<Vec<String>, BoxError> {
+        list_tables(&self.pool, schema).await
+    }
+
+    async fn drop_schema(&self, schema: &str) -> Result<(), BoxError> {
+        sqlx::raw_sql(&format!("DROP DATABASE IF EXISTS {}", quote_ident(schema)))
+            .execute(&*self.pool)
+            .await?;
+        Ok(())
+    }
+
+    async fn prepare_schema(&self, schema: &str) -> Result<(), BoxError> {
+        sqlx::raw_sql(&format!(
+            "CREATE DATABASE IF NOT EXISTS {}",
+            quote_ident(schema)
+        ))
+        .execute(&*self.pool)
+        .await?;
+        Ok(())
+    }
+
+    async fn create_table(
+        &self,
+        schema: &str,
+        table: &TableSchema,
+        source_ddl: Option<&str>,
+        report: &mut TranslationReport,
+    ) -> Result<TargetTable, BoxError> {
+        let ddl = match source_ddl {
+            Some(ddl) => self.rewrite.apply(ddl),
+            None => translate_table_to_mysql(table, report),
+        };
+        // 建表语句的表名不带库名，在同一个连接上切换到目标库执行
+        let sql = format!(
+            "USE {}; DROP TABLE IF EXISTS {}; {}",
+            quote_ident(schema),
+            quote_ident(&table.name),
+            ddl
+        );
+        execute_without_fk_checks(&self.pool, &sql).await?;
+        if let Some(charset) = &self.charset {
+            convert_tables(
+                &self.pool,
+                schema,
+                std::slice::from_ref(&table.name),
+                charset,
+            )
+            .await?;
+        }
+        // 源库的建表语句保留生成列，写入时跳过；按表结构建表时生成列已转换为普通列
+        if source_ddl.is_some() {
+            return Ok(TargetTable::new(stored_columns(table.clone())));
+        }
+        Ok(TargetTable::new(table.clone()))
+    }
+
+    async fn apply_rows(
+        &self,
+        schema: &str,
+        table: &TableSchema,
+        rows: Vec<RowValues>,
+    ) -> Result<ApplyResult, BoxError> {
+        let columns = table
+            .columns
+            .iter()
+            .map(|c| quote_ident(&c.name))
+            .collect::<Vec<_>>()
+            .join(", ");
+        let values = rows
+            .iter()
+            .map(|row| mysql_row_values(table, row))
+            .collect::<Vec<_>>()
+            .join(",\n");
+        // 表之间的导入顺序不保证满足外键
+        let sql = format!(
+            "INSERT INTO {}.{} ({}) VALUES {}",
+            quote_ident(schema),
+            quote_ident(&table.name),
+            columns,
+            values
+        );
+        execute_without_fk_checks(&self.pool, &sql).await?;
+        Ok(ApplyResult {
+            rows: rows.len() as u64,
+            null_values: 0,
+        })
+    }
+
+    async fn execute(&self, schema: &str, sql: &str) -> Result<(), BoxError> {
+        let sql = format!("USE {}; {}", quote_ident(schema), sql);
+        sqlx::raw_sql(&sql).execute(&*self.pool).await?;
+        Ok(())
+    }
+
+    async fn truncate_table(
+        &self,
+        schema: &str,
+        table: &TableSchema,
+    ) -> Result<TargetTable, BoxError> {
+        let sql = format!(
+            "TRUNCATE TABLE {}.{}",
+            quote_ident(schema),
+            quote_ident(&table.name)
+        );
+        execute_without_fk_checks(&self.pool, &sql).await?;
+        // 目标库中已有的表，生成列由目标库计算
+        Ok(TargetTable::new(stored_columns(table.clone())))
+    }
+}
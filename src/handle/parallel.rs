@@ -0,0 +1,548 @@
+// 库内并行导出导入（类似 mydumper/myloader）：[job] parallel 个工作连接在同一个一致性快照中
+// 按表、按主键范围分块读取，每个工作连接使用独立的目标库连接写入
+
+use std::{
+    collections::{HashMap, VecDeque},
+    sync::{
+        Arc, Mutex,
+        atomic::{AtomicUsize, Ordering},
+    },
+};
+
+use sqlx::{Connection, Executor, MySql, MySqlConnection, Pool, Row};
+use tokio::sync::mpsc;
+
+use crate::{
+    handle::{
+        help::query_base_tables,
+        load_data::{LOAD_DATA_ROWS, LoadOptions, load_data_sql, load_file, write_tsv_row},
+        mysql_schema::{quote_ident, read_rows, read_tables, stored_columns},
+        sync::{BoxError, SyncOptions},
+        type_map::mysql_row_values,
+    },
+    model::{
+        job::{Source, Target},
+        schema::{RowValues, TableSchema, ValueKind},
+    },
+    util::progress::{self, DbProgress},
+};
+
+// 每条INSERT语句的行数
+const INSERT_BATCH_SIZE: usize = 500;
+
+// 一个导入任务：tables 中第 table 个表的一个主键范围（None 为整个表）
+#[derive(Debug, Clone)]
+struct Chunk {
+    table: usize,
+    where_clause: Option<String>,
+    index: usize, // 第几块，从 1 开始
+    count: usize, // 这个表共几块
+}
+
+// 库内并行或 LOAD DATA 导入（parallel = 1 时一个工作连接）时，表数据不经过 mysqldump
+pub fn native_load(options: &SyncOptions, target: &Target) -> bool {
+    options.data && (options.parallel > 1 || target.load_data.unwrap_or(false))
+}
+
+// 打开 workers 个工作连接，在同一个时间点开始一致性快照事务：
+// FLUSH TABLES WITH READ LOCK 期间所有连接执行 START TRANSACTION WITH CONSISTENT SNAPSHOT，
+// 快照开始后立即解锁，只在开始快照事务时短暂阻塞源库的写入
+// 加锁失败时任务失败，除非配置了 unlocked_snapshot（各工作连接的快照时间点可能不同）
+pub async fn open_snapshot(
+    source: &Source,
+    workers: usize,
+    unlocked_snapshot: bool,
+) -> Result<Vec<MySqlConnection>, BoxError> {
+    let dsn = source.dsn("mysql", None);
+    // 先建立连接，缩短持有全局读锁的时间
+    let mut lock = MySqlConnection::connect(&dsn).await?;
+    let mut connections = Vec::with_capacity(workers);
+    for _ in 0..workers {
+        connections.push(MySqlConnection::connect(&dsn).await?);
+    }
+    // 需要 RELOAD 权限（云数据库通常没有）
+    let lock = match execute(&mut lock, "FLUSH TABLES WITH READ LOCK").await {
+        Ok(_) => Some(lock),
+        Err(e) if unlocked_snapshot => {
+            eprintln!(
+                "[warn] FLUSH TABLES WITH READ LOCK 失败（{}），unlocked_snapshot = true，各工作连接的快照不保证一致",
+                e
+            );
+            lock.close().await?;
+            None
+        }
+        Err(e) => {
+            return Err(format!(
+                "FLUSH TABLES WITH READ LOCK 失败（{}），各工作连接的快照不能保证一致；\
+                 可接受不一致的快照时配置 [job] unlocked_snapshot = true",
+                e
+            )
+            .into());
+        }
+    };
+    for conn in connections.iter_mut() {
+        execute(
+            conn,
+            "SET SESSION TRANSACTION ISOLATION LEVEL REPEATABLE READ; \
+             START TRANSACTION WITH CONSISTENT SNAPSHOT",
+        )
+        .await?;
+    }
+    if let Some(mut lock) = lock {
+        execute(&mut lock, "UNLOCK TABLES").await?;
+        lock.close().await?;
+    }
+    println!("[snapshot] {} 个工作连接已开始一致性快照", workers);
+    Ok(connections)
+}
+
+// 把主键范围 [min, max] 按估计行数 rows 分成每块约 chunk_rows 行的区间
+pub fn split_range(min: i128, max: i128, rows: u64, chunk_rows: u64) -> Vec<(i128, i128)> {
+    if max < min {
+        return Vec::new();
+    }
+    let chunks = rows.div_ceil(chunk_rows.max(1)).max(1) as i128;
+    let span = max - min + 1;
+    let step = ((span + chunks - 1) / chunks).max(1);
+    let mut ranges = Vec::new();
+    let mut start = min;
+    while start <= max {
+        let end = (start + step - 1).min(max);
+        ranges.push((start, end));
+        start = end + 1;
+    }
+    ranges
+}
+
+// 估计行数（information_schema.TABLES.TABLE_ROWS），用于分块和安排导入顺序
+async fn estimate_rows(
+    pool: &Pool<MySql>,
+    db_name: &str,
+) -> Result<HashMap<String, u64>, sqlx::Error> {
+    let rows = sqlx::query(
+        "SELECT TABLE_NAME, CAST(IFNULL(TABLE_ROWS, 0) AS UNSIGNED) FROM information_schema.TABLES \
+         WHERE TABLE_SCHEMA = ? AND TABLE_TYPE = 'BASE TABLE'",
+    )
+    .bind(db_name)
+    .fetch_all(pool)
+    .await?;
+    rows.iter()
+        .map(|row| Ok((row.try_get(0)?, row.try_get(1)?)))
+        .collect()
+}
+
+// 单列整数主键、估计行数超过 chunk_rows 的表按主键范围分块，在快照连接上查询主键的最小值和最大值
+async fn plan_table(
+    conn: &mut MySqlConnection,
+    db_name: &str,
+    table: &TableSchema,
+    filter: Option<&str>,
+    rows: u64,
+    chunk_rows: u64,
+) -> Result<Vec<Option<String>>, BoxError> {
+    let whole = vec![filter.map(|f| f.to_string())];
+    let [pk] = table.primary_key.as_slice() else {
+        return Ok(whole);
+    };
+    let integer = table
+        .columns
+        .iter()
+        .any(|c| &c.name == pk && c.kind() == ValueKind::Integer);
+    if !integer || rows <= chunk_rows {
+        return Ok(whole);
+    }
+    let mut sql = format!(
+        "SELECT CAST(MIN({pk}) AS CHAR), CAST(MAX({pk}) AS CHAR) FROM {}.{}",
+        quote_ident(db_name),
+        quote_ident(&table.name),
+        pk = quote_ident(pk)
+    );
+    if let Some(filter) = filter {
+        sql.push_str(&format!(" WHERE ({})", filter));
+    }
+    let row = Executor::fetch_one(&mut *conn, sqlx::raw_sql(&sql)).await?;
+    let bound = |i: usize| -> Result<Option<i128>, BoxError> {
+        let value = row.try_get_unchecked::<Option<Vec<u8>>, _>(i)?;
+        Ok(value.and_then(|v| String::from_utf8_lossy(&v).parse().ok()))
+    };
+    let (Some(min), Some(max)) = (bound(0)?, bound(1)?) else {
+        return Ok(whole);
+    };
+    Ok(split_range(min, max, rows, chunk_rows)
+        .into_iter()
+        .map(|(start, end)| {
+            let range = format!("{} BETWEEN {} AND {}", quote_ident(pk), start, end);
+            Some(match filter {
+                Some(filter) => format!("({}) AND {}", filter, range),
+                None => range,
+            })
+        })
+        .collect())
+}
+
+// 快照中导入的计划：表结构、分块队列和各表的分块数
+pub struct CopyPlan {
+    schemas: Vec<TableSchema>,
+    queue: VecDeque<Chunk>,
+    remaining: Vec<AtomicUsize>,
+}
+
+// 规划导入 db_name 中的表数据（tables 为空时为所有表），表定义和分块边界在快照连接 conn 上读取
+pub async fn plan_copy(
+    source_pool: &Pool<MySql>,
+    source: &Source,
+    db_name: &str,
+    tables: &[String],
+    options: &SyncOptions,
+    conn: &mut MySqlConnection,
+) -> Result<CopyPlan, BoxError> {
+    let tables = if tables.is_empty() {
+        query_base_tables(source_pool, db_name).await?
+    } else {
+        tables.to_vec()
+    };
+    let tables = tables
+        .into_iter()
+        .filter(|t| !options.ignore_tables.contains(t))
+        .collect::<Vec<_>>();
+    if tables.is_empty() {
+        return Ok(CopyPlan {
+            schemas: Vec::new(),
+            queue: VecDeque::new(),
+            remaining: Vec::new(),
+        });
+    }
+    let schemas = read_tables(conn, db_name, &tables)
+        .await?
+        .into_iter()
+        .map(stored_columns)
+        .collect::<Vec<_>>();
+    let estimates = estimate_rows(source_pool, db_name).await?;
+
+    // 大表先导入，同一个表的分块相邻，由多个工作连接同时导入
+    let mut order = (0..schemas.len()).collect::<Vec<_>>();
+    order
+        .sort_by_key(|i| std::cmp::Reverse(estimates.get(&schemas[*i].name).copied().unwrap_or(0)));
+    let mut queue = VecDeque::new();
+    // 各表还未导入完成的分块数，归零时这个表完成
+    let remaining = (0..schemas.len())
+        .map(|_| AtomicUsize::new(0))
+        .collect::<Vec<_>>();
+    for i in order {
+        let table = &schemas[i];
+        let filter = source.table_filter(db_name, &table.name);
+        if let Some(filter) = filter {
+            println!("[filter] {}.{} WHERE {}", db_name, table.name, filter);
+        }
+        let rows = estimates.get(&table.name).copied().unwrap_or(0);
+        let ranges = plan_table(conn, db_name, table, filter, rows, options.chunk_rows).await?;
+        let count = ranges.len();
+        remaining[i].store(count, Ordering::Relaxed);
+        for (index, where_clause) in ranges.into_iter().enumerate() {
+            queue.push_back(Chunk {
+                table: i,
+                where_clause,
+                index: index + 1,
+                count,
+            });
+        }
+    }
+    Ok(CopyPlan {
+        schemas,
+        queue,
+        remaining,
+    })
+}
+
+// 按计划并行导入到目标库 target_db_name 中已存在的表，返回总行数
+// merge 模式用 REPLACE 覆盖主键/唯一键相同的行
+pub async fn parallel_copy(
+    plan: CopyPlan,
+    connections: Vec<MySqlConnection>,
+    target: &Target,
+    db_name: &str,
+    target_db_name: &str,
+    options: &SyncOptions,
+) -> Result<u64, BoxError> {
+    let CopyPlan {
+        schemas,
+        queue,
+        remaining,
+    } = plan;
+    if schemas.is_empty() {
+        return Ok(0);
+    }
+    println!(
+        "[parallel] {}：{} 个表，{} 个分块，{} 个工作连接",
+        db_name,
+        schemas.len(),
+        queue.len(),
+        connections.len()
+    );
+
+    let queue = Arc::new(Mutex::new(queue));
+    let schemas = Arc::new(schemas);
+    let remaining = Arc::new(remaining);
+    let progress = progress::find(db_name);
+    let mut workers = Vec::new();
+    for conn in connections {
+        let worker = Worker {
+            target: target.clone(),
+            db_name: db_name.to_string(),
+            target_db_name: target_db_name.to_string(),
+            tables: schemas.clone(),
+            queue: queue.clone(),
+            replace: options.merge,
+            load: LoadOptions::from_target(target),
+            id: workers.len(),
+            remaining: remaining.clone(),
+            progress: progress.clone(),
+        };
+        let queue = queue.clone();
+        workers.push(tokio::spawn(async move {
+            let result = worker.run(conn).await;
+            if result.is_err() {
+                // 一个工作连接失败时其它连接不再领取新的分块
+                queue.lock().unwrap().clear();
+            }
+            result
+        }));
+    }
+    let mut total = 0;
+    let mut error = None;
+    for worker in workers {
+        match worker.await {
+            Ok(Ok(rows)) => total += rows,
+            Ok(Err(e)) => error = error.or(Some(e)),
+            Err(e) => error = error.or(Some(e.into())),
+        }
+    }
+    match error {
+        Some(e) => Err(e),
+        None => Ok(total),
+    }
+}
+
+struct Worker {
+    target: Target,
+    db_name: String,
+    target_db_name: String,
+    tables: Arc<Vec<TableSchema>>,
+    queue: Arc<Mutex<VecDeque<Chunk>>>,
+    replace: bool,
+    load: LoadOptions,
+    id: usize, // 工作连接编号，用于 LOAD DATA 的文件名
+    remaining: Arc<Vec<AtomicUsize>>,
+    progress: Option<Arc<DbProgress>>,
+}
+
+impl Worker {
+    // 依次领取分块：从快照连接 source_conn 读取，边读边写入目标库，返回写入的总行数
+    async fn run(&self, mut source_conn: MySqlConnection) -> Result<u64, BoxError> {
+        let mut target_conn = MySqlConnection::connect(&self.target.dsn("mysql", None)).await?;
+        let session_sql = self.load.session_sql();
+        if !session_sql.is_empty() {
+            execute(&mut target_conn, &session_sql).await?;
+        }
+        let mut load_data = self.load.load_data;
+        if load_data {
+            let row = Executor::fetch_one(
+                &mut target_conn,
+                "SELECT CAST(@@GLOBAL.local_infile AS SIGNED)",
+            )
+            .await?;
+            if row.try_get::<i64, _>(0)? == 0 {
+                eprintln!("[warn] 目标库 local_infile = OFF，改用多行 INSERT 导入");
+                load_data = false;
+            }
+        }
+        let mut total = 0;
+        loop {
+            let Some(chunk) = self.queue.lock().unwrap().pop_front() else {
+                break;
+            };
+            let table = &self.tables[chunk.table];
+            let (tx, rx) = mpsc::channel::<Vec<RowValues>>(2);
+            let read = read_rows(
+                &mut source_conn,
+                &self.db_name,
+                table,
+                chunk.where_clause.as_deref(),
+                INSERT_BATCH_SIZE,
+                tx,
+            );
+            let write = async {
+                if load_data {
+                    self.load_rows(&mut target_conn, table, &mut load_data, rx)
+                        .await
+                } else {
+                    write_rows(
+                        &mut target_conn,
+                        &self.target_db_name,
+                        table,
+                        self.replace,
+                        rx,
+                    )
+                    .await
+                }
+            };
+            let (_, rows) = tokio::try_join!(read, write)?;
+            if chunk.count > 1 {
+                println!(
+                    "[ok] 表 {}.{} 第 {}/{} 块导入完成，{} 行",
+                    self.db_name, table.name, chunk.index, chunk.count, rows
+                );
+            } else {
+                println!(
+                    "[ok] 表 {}.{} 导入完成，{} 行",
+                    self.db_name, table.name, rows
+                );
+            }
+            total += rows;
+            if let Some(progress) = &self.progress {
+                progress.rows.fetch_add(rows, Ordering::Relaxed);
+                if self.remaining[chunk.table].fetch_sub(1, Ordering::Relaxed) == 1 {
+                    progress.tables_done.fetch_add(1, Ordering::Relaxed);
+                }
+            }
+        }
+        // 快照事务只读，结束后提交
+        execute(&mut source_conn, "COMMIT").await?;
+        source_conn.close().await?;
+        target_conn.close().await?;
+        Ok(total)
+    }
+}
+
+impl Worker {
+    // 每 LOAD_DATA_ROWS 行写成TSV用 LOAD DATA LOCAL INFILE 导入，返回导入的行数
+    async fn load_rows(
+        &self,
+        conn: &mut MySqlConnection,
+        table: &TableSchema,
+        load_data: &mut bool,
+        mut rx: mpsc::Receiver<Vec<RowValues>>,
+    ) -> Result<u64, BoxError> {
+        let mut pending: Vec<RowValues> = Vec::new();
+        let mut rows = 0u64;
+        while let Some(batch) = rx.recv().await {
+            pending.extend(batch);
+            if pending.len() >= LOAD_DATA_ROWS {
+                rows += self.flush(conn, table, load_data, &mut pending).await?;
+            }
+        }
+        if !pending.is_empty() {
+            rows += self.flush(conn, table, load_data, &mut pending).await?;
+        }
+        Ok(rows)
+    }
+
+    // LOCAL INFILE 被禁用时（load_data 改为 false）这一批和之后的数据用多行 INSERT 写入
+    async fn flush(
+        &self,
+        conn: &mut MySqlConnection,
+        table: &TableSchema,
+        load_data: &mut bool,
+        pending: &mut Vec<RowValues>,
+    ) -> Result<u64, BoxError> {
+        if *load_data {
+            // 数据只在内存中，通过标准输入交给 mysql 客户端，不写入磁盘
+            let mut buf = Vec::new();
+            for row in pending.iter() {
+                write_tsv_row(&mut buf, row);
+            }
+            let sql = [
+                self.load.session_sql(),
+                load_data_sql(&self.target_db_name, table, self.replace),
+            ]
+            .into_iter()
+            .filter(|sql| !sql.is_empty())
+            .collect::<Vec<_>>()
+            .join("; ");
+            if !load_file(&self.target, &sql, buf).await? {
+                eprintln!("[warn] 工作连接 {} 改用多行 INSERT 导入", self.id);
+                *load_data = false;
+            }
+        }
+        if !*load_data {
+            for batch in pending.chunks(INSERT_BATCH_SIZE) {
+                execute(
+                    conn,
+                    &insert_sql(&self.target_db_name, table, batch, self.replace),
+                )
+                .await?;
+            }
+        }
+        let rows = pending.len() as u64;
+        pending.clear();
+        Ok(rows)
+    }
+}
+
+// 写入目标库，返回写入的行数
+async fn write_rows(
+    conn: &mut MySqlConnection,
+    db_name: &str,
+    table: &TableSchema,
+    replace: bool,
+    mut rx: mpsc::Receiver<Vec<RowValues>>,
+) -> Result<u64, BoxError> {
+    let mut rows = 0u64;
+    while let Some(batch) = rx.recv().await {
+        execute(conn, &insert_sql(db_name, table, &batch, replace)).await?;
+        rows += batch.len() as u64;
+    }
+    Ok(rows)
+}
+
+// 直接调用 Executor 的方法（返回的 future 满足 Send），在 tokio::spawn 的任务中使用连接时不能用 raw_sql(..).execute
+pub async fn execute(conn: &mut MySqlConnection, sql: &str) -> Result<(), sqlx::Error> {
+    Executor::execute(conn, sqlx::raw_sql(sql)).await?;
+    Ok(())
+}
+
+fn insert_sql(db_name: &str, table: &TableSchema, rows: &[RowValues], replace: bool) -> String {
+    let columns = table
+        .columns
+        .iter()
+        .map(|c| quote_ident(&c.name))
+        .collect::<Vec<_>>()
+        .join(", ");
+    let values = rows
+        .iter()
+        .map(|row| mysql_row_values(table, row))
+        .collect::<Vec<_>>()
+        .join(",\n");
+    format!(
+        "{} INTO {}.{} ({}) VALUES {}",
+        if replace { "REPLACE" } else { "INSERT" },
+        quote_ident(db_name),
+        quote_ident(&table.name),
+        columns,
+        values
+    )
+}
+
+#[cfg(test)]
+mod test_parallel {
+    use super::*;
+
+    #[test]
+    fn test_split_range() {
+        // 不超过一块
+        assert_eq!(split_range(1, 100, 50, 1000), vec![(1, 100)]);
+        assert_eq!(
+            split_range(1, 100, 100, 30),
+            vec![(1, 25), (26, 50), (51, 75), (76, 100)]
+        );
+        // 主键稀疏时块数不超过主键范围
+        assert_eq!(split_range(5, 6, 1000, 10), vec![(5, 5), (6, 6)]);
+        assert_eq!(split_range(-10, 9, 20, 10), vec![(-10, -1), (0, 9)]);
+        assert!(split_range(10, 1, 100, 10).is_empty());
+        // bigint unsigned 的最大值
+        let max = u64::MAX as i128;
+        let ranges = split_range(0, max, 4, 1);
+        assert_eq!(ranges.len(), 4);
+        assert_eq!(ranges.last().unwrap().1, max);
+    }
+}
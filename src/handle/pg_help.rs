@@ -0,0 +1,392 @@
+// PostgreSQL 同步，使用 pg_dump（plain格式）导出、psql 导入
+
+use std::{
+    fs::{self, File},
+    io::{self, Read},
+    path::Path,
+    process::{Command, Stdio},
+    sync::Arc,
+};
+
+use chrono::Local;
+use sqlx::Row;
+use tokio::sync::Semaphore;
+
+use crate::{
+    handle::help::decode_stderr,
+    model::job::{Source, Target},
+    util::checksum::{HashingWriter, PG_DUMP_COMPLETED, TailWriter, verify_file, write_sidecar},
+};
+
+#[derive(Clone, Debug)]
+pub struct PostgresHelp {
+    pub source_pool: Arc<sqlx::Pool<sqlx::Postgres>>,
+    pub target_pool: Arc<sqlx::Pool<sqlx::Postgres>>,
+}
+
+impl PostgresHelp {
+    pub fn new(
+        source_pool: Arc<sqlx::Pool<sqlx::Postgres>>,
+        target_pool: Arc<sqlx::Pool<sqlx::Postgres>>,
+    ) -> Self {
+        PostgresHelp {
+            source_pool,
+            target_pool,
+        }
+    }
+
+    pub async fn get_postgres_version(&self) -> Result<Vec<String>, sqlx::Error> {
+        let source_version = query_postgres_version(&self.source_pool).await?;
+        let target_version = query_postgres_version(&self.target_pool).await?;
+        Ok(vec![
+            format!("source version: {}", source_version),
+            format!("target version: {}", target_version),
+        ])
+    }
+
+    // 所有用户数据库（不包括模板库）
+    pub async fn get_all_databases(&self) -> Result<Vec<String>, sqlx::Error> {
+        query_all_databases(&self.source_pool).await
+    }
+
+    // 同步所有数据库
+    pub async fn sync_all_db(&self, source: &Source, target: &Target) -> Result<(), sqlx::Error> {
+        let databases = self.get_all_databases().await?;
+        // 设置最大并发数为 5
+        let semaphore = Arc::new(Semaphore::new(5));
+        let mut tasks = Vec::new();
+
+        for db_name in databases {
+            let source_cloned = source.clone();
+            let target_cloned = target.clone();
+            let help_arc = Arc::new(self.clone());
+
+            let permit = semaphore.clone().acquire_owned().await.unwrap();
+            let task = tokio::spawn(async move {
+                // 备份成功则还原
+                let result = match help_arc.pg_dump_backup(&source_cloned, &db_name, &[]).await {
+                    Ok(backup_file_path) => {
+                        help_arc
+                            .pg_dump_restore(&backup_file_path, &target_cloned, &db_name)
+                            .await
+                    }
+                    Err(e) => Err(e),
+                };
+                if let Err(e) = result {
+                    eprintln!("Failed to sync database {}: {}", db_name, e);
+                }
+                // 释放信号量
+                drop(permit);
+            });
+            tasks.push(task);
+        }
+        // 等待所有任务完成
+        for h in tasks {
+            if let Err(e) = h.await {
+                eprintln!("Task failed: {}", e);
+            }
+        }
+        Ok(())
+    }
+
+    // 同步单个数据库：source.db_name -> target.db_name（未配置时同名）
+    pub async fn sync_db(
+        &self,
+        source: &Source,
+        target: &Target,
+    ) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+        let db_name = source
+            .db_name
+            .as_deref()
+            .ok_or("database_sync任务需要配置 source.db_name")?;
+        let target_db_name = target.db_name.as_deref().unwrap_or(db_name);
+        let backup_file_path = self.pg_dump_backup(source, db_name, &[]).await?;
+        self.pg_dump_restore(&backup_file_path, target, target_db_name)
+            .await
+    }
+
+    // 同步数据表：table_name 可以带schema（public.orders），多个表用逗号分隔
+    pub async fn sync_table(
+        &self,
+        source: &Source,
+        target: &Target,
+    ) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+        let db_name = source
+            .db_name
+            .as_deref()
+            .ok_or("table_sync任务需要配置 source.db_name")?;
+        let tables = source.table_names();
+        if tables.is_empty() {
+            return Err("table_sync任务需要配置 source.table_name".into());
+        }
+        let target_db_name = target.db_name.as_deref().unwrap_or(db_name);
+        let backup_file_path = self.pg_dump_backup(source, db_name, &tables).await?;
+        self.pg_dump_restore(&backup_file_path, target, target_db_name)
+            .await
+    }
+
+    // 还原数据库：目标库不存在则创建，然后执行psql导入
+    pub async fn pg_dump_restore(
+        &self,
+        backup_file_path: &str,
+        target: &Target,
+        db_name: &str,
+    ) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+        // 校验sha256和结束标记，不完整的备份文件不还原
+        verify_file(backup_file_path, PG_DUMP_COMPLETED)?;
+
+        ensure_database(&self.target_pool, db_name)
+            .await
+            .map_err(|e| format!("Failed to create database {}: {}", db_name, e))?;
+
+        let output = File::open(backup_file_path)
+            .and_then(|file| {
+                execute_psql_restore(
+                    Box::new(file),
+                    &target.host,
+                    &target.port,
+                    &target.user,
+                    &target.password,
+                    db_name,
+                )
+            })
+            .map_err(|e| format!("Failed to execute psql restore command: {}", e))?;
+        if !output.status.success() {
+            return Err(format!("psql restored failed: {}", decode_stderr(&output.stderr)).into());
+        }
+        println!("[ok] Database restored from {}", backup_file_path);
+        Ok(())
+    }
+
+    // 备份数据库（tables不为空时只备份这些表）
+    pub async fn pg_dump_backup(
+        &self,
+        source: &Source,
+        db_name: &str,
+        tables: &[String],
+    ) -> Result<String, Box<dyn std::error::Error + Send + Sync>> {
+        let time_str = Local::now().format("%Y%m%d_%H%M%S").to_string();
+        let output_file_path = backup_file_path(db_name, tables, &time_str);
+        if let Some(parent) = Path::new(&output_file_path).parent() {
+            fs::create_dir_all(parent)?;
+        }
+
+        let mut output_file = HashingWriter::new(File::create(&output_file_path)?);
+        pg_dump_to_writer(source, db_name, tables, &mut output_file)?;
+        let (output_file, _, sha256) = output_file.into_parts();
+        output_file.sync_all()?;
+        write_sidecar(&output_file_path, &sha256)?;
+        println!("[ok] Database {} dumped to {}", db_name, output_file_path);
+        Ok(output_file_path)
+    }
+}
+
+// 导出文件路径，tables 不为空时文件名中带表名
+fn backup_file_path(db_name: &str, tables: &[String], time_str: &str) -> String {
+    if tables.is_empty() {
+        format!("sql/pg_backup_{}_{}.sql", db_name, time_str)
+    } else {
+        format!(
+            "sql/pg_backup_{}_{}_{}.sql",
+            db_name,
+            tables.join("_"),
+            time_str
+        )
+    }
+}
+
+// 所有用户数据库（不包括模板库和 postgres 库）
+pub async fn query_all_databases(
+    pool: &sqlx::Pool<sqlx::Postgres>,
+) -> Result<Vec<String>, sqlx::Error> {
+    let rows = sqlx::query(
+        "SELECT datname FROM pg_database WHERE NOT datistemplate AND datname <> 'postgres' ORDER BY datname",
+    )
+    .fetch_all(pool)
+    .await?;
+    Ok(rows.iter().map(|row| row.get("datname")).collect())
+}
+
+// 查询数据库版本
+pub async fn query_postgres_version(
+    pool: &sqlx::Pool<sqlx::Postgres>,
+) -> Result<String, sqlx::Error> {
+    let row: (String,) = sqlx::query_as("SHOW server_version")
+        .fetch_one(pool)
+        .await?;
+    Ok(row.0)
+}
+
+// 目标库不存在则创建
+pub async fn ensure_database(
+    pool: &sqlx::Pool<sqlx::Postgres>,
+    db_name: &str,
+) -> Result<(), sqlx::Error> {
+    let db_exists = sqlx::query("SELECT 1 FROM pg_database WHERE datname = $1")
+        .bind(db_name)
+        .fetch_optional(pool)
+        .await?;
+    if db_exists.is_none() {
+        sqlx::query(&format!("CREATE DATABASE {}", quote_ident(db_name)))
+            .execute(pool)
+            .await?;
+        println!("Database {} created", db_name);
+    } else {
+        println!("Database {} already exists", db_name);
+    }
+    Ok(())
+}
+
+// 执行pg_dump，输出流式写入writer，返回写入的字节数
+pub fn pg_dump_to_writer(
+    source: &Source,
+    db_name: &str,
+    tables: &[String],
+    writer: &mut dyn io::Write,
+) -> Result<u64, Box<dyn std::error::Error + Send + Sync>> {
+    let mut child = Command::new("pg_dump")
+        .env("PGPASSWORD", &source.password)
+        .args(pg_dump_args(source, db_name, tables))
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()?;
+
+    // 单独的线程读取错误输出，避免管道写满阻塞pg_dump
+    let mut stderr = child.stderr.take().unwrap();
+    let stderr_reader = std::thread::spawn(move || {
+        let mut buf = Vec::new();
+        let _ = stderr.read_to_end(&mut buf);
+        buf
+    });
+
+    let mut stdout = child.stdout.take().unwrap();
+    let mut writer = TailWriter::new(writer);
+    let copy_result = io::copy(&mut stdout, &mut writer);
+    let status = child.wait()?;
+    let stderr = stderr_reader.join().unwrap_or_default();
+    let size = copy_result?;
+
+    if !status.success() {
+        eprintln!("pg_dump failed: {}", decode_stderr(&stderr));
+        return Err("pg_dump failed".into());
+    }
+    if !writer.dump_completed(PG_DUMP_COMPLETED) {
+        return Err(format!("pg_dump output of {} is incomplete", db_name).into());
+    }
+    Ok(size)
+}
+
+// pg_dump 的参数，密码通过 PGPASSWORD 传递
+fn pg_dump_args(source: &Source, db_name: &str, tables: &[String]) -> Vec<String> {
+    let mut args = vec![
+        format!("--host={}", source.host),
+        format!("--port={}", source.port),
+        format!("--username={}", source.user),
+        "--format=plain".to_string(),
+        "--no-owner".to_string(), // 目标库用户可能不同
+        "--no-privileges".to_string(),
+        "--clean".to_string(),
+        "--if-exists".to_string(),
+    ];
+    for table in tables {
+        args.push(format!("--table={}", table));
+    }
+    args.push(db_name.to_string());
+    args
+}
+
+// 执行psql命令，备份数据流作为标准输入，出错时立即停止
+fn execute_psql_restore(
+    mut reader: Box<dyn Read + Send>,
+    host: &str,
+    port: &str,
+    user: &str,
+    password: &str,
+    db_name: &str,
+) -> io::Result<std::process::Output> {
+    let mut child = Command::new("psql")
+        .env("PGPASSWORD", password)
+        .arg(format!("--host={}", host))
+        .arg(format!("--port={}", port))
+        .arg(format!("--username={}", user))
+        .arg(format!("--dbname={}", db_name))
+        .arg("--quiet")
+        .arg("--no-psqlrc")
+        .arg("--set=ON_ERROR_STOP=1")
+        .stdin(Stdio::piped())
+        .stdout(Stdio::null())
+        .stderr(Stdio::piped())
+        .spawn()?;
+
+    // 单独的线程写入标准输入，写完后关闭管道
+    let mut stdin = child.stdin.take().unwrap();
+    let writer = std::thread::spawn(move || io::copy(&mut reader, &mut stdin).map(|_| ()));
+    let output = child.wait_with_output()?;
+    match writer.join() {
+        Ok(Ok(())) => Ok(output),
+        // psql提前退出时写入会失败，以psql的错误输出为准
+        Ok(Err(_)) if !output.status.success() => Ok(output),
+        Ok(Err(e)) => Err(e),
+        Err(_) => Err(io::Error::other("restore writer thread panicked")),
+    }
+}
+
+// 标识符加双引号
+pub fn quote_ident(name: &str) -> String {
+    format!("\"{}\"", name.replace('"', "\"\""))
+}
+
+#[cfg(test)]
+mod test_pg_help {
+    use super::*;
+
+    fn source() -> Source {
+        toml::from_str(
+            r#"
+            host = "127.0.0.1"
+            port = "5432"
+            user = "postgres"
+            password = "secret"
+            db_name = "canteen"
+            "#,
+        )
+        .unwrap()
+    }
+
+    #[test]
+    fn test_pg_dump_args() {
+        let tables = vec!["public.orders".to_string(), "order_items".to_string()];
+        let args = pg_dump_args(&source(), "canteen", &tables);
+        assert_eq!(
+            args[..3],
+            ["--host=127.0.0.1", "--port=5432", "--username=postgres"]
+        );
+        assert!(args.contains(&"--clean".to_string()));
+        assert!(args.ends_with(&[
+            "--table=public.orders".to_string(),
+            "--table=order_items".to_string(),
+            "canteen".to_string(),
+        ]));
+        // 密码不出现在命令行中
+        assert!(!args.iter().any(|a| a.contains("secret")));
+    }
+
+    #[test]
+    fn test_backup_file_path() {
+        assert_eq!(
+            backup_file_path("canteen", &[], "20250101_020000"),
+            "sql/pg_backup_canteen_20250101_020000.sql"
+        );
+        let tables = vec!["public.orders".to_string(), "public.items".to_string()];
+        assert_eq!(
+            backup_file_path("canteen", &tables, "20250101_020000"),
+            "sql/pg_backup_canteen_public.orders_public.items_20250101_020000.sql"
+        );
+    }
+
+    #[test]
+    fn test_quote_ident() {
+        assert_eq!(quote_ident("canteen"), "\"canteen\"");
+        assert_eq!(quote_ident("a\"b"), "\"a\"\"b\"");
+    }
+}
@@ -0,0 +1,520 @@
+// 读取PostgreSQL表结构和表数据（PostgreSQL作为按行同步的源库）
+// 只读取 public 模式，列类型按MySQL的类型写法表示，与 mysql_schema 读取的表结构一致
+
+use futures_util::TryStreamExt;
+use sqlx::{PgConnection, Pool, Postgres, Row};
+use tokio::sync::mpsc;
+
+use crate::{
+    handle::pg_help::quote_ident,
+    model::schema::{ColumnSchema, ForeignKeySchema, IndexSchema, RowValues, TableSchema},
+};
+
+type BoxError = Box<dyn std::error::Error + Send + Sync>;
+
+// 读取库中 public 模式的表结构，tables 为空时读取所有表
+pub async fn read_tables(
+    pool: &Pool<Postgres>,
+    tables: &[String],
+) -> Result<Vec<TableSchema>, sqlx::Error> {
+    let rows = sqlx::query(
+        "SELECT c.relname::text, obj_description(c.oid, 'pg_class') \
+         FROM pg_class c JOIN pg_namespace n ON n.oid = c.relnamespace \
+         WHERE n.nspname = 'public' AND c.relkind IN ('r', 'p') AND NOT c.relispartition \
+         ORDER BY c.relname",
+    )
+    .fetch_all(pool)
+    .await?;
+
+    let mut result = Vec::new();
+    for row in rows {
+        let name: String = row.try_get(0)?;
+        if !tables.is_empty() && !tables.contains(&name) {
+            continue;
+        }
+        let mut table = TableSchema {
+            columns: read_columns(pool, &name).await?,
+            primary_key: Vec::new(),
+            indexes: Vec::new(),
+            foreign_keys: read_foreign_keys(pool, &name).await?,
+            comment: row.try_get(1)?,
+            charset: None,
+            collation: None,
+            name,
+        };
+        for (index, primary) in read_indexes(pool, &table).await? {
+            if primary {
+                table.primary_key = index.columns;
+            } else {
+                table.indexes.push(index);
+            }
+        }
+        result.push(table);
+    }
+    Ok(result)
+}
+
+async fn read_columns(
+    pool: &Pool<Postgres>,
+    table_name: &str,
+) -> Result<Vec<ColumnSchema>, sqlx::Error> {
+    let rows = sqlx::query(
+        "SELECT a.attname::text, format_type(a.atttypid, a.atttypmod), NOT a.attnotnull, \
+                pg_get_expr(d.adbin, d.adrelid), a.attidentity <> '', col_description(c.oid, a.attnum) \
+         FROM pg_attribute a \
+         JOIN pg_class c ON c.oid = a.attrelid \
+         JOIN pg_namespace n ON n.oid = c.relnamespace \
+         LEFT JOIN pg_attrdef d ON d.adrelid = a.attrelid AND d.adnum = a.attnum \
+         WHERE n.nspname = 'public' AND c.relname = $1 AND a.attnum > 0 AND NOT a.attisdropped \
+         ORDER BY a.attnum",
+    )
+    .bind(table_name)
+    .fetch_all(pool)
+    .await?;
+
+    let mut columns = Vec::new();
+    for row in rows {
+        let name: String = row.try_get(0)?;
+        let pg_type: String = row.try_get(1)?;
+        let default: Option<String> = row.try_get(3)?;
+        let identity: bool = row.try_get(4)?;
+        let mut column = pg_column(&name, &pg_type);
+        column.nullable = row.try_get(2)?;
+        column.comment = row.try_get(5)?;
+        let (default, serial) = pg_default(default.as_deref());
+        column.default = default;
+        column.auto_increment = identity || serial;
+        columns.push(column);
+    }
+    Ok(columns)
+}
+
+// 只读取 btree/hash 索引，表达式索引和其它类型的索引需要人工处理
+async fn read_indexes(
+    pool: &Pool<Postgres>,
+    table: &TableSchema,
+) -> Result<Vec<(IndexSchema, bool)>, sqlx::Error> {
+    let rows = sqlx::query(
+        "SELECT i.relname::text, ix.indisunique, ix.indisprimary, am.amname::text, \
+                ARRAY(SELECT pg_get_indexdef(ix.indexrelid, k, true) \
+                      FROM generate_series(1, ix.indnkeyatts) k ORDER BY k)::text[] \
+         FROM pg_index ix \
+         JOIN pg_class t ON t.oid = ix.indrelid \
+         JOIN pg_class i ON i.oid = ix.indexrelid \
+         JOIN pg_namespace n ON n.oid = t.relnamespace \
+         JOIN pg_am am ON am.oid = i.relam \
+         WHERE n.nspname = 'public' AND t.relname = $1 ORDER BY i.relname",
+    )
+    .bind(&table.name)
+    .fetch_all(pool)
+    .await?;
+
+    let mut indexes = Vec::new();
+    for row in rows {
+        let index_type = match row.try_get::<String, _>(3)?.as_str() {
+            "btree" => "BTREE",
+            "hash" => "HASH",
+            _ => continue,
+        };
+        let columns = row
+            .try_get::<Vec<String>, _>(4)?
+            .iter()
+            .map(|c| unquote_ident(c))
+            .collect::<Vec<_>>();
+        if !columns
+            .iter()
+            .all(|c| table.columns.iter().any(|column| column.name == *c))
+        {
+            continue;
+        }
+        let index = IndexSchema {
+            name: row.try_get(0)?,
+            sub_parts: vec![None; columns.len()],
+            columns,
+            unique: row.try_get(1)?,
+            index_type: index_type.to_string(),
+        };
+        indexes.push((index, row.try_get(2)?));
+    }
+    Ok(indexes)
+}
+
+async fn read_foreign_keys(
+    pool: &Pool<Postgres>,
+    table_name: &str,
+) -> Result<Vec<ForeignKeySchema>, sqlx::Error> {
+    let rows = sqlx::query(
+        "SELECT c.conname::text, r.relname::text, c.confupdtype::text, c.confdeltype::text, \
+                ARRAY(SELECT a.attname FROM unnest(c.conkey) WITH ORDINALITY k(attnum, ord) \
+                      JOIN pg_attribute a ON a.attrelid = c.conrelid AND a.attnum = k.attnum \
+                      ORDER BY k.ord)::text[], \
+                ARRAY(SELECT a.attname FROM unnest(c.confkey) WITH ORDINALITY k(attnum, ord) \
+                      JOIN pg_attribute a ON a.attrelid = c.confrelid AND a.attnum = k.attnum \
+                      ORDER BY k.ord)::text[] \
+         FROM pg_constraint c \
+         JOIN pg_class t ON t.oid = c.conrelid \
+         JOIN pg_class r ON r.oid = c.confrelid \
+         JOIN pg_namespace n ON n.oid = t.relnamespace \
+         WHERE c.contype = 'f' AND n.nspname = 'public' AND t.relname = $1 ORDER BY c.conname",
+    )
+    .bind(table_name)
+    .fetch_all(pool)
+    .await?;
+
+    rows.iter()
+        .map(|row| {
+            Ok(ForeignKeySchema {
+                name: row.try_get(0)?,
+                ref_table: row.try_get(1)?,
+                on_update: fk_action(&row.try_get::<String, _>(2)?).to_string(),
+                on_delete: fk_action(&row.try_get::<String, _>(3)?).to_string(),
+                columns: row.try_get(4)?,
+                ref_columns: row.try_get(5)?,
+            })
+        })
+        .collect()
+}
+
+// 视图、函数/存储过程、触发器，返回 (类型, 名称)
+pub async fn read_other_objects(
+    pool: &Pool<Postgres>,
+) -> Result<Vec<(String, String)>, sqlx::Error> {
+    let rows = sqlx::query(
+        "SELECT 'VIEW', table_name::text FROM information_schema.views WHERE table_schema = 'public' \
+         UNION ALL SELECT CASE p.prokind WHEN 'p' THEN 'PROCEDURE' ELSE 'FUNCTION' END, p.proname::text \
+           FROM pg_proc p JOIN pg_namespace n ON n.oid = p.pronamespace WHERE n.nspname = 'public' \
+         UNION ALL SELECT DISTINCT 'TRIGGER', trigger_name::text FROM information_schema.triggers \
+           WHERE trigger_schema = 'public'",
+    )
+    .fetch_all(pool)
+    .await?;
+    rows.iter()
+        .map(|row| Ok((row.try_get(0)?, row.try_get(1)?)))
+        .collect()
+}
+
+// 逐批读取表数据（where_clause 为过滤条件），每读满 batch_size 行发送到 tx，返回总行数
+// 使用文本协议读取，值转换为与MySQL相同的文本形式（布尔值 1/0、bytea 为原始字节、时间为UTC）
+pub async fn read_rows(
+    conn: &mut PgConnection,
+    table: &TableSchema,
+    where_clause: Option<&str>,
+    batch_size: usize,
+    tx: mpsc::Sender<Vec<RowValues>>,
+) -> Result<u64, BoxError> {
+    let sql = select_sql(table, where_clause);
+    let mut stream = sqlx::raw_sql(&sql).fetch(&mut *conn);
+    let mut batch = Vec::with_capacity(batch_size);
+    let mut total = 0u64;
+    while let Some(row) = stream.try_next().await? {
+        let mut values = Vec::with_capacity(table.columns.len());
+        for (i, column) in table.columns.iter().enumerate() {
+            let value = row.try_get_unchecked::<Option<String>, _>(i)?;
+            values.push(value.map(|v| text_value(column, v)).transpose()?);
+        }
+        batch.push(values);
+        if batch.len() >= batch_size {
+            total += batch.len() as u64;
+            let full = std::mem::replace(&mut batch, Vec::with_capacity(batch_size));
+            tx.send(full).await.map_err(|_| "目标库写入已停止")?;
+        }
+    }
+    if !batch.is_empty() {
+        total += batch.len() as u64;
+        tx.send(batch).await.map_err(|_| "目标库写入已停止")?;
+    }
+    Ok(total)
+}
+
+fn select_sql(table: &TableSchema, where_clause: Option<&str>) -> String {
+    let columns = table
+        .columns
+        .iter()
+        .map(select_expr)
+        .collect::<Vec<_>>()
+        .join(", ");
+    let mut sql = format!(
+        "SELECT {} FROM public.{}",
+        columns,
+        quote_ident(&table.name)
+    );
+    if let Some(where_clause) = where_clause {
+        sql.push_str(&format!(" WHERE ({})", where_clause));
+    }
+    sql
+}
+
+// 带时区的时间转换为UTC，time with time zone 去掉时区
+fn select_expr(column: &ColumnSchema) -> String {
+    let name = quote_ident(&column.name);
+    match column.data_type.as_str() {
+        "timestamp" => format!("({} AT TIME ZONE 'UTC')", name),
+        "time" => format!("CAST({} AS time)", name),
+        _ => name,
+    }
+}
+
+// 文本形式的值转换为MySQL的文本形式
+fn text_value(column: &ColumnSchema, value: String) -> Result<Vec<u8>, BoxError> {
+    if column.is_bool() {
+        return Ok(match value.as_str() {
+            "t" => b"1".to_vec(),
+            _ => b"0".to_vec(),
+        });
+    }
+    if column.data_type == "longblob" {
+        let hex = value
+            .strip_prefix("\\x")
+            .ok_or_else(|| format!("列 {} 的bytea值不是十六进制格式", column.name))?;
+        return Ok(hex::decode(hex)?);
+    }
+    Ok(value.into_bytes())
+}
+
+// PostgreSQL列类型（format_type 的结果，例如 character varying(32)、numeric(10,2)、
+// timestamp(3) without time zone）转换为MySQL类型写法的列，无法对应的类型作为 longtext
+pub fn pg_column(name: &str, pg_type: &str) -> ColumnSchema {
+    let pg_type = pg_type.trim().to_lowercase();
+    let (base, params) = match (pg_type.find('('), pg_type.find(')')) {
+        (Some(start), Some(end)) if start < end => (
+            format!("{}{}", &pg_type[..start], &pg_type[end + 1..]),
+            pg_type[start + 1..end]
+                .split(',')
+                .filter_map(|p| p.trim().parse::<u64>().ok())
+                .collect::<Vec<_>>(),
+        ),
+        _ => (pg_type.clone(), Vec::new()),
+    };
+    let param = params.first().copied();
+    let mut column = ColumnSchema::new(name, "longtext", "longtext");
+    let (data_type, column_type) = match base.as_str() {
+        _ if base.ends_with("[]") => return column,
+        "smallint" => ("smallint", "smallint".to_string()),
+        "integer" => ("int", "int".to_string()),
+        "bigint" => ("bigint", "bigint".to_string()),
+        "real" => ("float", "float".to_string()),
+        "double precision" => ("double", "double".to_string()),
+        // 未指定精度的 numeric 按MySQL允许的最大精度
+        "numeric" => {
+            let (precision, scale) = match param {
+                Some(precision) => (precision, params.get(1).copied().unwrap_or(0)),
+                None => (65, 30),
+            };
+            column.numeric_precision = Some(precision);
+            column.numeric_scale = Some(scale);
+            ("decimal", format!("decimal({},{})", precision, scale))
+        }
+        "boolean" => ("tinyint", "tinyint(1)".to_string()),
+        "character varying" => match param {
+            Some(len) => {
+                column.char_length = Some(len);
+                ("varchar", format!("varchar({})", len))
+            }
+            None => return column,
+        },
+        "character" => {
+            let len = param.unwrap_or(1);
+            column.char_length = Some(len);
+            ("char", format!("char({})", len))
+        }
+        "bytea" => ("longblob", "longblob".to_string()),
+        "date" => ("date", "date".to_string()),
+        // 带时区的时间读取时转换为UTC
+        "timestamp without time zone" | "timestamp with time zone" => {
+            let precision = param.unwrap_or(6);
+            column.datetime_precision = Some(precision);
+            let data_type = if base.ends_with("without time zone") {
+                "datetime"
+            } else {
+                "timestamp"
+            };
+            (data_type, format!("{}({})", data_type, precision))
+        }
+        "time without time zone" | "time with time zone" => {
+            let precision = param.unwrap_or(6);
+            column.datetime_precision = Some(precision);
+            ("time", format!("time({})", precision))
+        }
+        "json" | "jsonb" => ("json", "json".to_string()),
+        "uuid" => {
+            column.char_length = Some(36);
+            ("char", "char(36)".to_string())
+        }
+        _ => return column,
+    };
+    column.data_type = data_type.to_string();
+    column.column_type = column_type;
+    column
+}
+
+// 列默认值（pg_get_expr 的结果）转换为 information_schema.COLUMNS 中的写法
+// 返回 (默认值, 是否为序列自增)，无法对应的表达式不保留默认值
+pub fn pg_default(expr: Option<&str>) -> (Option<String>, bool) {
+    let Some(expr) = expr.map(str::trim) else {
+        return (None, false);
+    };
+    let lower = expr.to_lowercase();
+    if lower.starts_with("nextval(") {
+        return (None, true);
+    }
+    if lower == "now()" || lower.starts_with("current_timestamp") {
+        return (Some("CURRENT_TIMESTAMP".to_string()), false);
+    }
+    match lower.as_str() {
+        "true" => return (Some("1".to_string()), false),
+        "false" => return (Some("0".to_string()), false),
+        _ => {}
+    }
+    // 'new'::character varying、'0'::numeric 等带类型转换的字面量
+    let literal = match expr.rfind("::") {
+        Some(pos) if expr.starts_with('\'') || expr.starts_with("('") => &expr[..pos],
+        _ => expr,
+    };
+    let literal = literal.trim_start_matches('(').trim_end_matches(')');
+    if let Some(inner) = literal
+        .strip_prefix('\'')
+        .and_then(|l| l.strip_suffix('\''))
+    {
+        return (Some(inner.replace("''", "'")), false);
+    }
+    if literal.trim_start_matches('-').parse::<f64>().is_ok() {
+        return (Some(literal.to_string()), false);
+    }
+    (None, false)
+}
+
+// 外键动作代码（pg_constraint.confupdtype/confdeltype）
+fn fk_action(code: &str) -> &'static str {
+    match code {
+        "r" => "RESTRICT",
+        "c" => "CASCADE",
+        "n" => "SET NULL",
+        "d" => "SET DEFAULT",
+        _ => "NO ACTION",
+    }
+}
+
+// pg_get_indexdef 返回的列名在需要时带双引号
+fn unquote_ident(name: &str) -> String {
+    match name.strip_prefix('"').and_then(|n| n.strip_suffix('"')) {
+        Some(inner) => inner.replace("\"\"", "\""),
+        None => name.to_string(),
+    }
+}
+
+#[cfg(test)]
+mod test_pg_schema {
+    use super::*;
+    use crate::model::schema::ValueKind;
+
+    #[test]
+    fn test_pg_column() {
+        let cases = [
+            ("integer", "int", "int"),
+            ("bigint", "bigint", "bigint"),
+            ("numeric(10,2)", "decimal", "decimal(10,2)"),
+            ("numeric", "decimal", "decimal(65,30)"),
+            ("boolean", "tinyint", "tinyint(1)"),
+            ("character varying(32)", "varchar", "varchar(32)"),
+            ("character varying", "longtext", "longtext"),
+            ("character(2)", "char", "char(2)"),
+            ("text", "longtext", "longtext"),
+            ("bytea", "longblob", "longblob"),
+            ("timestamp(3) without time zone", "datetime", "datetime(3)"),
+            ("timestamp with time zone", "timestamp", "timestamp(6)"),
+            ("time with time zone", "time", "time(6)"),
+            ("jsonb", "json", "json"),
+            ("uuid", "char", "char(36)"),
+            ("integer[]", "longtext", "longtext"),
+            ("order_status", "longtext", "longtext"),
+        ];
+        for (pg_type, data_type, column_type) in cases {
+            let column = pg_column("c", pg_type);
+            assert_eq!(
+                (column.data_type.as_str(), column.column_type.as_str()),
+                (data_type, column_type),
+                "{}",
+                pg_type
+            );
+        }
+        assert!(pg_column("c", "boolean").is_bool());
+        let amount = pg_column("c", "numeric(10,2)");
+        assert_eq!(amount.kind(), ValueKind::Decimal);
+        assert_eq!(
+            (amount.numeric_precision, amount.numeric_scale),
+            (Some(10), Some(2))
+        );
+        assert_eq!(
+            pg_column("c", "character varying(32)").char_length,
+            Some(32)
+        );
+    }
+
+    #[test]
+    fn test_pg_default() {
+        assert_eq!(
+            pg_default(Some("nextval('orders_id_seq'::regclass)")),
+            (None, true)
+        );
+        assert_eq!(
+            pg_default(Some("'new'::character varying")),
+            (Some("new".to_string()), false)
+        );
+        assert_eq!(
+            pg_default(Some("'it''s'::text")),
+            (Some("it's".to_string()), false)
+        );
+        assert_eq!(pg_default(Some("0")), (Some("0".to_string()), false));
+        assert_eq!(
+            pg_default(Some("'-1.5'::numeric")),
+            (Some("-1.5".to_string()), false)
+        );
+        assert_eq!(pg_default(Some("false")), (Some("0".to_string()), false));
+        assert_eq!(
+            pg_default(Some("now()")),
+            (Some("CURRENT_TIMESTAMP".to_string()), false)
+        );
+        assert_eq!(pg_default(Some("gen_random_uuid()")), (None, false));
+        assert_eq!(pg_default(None), (None, false));
+    }
+
+    #[test]
+    fn test_read_rows_sql() {
+        let mut table = TableSchema {
+            name: "orders".to_string(),
+            columns: vec![
+                pg_column("id", "integer"),
+                pg_column("created_at", "timestamp with time zone"),
+                pg_column("opens_at", "time with time zone"),
+            ],
+            primary_key: vec!["id".to_string()],
+            indexes: Vec::new(),
+            foreign_keys: Vec::new(),
+            comment: None,
+            charset: None,
+            collation: None,
+        };
+        assert_eq!(
+            select_sql(&table, Some("id > 10")),
+            "SELECT \"id\", (\"created_at\" AT TIME ZONE 'UTC'), CAST(\"opens_at\" AS time) \
+             FROM public.\"orders\" WHERE (id > 10)"
+        );
+
+        table.columns = vec![pg_column("paid", "boolean"), pg_column("photo", "bytea")];
+        assert_eq!(
+            text_value(&table.columns[0], "t".to_string()).unwrap(),
+            b"1"
+        );
+        assert_eq!(
+            text_value(&table.columns[0], "f".to_string()).unwrap(),
+            b"0"
+        );
+        assert_eq!(
+            text_value(&table.columns[1], "\\x00ff".to_string()).unwrap(),
+            vec![0, 255]
+        );
+        assert!(text_value(&table.columns[1], "00ff".to_string()).is_err());
+        assert_eq!(unquote_ident("\"Order\"\"Id\""), "Order\"Id");
+        assert_eq!(fk_action("c"), "CASCADE");
+    }
+}
@@ -0,0 +1,592 @@
+// 表结构对比：比较源库和目标库的表、列、索引、外键、视图、存储过程/函数和触发器
+// 输出结构化的差异（JSON）和使目标库与源库一致的语句（SQL脚本），不修改目标库
+
+use std::{collections::BTreeMap, fs};
+
+use chrono::Local;
+use serde::Serialize;
+use sqlx::{MySql, Pool, Row};
+
+use crate::{
+    handle::{
+        mysql_schema::{quote_ident, read_tables},
+        sync::BoxError,
+        type_map::{
+            mysql_column_definition, mysql_foreign_key_definition, mysql_idents,
+            mysql_index_definition, mysql_quote,
+        },
+    },
+    model::schema::TableSchema,
+};
+
+#[derive(Debug, Default, Serialize)]
+pub struct SchemaDiff {
+    pub source: String,
+    pub target: String,
+    pub items: Vec<DiffItem>,
+    pub statements: Vec<String>, // 按顺序执行即可与源库一致
+}
+
+#[derive(Debug, Serialize)]
+pub struct DiffItem {
+    pub object_type: String, // TABLE | COLUMN | PRIMARY KEY | INDEX | FOREIGN KEY | TABLE OPTIONS | VIEW | PROCEDURE | FUNCTION | TRIGGER
+    pub name: String,
+    pub change: &'static str, // missing：目标库缺少 | extra：目标库多出 | changed：定义不同
+    pub source: Option<String>,
+    pub target: Option<String>,
+}
+
+// 视图、存储过程/函数、触发器，definition 为去掉 DEFINER 和库名后的建立语句
+#[derive(Debug, Clone)]
+struct DbObject {
+    object_type: String,
+    name: String,
+    definition: String,
+}
+
+// 生成语句的阶段：先删除外键，再修改表，然后添加外键，最后重建视图、存储过程和触发器
+#[derive(Default)]
+struct Statements {
+    drop_foreign_keys: Vec<String>,
+    tables: Vec<String>,
+    add_foreign_keys: Vec<String>,
+    objects: Vec<String>,
+}
+
+impl SchemaDiff {
+    fn add(
+        &mut self,
+        object_type: &str,
+        name: impl Into<String>,
+        change: &'static str,
+        source: Option<String>,
+        target: Option<String>,
+    ) {
+        self.items.push(DiffItem {
+            object_type: object_type.to_string(),
+            name: name.into(),
+            change,
+            source,
+            target,
+        });
+    }
+}
+
+// 对比 source_pool 中的 db_name 和 target_pool 中的 target_db_name，tables 不为空时只对比这些表
+pub async fn diff_database(
+    source_pool: &Pool<MySql>,
+    target_pool: &Pool<MySql>,
+    db_name: &str,
+    target_db_name: &str,
+    tables: &[String],
+) -> Result<SchemaDiff, BoxError> {
+    let mut diff = SchemaDiff {
+        source: db_name.to_string(),
+        target: target_db_name.to_string(),
+        ..Default::default()
+    };
+    let mut statements = Statements::default();
+
+    let source_tables = read_tables(&mut *source_pool.acquire().await?, db_name, tables).await?;
+    let target_tables =
+        read_tables(&mut *target_pool.acquire().await?, target_db_name, tables).await?;
+    for table in &source_tables {
+        match target_tables.iter().find(|t| t.name == table.name) {
+            Some(target_table) => diff_table(&mut diff, &mut statements, table, target_table),
+            None => {
+                let ddl = show_create_table(source_pool, db_name, &table.name).await?;
+                statements.tables.push(ddl.clone());
+                diff.add("TABLE", &table.name, "missing", Some(ddl), None);
+            }
+        }
+    }
+    for table in &target_tables {
+        if !source_tables.iter().any(|t| t.name == table.name) {
+            // 删除表会丢失数据，默认注释，确认后手动执行
+            statements
+                .tables
+                .push(format!("-- DROP TABLE {}", quote_ident(&table.name)));
+            diff.add(
+                "TABLE",
+                &table.name,
+                "extra",
+                None,
+                Some(table.name.clone()),
+            );
+        }
+    }
+
+    if tables.is_empty() {
+        let source_objects = read_objects(source_pool, db_name).await?;
+        let target_objects = read_objects(target_pool, target_db_name).await?;
+        diff_objects(&mut diff, &mut statements, &source_objects, &target_objects);
+    }
+
+    diff.statements = [
+        statements.drop_foreign_keys,
+        statements.tables,
+        statements.add_foreign_keys,
+        statements.objects,
+    ]
+    .concat();
+    Ok(diff)
+}
+
+fn diff_table(
+    diff: &mut SchemaDiff,
+    statements: &mut Statements,
+    source: &TableSchema,
+    target: &TableSchema,
+) {
+    let table = quote_ident(&source.name);
+    let mut alters = Vec::new();
+
+    // 列：缺少的列按源库的位置添加
+    for (i, column) in source.columns.iter().enumerate() {
+        let name = format!("{}.{}", source.name, column.name);
+        let definition = mysql_column_definition(column);
+        match target.columns.iter().find(|c| c.name == column.name) {
+            Some(target_column) => {
+                let target_definition = mysql_column_definition(target_column);
+                if target_definition != definition {
+                    alters.push(format!("MODIFY COLUMN {}", definition));
+                    diff.add(
+                        "COLUMN",
+                        name,
+                        "changed",
+                        Some(definition),
+                        Some(target_definition),
+                    );
+                }
+            }
+            None => {
+                let position = match i {
+                    0 => " FIRST".to_string(),
+                    _ => format!(" AFTER {}", quote_ident(&source.columns[i - 1].name)),
+                };
+                alters.push(format!("ADD COLUMN {}{}", definition, position));
+                diff.add("COLUMN", name, "missing", Some(definition), None);
+            }
+        }
+    }
+    for column in &target.columns {
+        if !source.columns.iter().any(|c| c.name == column.name) {
+            statements.tables.push(format!(
+                "-- ALTER TABLE {} DROP COLUMN {}",
+                table,
+                quote_ident(&column.name)
+            ));
+            diff.add(
+                "COLUMN",
+                format!("{}.{}", source.name, column.name),
+                "extra",
+                None,
+                Some(mysql_column_definition(column)),
+            );
+        }
+    }
+
+    if source.primary_key != target.primary_key {
+        if !target.primary_key.is_empty() {
+            alters.push("DROP PRIMARY KEY".to_string());
+        }
+        if !source.primary_key.is_empty() {
+            alters.push(format!(
+                "ADD PRIMARY KEY ({})",
+                mysql_idents(&source.primary_key)
+            ));
+        }
+        diff.add(
+            "PRIMARY KEY",
+            &source.name,
+            "changed",
+            Some(source.primary_key.join(", ")).filter(|k| !k.is_empty()),
+            Some(target.primary_key.join(", ")).filter(|k| !k.is_empty()),
+        );
+    }
+
+    // 索引按名称对比
+    let source_indexes = source
+        .indexes
+        .iter()
+        .map(|i| (i.name.clone(), mysql_index_definition(i)))
+        .collect::<BTreeMap<_, _>>();
+    let target_indexes = target
+        .indexes
+        .iter()
+        .map(|i| (i.name.clone(), mysql_index_definition(i)))
+        .collect::<BTreeMap<_, _>>();
+    for (name, definition, change) in compare(&source_indexes, &target_indexes) {
+        if change != "missing" {
+            alters.push(format!("DROP INDEX {}", quote_ident(name)));
+        }
+        if change != "extra" {
+            alters.push(format!("ADD {}", source_indexes[name]));
+        }
+        let object = format!("{}.{}", source.name, name);
+        diff.add("INDEX", object, change, definition.0, definition.1);
+    }
+
+    // 外键先删除后添加，避免引用的列或索引还没有修改
+    let source_fks = source
+        .foreign_keys
+        .iter()
+        .map(|fk| (fk.name.clone(), mysql_foreign_key_definition(fk)))
+        .collect::<BTreeMap<_, _>>();
+    let target_fks = target
+        .foreign_keys
+        .iter()
+        .map(|fk| (fk.name.clone(), mysql_foreign_key_definition(fk)))
+        .collect::<BTreeMap<_, _>>();
+    for (name, definition, change) in compare(&source_fks, &target_fks) {
+        if change != "missing" {
+            statements.drop_foreign_keys.push(format!(
+                "ALTER TABLE {} DROP FOREIGN KEY {}",
+                table,
+                quote_ident(name)
+            ));
+        }
+        if change != "extra" {
+            statements
+                .add_foreign_keys
+                .push(format!("ALTER TABLE {} ADD {}", table, source_fks[name]));
+        }
+        let object = format!("{}.{}", source.name, name);
+        diff.add("FOREIGN KEY", object, change, definition.0, definition.1);
+    }
+
+    let options = |t: &TableSchema| {
+        format!(
+            "DEFAULT CHARSET={} COLLATE={} COMMENT={}",
+            t.charset.as_deref().unwrap_or(""),
+            t.collation.as_deref().unwrap_or(""),
+            mysql_quote(t.comment.as_deref().unwrap_or(""))
+        )
+    };
+    if options(source) != options(target) {
+        alters.push(options(source));
+        diff.add(
+            "TABLE OPTIONS",
+            &source.name,
+            "changed",
+            Some(options(source)),
+            Some(options(target)),
+        );
+    }
+
+    if !alters.is_empty() {
+        statements
+            .tables
+            .push(format!("ALTER TABLE {}\n  {}", table, alters.join(",\n  ")));
+    }
+}
+
+// (源库定义, 目标库定义)
+type Definitions = (Option<String>, Option<String>);
+
+// 按名称对比两组定义，返回 (名称, (源库定义, 目标库定义), 差异类型)
+fn compare<'a>(
+    source: &'a BTreeMap<String, String>,
+    target: &'a BTreeMap<String, String>,
+) -> Vec<(&'a str, Definitions, &'static str)> {
+    let mut result = Vec::new();
+    for (name, definition) in source {
+        match target.get(name) {
+            None => result.push((name.as_str(), (Some(definition.clone()), None), "missing")),
+            Some(t) if t != definition => result.push((
+                name.as_str(),
+                (Some(definition.clone()), Some(t.clone())),
+                "changed",
+            )),
+            _ => {}
+        }
+    }
+    for (name, definition) in target {
+        if !source.contains_key(name) {
+            result.push((name.as_str(), (None, Some(definition.clone())), "extra"));
+        }
+    }
+    result
+}
+
+fn diff_objects(
+    diff: &mut SchemaDiff,
+    statements: &mut Statements,
+    source: &[DbObject],
+    target: &[DbObject],
+) {
+    for object in source {
+        let existing = target
+            .iter()
+            .find(|t| t.object_type == object.object_type && t.name == object.name);
+        let change = match existing {
+            None => "missing",
+            Some(t) if t.definition != object.definition => "changed",
+            Some(_) => continue,
+        };
+        if change == "changed" {
+            statements.objects.push(drop_object(object));
+        }
+        statements.objects.push(object.definition.clone());
+        diff.add(
+            &object.object_type,
+            &object.name,
+            change,
+            Some(object.definition.clone()),
+            existing.map(|t| t.definition.clone()),
+        );
+    }
+    for object in target {
+        if !source
+            .iter()
+            .any(|s| s.object_type == object.object_type && s.name == object.name)
+        {
+            statements.objects.push(drop_object(object));
+            diff.add(
+                &object.object_type,
+                &object.name,
+                "extra",
+                None,
+                Some(object.definition.clone()),
+            );
+        }
+    }
+}
+
+fn drop_object(object: &DbObject) -> String {
+    format!(
+        "DROP {} IF EXISTS {}",
+        object.object_type,
+        quote_ident(&object.name)
+    )
+}
+
+async fn show_create_table(
+    pool: &Pool<MySql>,
+    db_name: &str,
+    table: &str,
+) -> Result<String, sqlx::Error> {
+    let row = sqlx::query(&format!(
+        "SHOW CREATE TABLE {}.{}",
+        quote_ident(db_name),
+        quote_ident(table)
+    ))
+    .fetch_one(pool)
+    .await?;
+    let ddl: String = row.try_get(1)?;
+    Ok(strip_auto_increment(&ddl))
+}
+
+// 读取视图、存储过程/函数、触发器的定义
+async fn read_objects(pool: &Pool<MySql>, db_name: &str) -> Result<Vec<DbObject>, BoxError> {
+    let rows = sqlx::query(
+        "SELECT 'VIEW', TABLE_NAME FROM information_schema.VIEWS WHERE TABLE_SCHEMA = ? \
+         UNION ALL SELECT ROUTINE_TYPE, ROUTINE_NAME FROM information_schema.ROUTINES WHERE ROUTINE_SCHEMA = ? \
+         UNION ALL SELECT 'TRIGGER', TRIGGER_NAME FROM information_schema.TRIGGERS WHERE TRIGGER_SCHEMA = ? \
+         ORDER BY 1, 2",
+    )
+    .bind(db_name)
+    .bind(db_name)
+    .bind(db_name)
+    .fetch_all(pool)
+    .await?;
+
+    let mut objects = Vec::new();
+    for row in rows {
+        let object_type: String = row.try_get(0)?;
+        let name: String = row.try_get(1)?;
+        // SHOW CREATE VIEW 的定义在第2列，其它在第3列
+        let column = if object_type == "VIEW" { 1 } else { 2 };
+        let show = sqlx::query(&format!(
+            "SHOW CREATE {} {}.{}",
+            object_type,
+            quote_ident(db_name),
+            quote_ident(&name)
+        ))
+        .fetch_one(pool)
+        .await?;
+        let definition: Option<String> = show.try_get(column)?;
+        let Some(definition) = definition else {
+            eprintln!(
+                "[warn] 没有权限读取 {} {}.{} 的定义",
+                object_type, db_name, name
+            );
+            continue;
+        };
+        objects.push(DbObject {
+            definition: normalize_definition(&definition, db_name),
+            object_type,
+            name,
+        });
+    }
+    Ok(objects)
+}
+
+// 去掉 DEFINER=`user`@`host` 和对本库的引用（`db`.），源库和目标库库名可以不同
+fn normalize_definition(definition: &str, db_name: &str) -> String {
+    let mut result = definition.replace(&format!("{}.", quote_ident(db_name)), "");
+    if let Some(start) = result.find("DEFINER=") {
+        let end = result[start..]
+            .find(' ')
+            .map(|i| start + i + 1)
+            .unwrap_or(result.len());
+        result.replace_range(start..end, "");
+    }
+    result.trim().to_string()
+}
+
+// 建表语句中的 AUTO_INCREMENT=n 与表结构无关
+fn strip_auto_increment(ddl: &str) -> String {
+    ddl.split(' ')
+        .filter(|part| !part.starts_with("AUTO_INCREMENT="))
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+// 输出差异到 sql/schema_diff_{库名}_{时间}.json 和 .sql，返回两个文件路径
+pub fn write_diff(diff: &SchemaDiff) -> Result<(String, String), BoxError> {
+    let time_str = Local::now().format("%Y%m%d_%H%M%S").to_string();
+    let path = format!("sql/schema_diff_{}_{}", diff.source, time_str);
+    fs::create_dir_all("sql")?;
+    let json_path = format!("{}.json", path);
+    fs::write(&json_path, serde_json::to_string_pretty(diff)?)?;
+
+    let mut script = format!(
+        "-- {} -> {} 结构对比生成，执行前请检查\nUSE {};\nSET FOREIGN_KEY_CHECKS = 0;\n",
+        diff.source,
+        diff.target,
+        quote_ident(&diff.target)
+    );
+    for sql in &diff.statements {
+        // 存储过程和触发器的语句体中有分号
+        if ["CREATE PROCEDURE", "CREATE FUNCTION", "CREATE TRIGGER"]
+            .iter()
+            .any(|p| sql.starts_with(p))
+        {
+            script.push_str(&format!("DELIMITER ;;\n{};;\nDELIMITER ;\n", sql));
+        } else {
+            script.push_str(&format!("{};\n", sql));
+        }
+    }
+    script.push_str("SET FOREIGN_KEY_CHECKS = 1;\n");
+    let sql_path = format!("{}.sql", path);
+    fs::write(&sql_path, script)?;
+    Ok((json_path, sql_path))
+}
+
+// 打印差异摘要
+pub fn print_diff(diff: &SchemaDiff) {
+    println!(
+        "结构对比 {} -> {}：{} 处差异",
+        diff.source,
+        diff.target,
+        diff.items.len()
+    );
+    for item in &diff.items {
+        let change = match item.change {
+            "missing" => "目标库缺少",
+            "extra" => "目标库多出",
+            _ => "定义不同",
+        };
+        println!("[diff] {} {}: {}", item.object_type, item.name, change);
+    }
+}
+
+#[cfg(test)]
+mod test_schema_diff {
+    use super::*;
+    use crate::model::schema::{ColumnSchema, IndexSchema};
+
+    fn column(name: &str, column_type: &str) -> ColumnSchema {
+        let data_type = column_type.split('(').next().unwrap();
+        ColumnSchema {
+            nullable: false,
+            ..ColumnSchema::new(name, data_type, column_type)
+        }
+    }
+
+    fn table(columns: Vec<ColumnSchema>, indexes: Vec<IndexSchema>) -> TableSchema {
+        TableSchema {
+            name: "orders".to_string(),
+            columns,
+            primary_key: vec!["id".to_string()],
+            indexes,
+            foreign_keys: Vec::new(),
+            comment: None,
+            charset: Some("utf8mb4".to_string()),
+            collation: None,
+        }
+    }
+
+    #[test]
+    fn test_diff_table() {
+        let index = IndexSchema {
+            name: "idx_code".to_string(),
+            columns: vec!["code".to_string()],
+            unique: false,
+            index_type: "BTREE".to_string(),
+            sub_parts: vec![None],
+        };
+        let source = table(
+            vec![
+                column("id", "int"),
+                column("code", "varchar(32)"),
+                column("amount", "decimal(10,2)"),
+            ],
+            vec![index],
+        );
+        let target = table(
+            vec![
+                column("id", "int"),
+                column("amount", "decimal(8,2)"),
+                column("remark", "text"),
+            ],
+            Vec::new(),
+        );
+        let mut diff = SchemaDiff::default();
+        let mut statements = Statements::default();
+        diff_table(&mut diff, &mut statements, &source, &target);
+
+        let changes = diff
+            .items
+            .iter()
+            .map(|i| (i.name.as_str(), i.change))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            changes,
+            vec![
+                ("orders.code", "missing"),
+                ("orders.amount", "changed"),
+                ("orders.remark", "extra"),
+                ("orders.idx_code", "missing"),
+            ]
+        );
+        assert_eq!(
+            statements.tables,
+            vec![
+                "-- ALTER TABLE `orders` DROP COLUMN `remark`".to_string(),
+                "ALTER TABLE `orders`\n  ADD COLUMN `code` varchar(32) NOT NULL AFTER `id`,\n  \
+                 MODIFY COLUMN `amount` decimal(10,2) NOT NULL,\n  \
+                 ADD KEY `idx_code` (`code`)"
+                    .to_string(),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_normalize_definition() {
+        assert_eq!(
+            normalize_definition(
+                "CREATE ALGORITHM=UNDEFINED DEFINER=`root`@`%` SQL SECURITY DEFINER VIEW `v` AS select `shop`.`t`.`id` AS `id` from `shop`.`t`",
+                "shop"
+            ),
+            "CREATE ALGORITHM=UNDEFINED SQL SECURITY DEFINER VIEW `v` AS select `t`.`id` AS `id` from `t`"
+        );
+        assert_eq!(
+            strip_auto_increment(
+                "CREATE TABLE `t` (\n) ENGINE=InnoDB AUTO_INCREMENT=42 DEFAULT CHARSET=utf8mb4"
+            ),
+            "CREATE TABLE `t` (\n) ENGINE=InnoDB DEFAULT CHARSET=utf8mb4"
+        );
+    }
+}
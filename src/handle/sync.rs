@@ -0,0 +1,311 @@
+// 同步源/目标的通用接口，新的数据库类型实现这两个trait即可接入按行同步（跨数据库迁移）
+
+use std::any::Any;
+
+use async_trait::async_trait;
+use tokio::sync::mpsc;
+
+use crate::{
+    handle::{
+        compat::ServerVersion,
+        migrate::{PgSource, PgTarget, SqliteTarget},
+        mysql_sync::{MysqlSource, MysqlTarget},
+        transform::TableTransform,
+        type_map::TranslationReport,
+    },
+    model::{
+        job::{Job, Source, Target},
+        schema::{RowValues, TableSchema},
+    },
+};
+
+pub type BoxError = Box<dyn std::error::Error + Send + Sync>;
+
+// 同步源：读取库、表结构和表数据
+#[async_trait]
+pub trait SyncSource: Send + Sync {
+    // 数据库类型：mysql | postgres | sqlite
+    fn engine(&self) -> &'static str;
+
+    // 只支持一种数据库的任务（mysqldump 备份、pg_dump 同步等）用 downcast_ref 取得具体类型
+    fn as_any(&self) -> &dyn Any;
+
+    // 用于日志和报告，例如 mysql://127.0.0.1:3306/db
+    fn describe(&self, schema: &str) -> String;
+
+    async fn version(&self) -> Result<String, BoxError>;
+
+    // 服务器标识，用于判断源库和目标库是否为同一个服务器
+    async fn server_id(&self) -> Result<String, BoxError> {
+        Ok(self.describe(""))
+    }
+
+    // 所有用户数据库（不包括系统库）
+    async fn list_schemas(&self) -> Result<Vec<String>, BoxError>;
+
+    async fn list_tables(&self, schema: &str) -> Result<Vec<String>, BoxError>;
+
+    // 表结构，tables 为空时读取所有表
+    async fn read_tables(
+        &self,
+        schema: &str,
+        tables: &[String],
+    ) -> Result<Vec<TableSchema>, BoxError>;
+
+    // 源库方言的建表语句
+    async fn read_ddl(&self, schema: &str, table: &str) -> Result<String, BoxError>;
+
+    // 表以外的对象（视图、触发器等），返回 (类型, 名称)
+    async fn read_other_objects(&self, schema: &str) -> Result<Vec<(String, String)>, BoxError>;
+
+    // 逐批读取表数据发送到 tx，返回总行数
+    async fn stream_rows(
+        &self,
+        schema: &str,
+        table: &TableSchema,
+        batch_size: usize,
+        tx: mpsc::Sender<Vec<RowValues>>,
+    ) -> Result<u64, BoxError>;
+}
+
+// 同步目标：建库建表、写入数据
+#[async_trait]
+pub trait SyncTarget: Send + Sync {
+    fn engine(&self) -> &'static str;
+
+    fn as_any(&self) -> &dyn Any;
+
+    fn describe(&self, schema: &str) -> String;
+
+    async fn version(&self) -> Result<String, BoxError>;
+
+    async fn server_id(&self) -> Result<String, BoxError> {
+        Ok(self.describe(""))
+    }
+
+    async fn list_schemas(&self) -> Result<Vec<String>, BoxError>;
+
+    async fn list_tables(&self, schema: &str) -> Result<Vec<String>, BoxError>;
+
+    // 删除目标库中的所有对象（conflict_policy = recreate），之后由 prepare_schema 重新创建
+    async fn drop_schema(&self, schema: &str) -> Result<(), BoxError>;
+
+    // 目标库不存在则创建
+    async fn prepare_schema(&self, schema: &str) -> Result<(), BoxError>;
+
+    // 删除并重新创建表，source_ddl 只在源库与目标库类型相同时提供
+    async fn create_table(
+        &self,
+        schema: &str,
+        table: &TableSchema,
+        source_ddl: Option<&str>,
+        report: &mut TranslationReport,
+    ) -> Result<TargetTable, BoxError>;
+
+    // 写入一批数据，table 为 create_table 返回的表结构
+    async fn apply_rows(
+        &self,
+        schema: &str,
+        table: &TableSchema,
+        rows: Vec<RowValues>,
+    ) -> Result<ApplyResult, BoxError>;
+
+    async fn execute(&self, schema: &str, sql: &str) -> Result<(), BoxError>;
+
+    // 清空已存在的表（data_only 模式导入前），返回写入数据时使用的表结构和导入后需要执行的语句
+    async fn truncate_table(
+        &self,
+        schema: &str,
+        table: &TableSchema,
+    ) -> Result<TargetTable, BoxError>;
+
+    // 一个库同步完成，释放连接等
+    async fn finish_schema(&self, _schema: &str) -> Result<(), BoxError> {
+        Ok(())
+    }
+}
+
+// 目标库中创建的表
+#[derive(Debug, Clone)]
+pub struct TargetTable {
+    pub table: TableSchema,        // 写入数据时使用的表结构
+    pub post_data: Vec<String>,    // 数据导入后执行：索引、序列等
+    pub foreign_keys: Vec<String>, // 所有表导入后执行
+}
+
+impl TargetTable {
+    pub fn new(table: TableSchema) -> Self {
+        TargetTable {
+            table,
+            post_data: Vec::new(),
+            foreign_keys: Vec::new(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default)]
+pub struct ApplyResult {
+    pub rows: u64,
+    pub null_values: u64, // 在目标库中无效或 [handler] 转换失败、写入了NULL的值
+}
+
+// 库内并行时每个分块的行数
+const DEFAULT_CHUNK_ROWS: u64 = 1_000_000;
+
+// 同步内容，mode 和对象开关来自 [job] 配置
+// 触发器、存储过程/函数、事件、视图只在 mysqldump 方式同步时导出
+#[derive(Debug, Clone)]
+pub struct SyncOptions {
+    pub schema: bool, // 表结构
+    pub data: bool,   // 表数据
+    pub triggers: bool,
+    pub routines: bool,
+    pub events: bool,
+    pub views: bool,
+    pub merge: bool,                // conflict_policy = merge：REPLACE 写入已有的表
+    pub ignore_tables: Vec<String>, // 导出单个库时跳过的表或视图（不带库名）
+    pub parallel: usize,            // 库内并行的工作连接数，1 为不并行
+    pub chunk_rows: u64,            // 按主键分块的行数
+    pub unlocked_snapshot: bool,    // 不能加全局读锁时允许各工作连接的快照不一致
+    pub source_version: Option<ServerVersion>, // 源库版本，用于选择 mysqldump 参数
+}
+
+impl Default for SyncOptions {
+    fn default() -> Self {
+        SyncOptions {
+            schema: true,
+            data: true,
+            triggers: true,
+            routines: true,
+            events: true,
+            views: true,
+            merge: false,
+            ignore_tables: Vec::new(),
+            parallel: 1,
+            chunk_rows: DEFAULT_CHUNK_ROWS,
+            unlocked_snapshot: false,
+            source_version: None,
+        }
+    }
+}
+
+impl SyncOptions {
+    pub fn from_job(job: &Job) -> Result<Self, String> {
+        let (schema, data) = match job.mode.as_deref().unwrap_or("full") {
+            "full" => (true, true),
+            "schema_only" => (true, false),
+            "data_only" => (false, true),
+            other => return Err(format!("不支持的同步模式 {}", other)),
+        };
+        Ok(SyncOptions {
+            schema,
+            data,
+            triggers: job.triggers.unwrap_or(schema),
+            routines: job.routines.unwrap_or(schema),
+            events: job.events.unwrap_or(schema),
+            views: job.views.unwrap_or(schema),
+            merge: false,
+            ignore_tables: Vec::new(),
+            parallel: job.parallel.unwrap_or(1).max(1),
+            chunk_rows: job.chunk_rows.unwrap_or(DEFAULT_CHUNK_ROWS).max(1),
+            unlocked_snapshot: job.unlocked_snapshot.unwrap_or(false),
+            source_version: None,
+        })
+    }
+
+    // 只同步数据：目标表需要已存在，导入前清空
+    pub fn data_only(&self) -> bool {
+        self.data && !self.schema
+    }
+
+    pub fn mode(&self) -> &'static str {
+        match (self.schema, self.data) {
+            (true, false) => "schema_only",
+            (false, true) => "data_only",
+            _ => "full",
+        }
+    }
+}
+
+// 连接池名称：{source|target}_{任务名}_{库名|all}
+pub fn pool_name(prefix: &str, job_name: &str, db_name: Option<&str>) -> String {
+    format!("{}_{}_{}", prefix, job_name, db_name.unwrap_or("all"))
+}
+
+// 按数据库类型创建同步源
+pub async fn open_source(
+    job_name: &str,
+    database_type: &str,
+    source: &Source,
+) -> Result<Box<dyn SyncSource>, BoxError> {
+    match database_type {
+        "mysql" => Ok(Box::new(MysqlSource::connect(job_name, source).await?)),
+        "postgres" => Ok(Box::new(PgSource::connect(job_name, source).await?)),
+        _ => Err(format!("暂不支持 {} 作为同步源", database_type).into()),
+    }
+}
+
+// 按数据库类型创建同步目标
+pub async fn open_target(
+    job_name: &str,
+    database_type: &str,
+    target: &Target,
+) -> Result<Box<dyn SyncTarget>, BoxError> {
+    match database_type {
+        "mysql" => Ok(Box::new(MysqlTarget::connect(job_name, target).await?)),
+        "postgres" => Ok(Box::new(PgTarget::connect(job_name, target).await?)),
+        "sqlite" => Ok(Box::new(SqliteTarget::new(target))),
+        _ => Err(format!("暂不支持 {} 作为同步目标", database_type).into()),
+    }
+}
+
+// 复制一个表的数据：读取和写入并行，返回写入结果
+// 按源表结构 table 读取，经过 transform 转换后，按列名对应到目标表 target_table 写入
+#[allow(clippy::too_many_arguments)]
+pub async fn copy_table(
+    source: &dyn SyncSource,
+    target: &dyn SyncTarget,
+    schema: &str,
+    target_schema: &str,
+    table: &TableSchema,
+    transform: Option<&TableTransform>,
+    target_table: &TableSchema,
+    batch_size: usize,
+) -> Result<ApplyResult, BoxError> {
+    let output = transform.map(|t| &t.output).unwrap_or(table);
+    let mut columns = Vec::with_capacity(target_table.columns.len());
+    for column in &target_table.columns {
+        let index = output
+            .columns
+            .iter()
+            .position(|c| c.name == column.name)
+            .ok_or_else(|| format!("表 {} 没有列 {}", output.name, column.name))?;
+        columns.push(index);
+    }
+    let project =
+        columns.len() != output.columns.len() || columns.iter().enumerate().any(|(i, c)| i != *c);
+
+    let (tx, mut rx) = mpsc::channel(2);
+    let read = source.stream_rows(schema, table, batch_size, tx);
+    let write = async {
+        let mut result = ApplyResult::default();
+        while let Some(rows) = rx.recv().await {
+            let (mut rows, failed) = match transform {
+                Some(transform) => transform.apply(rows),
+                None => (rows, 0),
+            };
+            if project {
+                rows = rows
+                    .into_iter()
+                    .map(|mut row| columns.iter().map(|i| row[*i].take()).collect())
+                    .collect();
+            }
+            let applied = target.apply_rows(target_schema, target_table, rows).await?;
+            result.rows += applied.rows;
+            result.null_values += applied.null_values + failed;
+        }
+        Ok::<_, BoxError>(result)
+    };
+    let (_, result) = tokio::try_join!(read, write)?;
+    Ok(result)
+}
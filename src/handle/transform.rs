@@ -0,0 +1,643 @@
+// [handler] 数据转换：按配置对源表的表结构和每一行数据做 set/default/cast/mask/drop/rename
+
+use crate::{
+    handle::{
+        expr::{Expr, parse},
+        mask::Mask,
+    },
+    model::{
+        job::{Handler, TableHandler},
+        schema::{ColumnSchema, RowValues, TableSchema, ValueKind},
+    },
+};
+
+// 一个表匹配到的所有转换步骤，output 为转换后的表结构
+#[derive(Debug, Clone)]
+pub struct TableTransform {
+    stages: Vec<Stage>,
+    pub output: TableSchema,
+}
+
+#[derive(Debug, Clone)]
+struct Stage {
+    sets: Vec<(Option<usize>, Expr)>, // (已有列的序号，新增列为None, 表达式)
+    defaults: Vec<(usize, Expr)>,
+    casts: Vec<(usize, ColumnSchema)>,
+    checks: Vec<(usize, ColumnSchema)>, // set/default 写入的列，结果按列类型检查
+    masks: Vec<(usize, Mask, ColumnSchema)>,
+    salt: Vec<u8>,
+    keep: Vec<usize>, // drop 之后保留的列
+}
+
+impl TableTransform {
+    // 没有匹配的配置时返回None；配置中的列不存在、表达式错误时返回错误
+    pub fn build(
+        handler: Option<&Handler>,
+        db_name: &str,
+        table: &TableSchema,
+    ) -> Result<Option<Self>, String> {
+        let Some(handler) = handler else {
+            return Ok(None);
+        };
+        let mut output = table.clone();
+        let mut stages = Vec::new();
+        for config in handler
+            .tables
+            .iter()
+            .filter(|t| t.matches(db_name, &table.name))
+        {
+            let stage = Stage::build(config, handler.salt.as_deref(), &mut output)
+                .map_err(|e| format!("[handler] 表 {}: {}", table.name, e))?;
+            stages.push(stage);
+        }
+        if stages.is_empty() {
+            return Ok(None);
+        }
+        Ok(Some(TableTransform { stages, output }))
+    }
+
+    // 转换一批数据，返回 (转换后的数据, 计算或类型转换失败而写入NULL的值个数)
+    pub fn apply(&self, rows: Vec<RowValues>) -> (Vec<RowValues>, u64) {
+        let mut failed = 0;
+        let rows = rows
+            .into_iter()
+            .map(|row| {
+                self.stages
+                    .iter()
+                    .fold(row, |row, stage| stage.apply(row, &mut failed))
+            })
+            .collect();
+        (rows, failed)
+    }
+}
+
+impl Stage {
+    // 根据配置修改表结构 table，并生成行转换步骤
+    fn build(
+        config: &TableHandler,
+        salt: Option<&str>,
+        table: &mut TableSchema,
+    ) -> Result<Self, String> {
+        let input_names = table
+            .columns
+            .iter()
+            .map(|c| c.name.clone())
+            .collect::<Vec<_>>();
+        let position = |table: &TableSchema, name: &str| {
+            table
+                .columns
+                .iter()
+                .position(|c| c.name == name)
+                .ok_or_else(|| format!("列 {} 不存在", name))
+        };
+
+        // 表达式都按本步骤输入的列计算
+        let mut sets = Vec::new();
+        let mut written = Vec::new();
+        for (name, input) in &config.set {
+            let expr = parse(input, &input_names)?;
+            let index = table.columns.iter().position(|c| c.name == *name);
+            if index.is_none() {
+                // set 新增的列，类型可以再用 cast 指定
+                table.columns.push(ColumnSchema::new(name, "text", "text"));
+            }
+            written.push(index.unwrap_or(table.columns.len() - 1));
+            sets.push((index, expr));
+        }
+        let mut defaults = Vec::new();
+        for (name, input) in &config.default {
+            let index = position(table, name)?;
+            written.push(index);
+            defaults.push((index, parse(input, &input_names)?));
+        }
+        let mut casts = Vec::new();
+        for (name, column_type) in &config.cast {
+            let index = position(table, name)?;
+            let column = cast_column(&table.columns[index], column_type);
+            table.columns[index] = column.clone();
+            casts.push((index, column));
+        }
+        // 表达式的结果是文本，写入数值等列前按列类型转换（cast 的列由 cast 转换）
+        written.sort_unstable();
+        written.dedup();
+        let checks = written
+            .into_iter()
+            .filter(|index| !casts.iter().any(|(i, _)| i == index))
+            .map(|index| (index, table.columns[index].clone()))
+            .collect();
+        let mut masks = Vec::new();
+        for (name, spec) in &config.mask {
+            let index = position(table, name)?;
+            let mask = Mask::parse(spec)?;
+            if mask.salted() && salt.is_none() {
+                return Err(format!("脱敏方式 {} 需要配置 [handler] salt", spec));
+            }
+            let column = &mut table.columns[index];
+            if mask.text_only() && column.kind() != ValueKind::Text {
+                return Err(format!(
+                    "列 {} 的类型是 {}，脱敏方式 {} 只能用于字符串列",
+                    name, column.column_type, spec
+                ));
+            }
+            if mask == Mask::Null {
+                column.nullable = true;
+            }
+            masks.push((index, mask, column.clone()));
+        }
+
+        let mut keep = (0..table.columns.len()).collect::<Vec<_>>();
+        for name in &config.drop {
+            let index = position(table, name)?;
+            keep.retain(|i| *i != index);
+            drop_column(table, name);
+        }
+        table.columns = keep.iter().map(|i| table.columns[*i].clone()).collect();
+        for (from, to) in &config.rename {
+            position(table, from)?;
+            rename_column(table, from, to);
+        }
+        Ok(Stage {
+            sets,
+            defaults,
+            casts,
+            checks,
+            masks,
+            salt: salt.unwrap_or_default().as_bytes().to_vec(),
+            keep,
+        })
+    }
+
+    fn apply(&self, input: RowValues, failed: &mut u64) -> RowValues {
+        let values = self
+            .sets
+            .iter()
+            .map(|(index, expr)| (*index, eval(expr, &input, failed)))
+            .collect::<Vec<_>>();
+        let mut row = input.clone();
+        for (index, value) in values {
+            match index {
+                Some(index) => row[index] = value,
+                None => row.push(value),
+            }
+        }
+        for (index, expr) in &self.defaults {
+            if row[*index].is_none() {
+                row[*index] = eval(expr, &input, failed);
+            }
+        }
+        for (index, column) in self.checks.iter().chain(&self.casts) {
+            if let Some(value) = row[*index].take() {
+                row[*index] = cast_value(column, value);
+                if row[*index].is_none() {
+                    *failed += 1;
+                }
+            }
+        }
+        for (index, mask, column) in &self.masks {
+            if let Some(value) = row[*index].take() {
+                row[*index] = mask
+                    .apply(&self.salt, column, &String::from_utf8_lossy(&value))
+                    .map(String::into_bytes);
+            }
+        }
+        self.keep.iter().map(|i| row[*i].take()).collect()
+    }
+}
+
+// 计算失败（例如对非数字做运算）时写入NULL
+fn eval(expr: &Expr, row: &RowValues, failed: &mut u64) -> Option<Vec<u8>> {
+    match expr.eval(row) {
+        Ok(value) => value.map(String::into_bytes),
+        Err(_) => {
+            *failed += 1;
+            None
+        }
+    }
+}
+
+// 按MySQL类型写法修改列类型，例如 int、bigint unsigned、decimal(12,2)、varchar(64)
+fn cast_column(column: &ColumnSchema, column_type: &str) -> ColumnSchema {
+    let column_type = column_type.trim().to_lowercase();
+    let data_type = column_type
+        .split(|c: char| c == '(' || c.is_whitespace())
+        .next()
+        .unwrap_or_default()
+        .to_string();
+    let params = column_type
+        .find('(')
+        .map(|start| {
+            column_type[start + 1..]
+                .split(')')
+                .next()
+                .unwrap_or_default()
+                .split(',')
+                .filter_map(|p| p.trim().parse::<u64>().ok())
+                .collect::<Vec<_>>()
+        })
+        .unwrap_or_default();
+    let mut cast = ColumnSchema {
+        data_type,
+        column_type,
+        char_length: None,
+        numeric_precision: None,
+        numeric_scale: None,
+        datetime_precision: None,
+        ..column.clone()
+    };
+    match cast.kind() {
+        ValueKind::Decimal => {
+            cast.numeric_precision = Some(params.first().copied().unwrap_or(10));
+            cast.numeric_scale = Some(params.get(1).copied().unwrap_or(0));
+        }
+        ValueKind::Text => cast.char_length = params.first().copied(),
+        ValueKind::DateTime | ValueKind::Time => cast.datetime_precision = params.first().copied(),
+        _ => {}
+    }
+    if cast.kind() != column.kind() {
+        cast.default = None;
+        cast.extra = String::new();
+        cast.auto_increment = false;
+    }
+    if cast.kind() != ValueKind::Text {
+        cast.charset = None;
+        cast.collation = None;
+    }
+    cast
+}
+
+// 按目标列类型转换值，无法转换时返回None
+// 整数和定点数按文本计算，不经过 f64，避免 BIGINT UNSIGNED 和高精度 DECIMAL 丢失精度
+fn cast_value(column: &ColumnSchema, value: Vec<u8>) -> Option<Vec<u8>> {
+    let text = String::from_utf8_lossy(&value);
+    let text = text.trim();
+    let converted = match column.kind() {
+        ValueKind::Boolean => match text.to_lowercase().as_str() {
+            "true" | "t" | "yes" | "y" => "1".to_string(),
+            "false" | "f" | "no" | "n" | "" => "0".to_string(),
+            other => ((other.parse::<f64>().ok()? != 0.0) as u8).to_string(),
+        },
+        // 小数部分截断
+        ValueKind::Integer => {
+            let (negative, int_part, _) = parse_decimal(text)?;
+            let value = int_part.parse::<i128>().ok()?;
+            let value = if negative { -value } else { value };
+            if column.unsigned() {
+                u64::try_from(value).ok()?.to_string()
+            } else {
+                i64::try_from(value).ok()?.to_string()
+            }
+        }
+        // NaN、inf 不能写入数据库
+        ValueKind::Float => text
+            .parse::<f64>()
+            .ok()
+            .filter(|f| f.is_finite())?
+            .to_string(),
+        ValueKind::Decimal => rescale(text, column.numeric_scale.unwrap_or(0) as usize)?,
+        _ => return Some(value),
+    };
+    Some(converted.into_bytes())
+}
+
+// 解析十进制数（可带符号、小数点和指数），返回 (是否为负数, 整数部分, 小数部分)
+// 整数部分去掉前导0，至少为 "0"
+fn parse_decimal(text: &str) -> Option<(bool, String, String)> {
+    let (negative, text) = match text.as_bytes().first()? {
+        b'-' => (true, &text[1..]),
+        b'+' => (false, &text[1..]),
+        _ => (false, text),
+    };
+    let (mantissa, exponent) = match text.find(['e', 'E']) {
+        // 指数过大时超出所有整数和DECIMAL类型的范围
+        Some(pos) => (
+            &text[..pos],
+            text[pos + 1..]
+                .parse::<i32>()
+                .ok()
+                .filter(|e| e.abs() <= 100)?,
+        ),
+        None => (text, 0),
+    };
+    let (int_part, frac_part) = mantissa.split_once('.').unwrap_or((mantissa, ""));
+    if int_part.is_empty() && frac_part.is_empty()
+        || !int_part
+            .bytes()
+            .chain(frac_part.bytes())
+            .all(|b| b.is_ascii_digit())
+    {
+        return None;
+    }
+    // 按指数移动小数点
+    let digits = format!("{}{}", int_part, frac_part);
+    let point = int_part.len() as i64 + exponent as i64;
+    let (int_part, frac_part) = if point <= 0 {
+        (
+            String::new(),
+            format!("{}{}", "0".repeat((-point) as usize), digits),
+        )
+    } else if point as usize >= digits.len() {
+        (
+            format!("{}{}", digits, "0".repeat(point as usize - digits.len())),
+            String::new(),
+        )
+    } else {
+        let (i, f) = digits.split_at(point as usize);
+        (i.to_string(), f.to_string())
+    };
+    let int_part = int_part.trim_start_matches('0');
+    let int_part = if int_part.is_empty() { "0" } else { int_part };
+    Some((negative, int_part.to_string(), frac_part))
+}
+
+// 定点数保留 scale 位小数，四舍五入（与MySQL相同，.5 远离0）
+fn rescale(text: &str, scale: usize) -> Option<String> {
+    let (negative, int_part, mut frac_part) = parse_decimal(text)?;
+    let round_up = frac_part.as_bytes().get(scale).is_some_and(|d| *d >= b'5');
+    if frac_part.len() < scale {
+        frac_part.push_str(&"0".repeat(scale - frac_part.len()));
+    }
+    frac_part.truncate(scale);
+    let mut digits = format!("{}{}", int_part, frac_part).into_bytes();
+    if round_up {
+        let mut i = digits.len();
+        loop {
+            if i == 0 {
+                digits.insert(0, b'1');
+                break;
+            }
+            i -= 1;
+            if digits[i] == b'9' {
+                digits[i] = b'0';
+            } else {
+                digits[i] += 1;
+                break;
+            }
+        }
+    }
+    let digits = String::from_utf8(digits).ok()?;
+    let (int_part, frac_part) = digits.split_at(digits.len() - scale);
+    let int_part = match int_part.trim_start_matches('0') {
+        "" => "0",
+        int_part => int_part,
+    };
+    let zero = int_part == "0" && frac_part.bytes().all(|d| d == b'0');
+    let sign = if negative && !zero { "-" } else { "" };
+    Some(if scale == 0 {
+        format!("{}{}", sign, int_part)
+    } else {
+        format!("{}{}.{}", sign, int_part, frac_part)
+    })
+}
+
+fn drop_column(table: &mut TableSchema, name: &str) {
+    let name = name.to_string();
+    if table.primary_key.contains(&name) {
+        table.primary_key.clear();
+    }
+    table.indexes.retain(|index| !index.columns.contains(&name));
+    table.foreign_keys.retain(|fk| !fk.columns.contains(&name));
+}
+
+fn rename_column(table: &mut TableSchema, from: &str, to: &str) {
+    let rename = |names: &mut Vec<String>| {
+        for name in names.iter_mut().filter(|n| *n == from) {
+            *name = to.to_string();
+        }
+    };
+    for column in table.columns.iter_mut().filter(|c| c.name == from) {
+        column.name = to.to_string();
+    }
+    rename(&mut table.primary_key);
+    for index in &mut table.indexes {
+        rename(&mut index.columns);
+    }
+    for fk in &mut table.foreign_keys {
+        rename(&mut fk.columns);
+    }
+}
+
+#[cfg(test)]
+mod test_transform {
+    use super::*;
+    use crate::model::schema::IndexSchema;
+
+    #[test]
+    fn test_table_transform() {
+        let table = TableSchema {
+            name: "orders".to_string(),
+            columns: vec![
+                ColumnSchema::new("id", "int", "int"),
+                ColumnSchema::new("customer_name", "varchar", "varchar(32)"),
+                ColumnSchema::new("amount", "varchar", "varchar(16)"),
+                ColumnSchema::new("internal_note", "text", "text"),
+            ],
+            primary_key: vec!["id".to_string()],
+            indexes: vec![IndexSchema {
+                name: "idx_customer".to_string(),
+                columns: vec!["customer_name".to_string()],
+                unique: false,
+                index_type: "BTREE".to_string(),
+                sub_parts: vec![None],
+            }],
+            foreign_keys: Vec::new(),
+            comment: None,
+            charset: None,
+            collation: None,
+        };
+        let handler: Handler = toml::from_str(
+            r#"
+            [[tables]]
+            table = "shop.orders"
+            set = { source = "'canteen'", amount_cents = "amount * 100" }
+            default = { customer_name = "'anonymous'" }
+            cast = { amount = "decimal(10,2)", amount_cents = "bigint" }
+            drop = ["internal_note"]
+            rename = { customer_name = "customer" }
+
+            [[tables]]
+            table = "other"
+            drop = ["id"]
+            "#,
+        )
+        .unwrap();
+
+        assert!(
+            TableTransform::build(Some(&handler), "report", &table)
+                .unwrap()
+                .is_none()
+        );
+        let transform = TableTransform::build(Some(&handler), "shop", &table)
+            .unwrap()
+            .unwrap();
+        let names = transform
+            .output
+            .columns
+            .iter()
+            .map(|c| c.name.as_str())
+            .collect::<Vec<_>>();
+        assert_eq!(
+            names,
+            vec!["id", "customer", "amount", "amount_cents", "source"]
+        );
+        assert_eq!(transform.output.columns[2].column_type, "decimal(10,2)");
+        assert_eq!(transform.output.columns[3].data_type, "bigint");
+        assert_eq!(transform.output.indexes[0].columns, vec!["customer"]);
+
+        let rows = vec![
+            vec![
+                Some(b"1".to_vec()),
+                None,
+                Some(b"12.5".to_vec()),
+                Some(b"secret".to_vec()),
+            ],
+            vec![
+                Some(b"2".to_vec()),
+                Some(b"Tom".to_vec()),
+                Some(b"n/a".to_vec()),
+                None,
+            ],
+        ];
+        let (rows, failed) = transform.apply(rows);
+        assert_eq!(
+            rows[0],
+            vec![
+                Some(b"1".to_vec()),
+                Some(b"anonymous".to_vec()),
+                Some(b"12.50".to_vec()),
+                Some(b"1250".to_vec()),
+                Some(b"canteen".to_vec()),
+            ]
+        );
+        assert_eq!(rows[1][2], None);
+        assert_eq!(rows[1][3], None);
+        assert_eq!(failed, 2);
+
+        let mut broken = handler.clone();
+        broken.tables[0].drop = vec!["missing".to_string()];
+        assert!(TableTransform::build(Some(&broken), "shop", &table).is_err());
+    }
+
+    #[test]
+    fn test_cast_value() {
+        let cast = |column_type: &str, value: &str| {
+            let column = cast_column(&ColumnSchema::new("c", "text", "text"), column_type);
+            cast_value(&column, value.as_bytes().to_vec()).map(|v| String::from_utf8(v).unwrap())
+        };
+        let decimal = [
+            ("2.675", "2.68"),
+            ("-2.675", "-2.68"),
+            ("2.674999", "2.67"),
+            ("9.995", "10.00"),
+            ("0.005", "0.01"),
+            ("-0.001", "0.00"),
+            ("12.5", "12.50"),
+            (".5", "0.50"),
+            ("1.5e2", "150.00"),
+            ("25e-3", "0.03"),
+        ];
+        for (value, expected) in decimal {
+            assert_eq!(
+                cast("decimal(10,2)", value).as_deref(),
+                Some(expected),
+                "{}",
+                value
+            );
+        }
+        // 超过 f64 精度的定点数
+        assert_eq!(
+            cast("decimal(38,9)", "12345678901234567890.123456789").as_deref(),
+            Some("12345678901234567890.123456789")
+        );
+        assert_eq!(cast("decimal(5,0)", "99.5").as_deref(), Some("100"));
+        assert_eq!(cast("decimal(10,2)", "n/a"), None);
+        assert_eq!(cast("decimal(10,2)", "1.2.3"), None);
+        assert_eq!(cast("decimal(10,2)", "-"), None);
+
+        assert_eq!(
+            cast("bigint unsigned", "18446744073709551615").as_deref(),
+            Some("18446744073709551615")
+        );
+        assert_eq!(cast("bigint unsigned", "18446744073709551616"), None);
+        assert_eq!(cast("bigint unsigned", "-1"), None);
+        assert_eq!(
+            cast("bigint", "-9223372036854775808").as_deref(),
+            Some("-9223372036854775808")
+        );
+        assert_eq!(cast("bigint", "9223372036854775808"), None);
+        assert_eq!(
+            cast("bigint", "9007199254740993").as_deref(),
+            Some("9007199254740993")
+        );
+        assert_eq!(cast("int", "3.9").as_deref(), Some("3"));
+        assert_eq!(cast("int", "-3.9").as_deref(), Some("-3"));
+        assert_eq!(cast("int", "1e3").as_deref(), Some("1000"));
+        assert_eq!(cast("int", "abc"), None);
+        assert_eq!(cast("int", "1e999999999"), None);
+    }
+
+    // set/default 的结果不是数值时写入NULL，不会原样写入数值列
+    #[test]
+    fn test_set_checks_column_kind() {
+        let table = TableSchema {
+            name: "orders".to_string(),
+            columns: vec![
+                ColumnSchema::new("id", "int", "int"),
+                ColumnSchema::new("note", "varchar", "varchar(32)"),
+                ColumnSchema::new("amount", "int", "int"),
+                ColumnSchema::new("rate", "double", "double"),
+            ],
+            primary_key: vec!["id".to_string()],
+            indexes: Vec::new(),
+            foreign_keys: Vec::new(),
+            comment: None,
+            charset: None,
+            collation: None,
+        };
+        let handler: Handler = toml::from_str(
+            r#"
+            [[tables]]
+            table = "orders"
+            set = { id = "note", rate = "'NaN'" }
+            default = { amount = "'1); DROP TABLE orders; --'" }
+            "#,
+        )
+        .unwrap();
+        let transform = TableTransform::build(Some(&handler), "shop", &table)
+            .unwrap()
+            .unwrap();
+        let rows = vec![
+            vec![
+                Some(b"1); DROP TABLE orders; --".to_vec()),
+                Some(b"1); DROP TABLE orders; --".to_vec()),
+                None,
+                Some(b"1.5".to_vec()),
+            ],
+            vec![
+                Some(b"2".to_vec()),
+                Some(b" 42 ".to_vec()),
+                Some(b"7".to_vec()),
+                None,
+            ],
+        ];
+        let (rows, failed) = transform.apply(rows);
+        assert_eq!(
+            rows[0],
+            vec![
+                None,
+                Some(b"1); DROP TABLE orders; --".to_vec()),
+                None,
+                None
+            ]
+        );
+        assert_eq!(
+            rows[1],
+            vec![
+                Some(b"42".to_vec()),
+                Some(b" 42 ".to_vec()),
+                Some(b"7".to_vec()),
+                None
+            ]
+        );
+        assert_eq!(failed, 4);
+    }
+}
@@ -0,0 +1,962 @@
+// MySQL 到 MySQL / PostgreSQL / SQLite 的类型映射和表结构转换
+// 无法自动转换的对象记录到 TranslationReport 中
+
+use serde::Serialize;
+
+use crate::{
+    handle::{mysql_schema::quote_ident as mysql_ident, pg_help::quote_ident},
+    model::schema::{
+        ColumnSchema, ForeignKeySchema, IndexSchema, RowValues, TableSchema, ValueKind,
+    },
+};
+
+// PostgreSQL 标识符最长63字节
+const PG_MAX_IDENT_LEN: usize = 63;
+
+#[derive(Debug, Default, Serialize)]
+pub struct TranslationReport {
+    pub source: String,
+    pub target: String,
+    pub tables: Vec<TableReport>,
+    pub warnings: Vec<ReportItem>,     // 已转换，但语义可能有差异
+    pub untranslated: Vec<ReportItem>, // 没有转换，需要人工处理
+}
+
+#[derive(Debug, Serialize)]
+pub struct TableReport {
+    pub name: String,
+    pub rows: u64,
+    pub null_values: u64, // 目标库不支持而写入NULL的值（例如 0000-00-00）
+}
+
+#[derive(Debug, Serialize)]
+pub struct ReportItem {
+    pub object: String,
+    pub message: String,
+}
+
+impl TranslationReport {
+    pub fn warn(&mut self, object: impl Into<String>, message: impl Into<String>) {
+        self.warnings.push(ReportItem {
+            object: object.into(),
+            message: message.into(),
+        });
+    }
+
+    pub fn untranslated(&mut self, object: impl Into<String>, message: impl Into<String>) {
+        self.untranslated.push(ReportItem {
+            object: object.into(),
+            message: message.into(),
+        });
+    }
+}
+
+// 转换后的表：建表语句、导入数据之后执行的语句（索引、注释、自增序列），外键最后创建
+#[derive(Debug)]
+pub struct PgTableDdl {
+    pub table: TableSchema, // 实际创建的列（去掉了无法转换的列）
+    pub create_table: String,
+    pub post_data: Vec<String>,
+    pub foreign_keys: Vec<String>,
+}
+
+// MySQL列类型转换为PostgreSQL类型，无法转换时返回原因
+pub fn mysql_type_to_pg(column: &ColumnSchema) -> Result<String, String> {
+    if column.is_bool() {
+        return Ok("boolean".to_string());
+    }
+    let unsigned = column.unsigned();
+    let pg_type = match column.data_type.as_str() {
+        "tinyint" => "smallint".to_string(),
+        "smallint" if unsigned => "integer".to_string(),
+        "smallint" => "smallint".to_string(),
+        "mediumint" => "integer".to_string(),
+        "int" | "integer" if unsigned => "bigint".to_string(),
+        "int" | "integer" => "integer".to_string(),
+        // 自增列必须是整数类型
+        "bigint" if unsigned && !column.auto_increment => "numeric(20,0)".to_string(),
+        "bigint" => "bigint".to_string(),
+        "decimal" | "numeric" => match (column.numeric_precision, column.numeric_scale) {
+            (Some(p), Some(s)) => format!("numeric({},{})", p, s),
+            _ => "numeric".to_string(),
+        },
+        "float" => "real".to_string(),
+        "double" | "real" => "double precision".to_string(),
+        "bit" => "bigint".to_string(),
+        "char" => format!("char({})", column.char_length.unwrap_or(1)),
+        "varchar" => format!("varchar({})", column.char_length.unwrap_or(255)),
+        "tinytext" | "text" | "mediumtext" | "longtext" => "text".to_string(),
+        "binary" | "varbinary" | "tinyblob" | "blob" | "mediumblob" | "longblob" => {
+            "bytea".to_string()
+        }
+        "date" => "date".to_string(),
+        "datetime" | "timestamp" => match column.datetime_precision {
+            Some(p) if p > 0 => format!("timestamp({})", p),
+            _ => "timestamp".to_string(),
+        },
+        "time" => match column.datetime_precision {
+            Some(p) if p > 0 => format!("time({})", p),
+            _ => "time".to_string(),
+        },
+        "year" => "smallint".to_string(),
+        "json" => "jsonb".to_string(),
+        "enum" => {
+            let max_len = column
+                .enum_values()
+                .iter()
+                .map(|v| v.chars().count())
+                .max()
+                .unwrap_or(1);
+            format!("varchar({})", max_len.max(1))
+        }
+        "set" => "text".to_string(),
+        other => return Err(format!("不支持的类型 {}", other)),
+    };
+    Ok(pg_type)
+}
+
+// 转换列默认值
+fn translate_default(column: &ColumnSchema) -> Result<Option<String>, String> {
+    let Some(default) = &column.default else {
+        return Ok(None);
+    };
+    let lower = default.to_lowercase();
+    if lower.starts_with("current_timestamp") || lower == "now()" {
+        return Ok(Some("CURRENT_TIMESTAMP".to_string()));
+    }
+    // MySQL 8 的表达式默认值
+    if column.extra.contains("DEFAULT_GENERATED") {
+        return Err(format!("表达式默认值 {}", default));
+    }
+    if lower.starts_with("0000-00-00") {
+        return Err(format!("零日期默认值 {}", default));
+    }
+    let value = match column.kind() {
+        ValueKind::Boolean => {
+            let truthy = !matches!(default.as_str(), "0" | "b'0'" | "'0'");
+            (if truthy { "true" } else { "false" }).to_string()
+        }
+        ValueKind::Bit => {
+            let bits = default.trim_start_matches("b'").trim_end_matches('\'');
+            u64::from_str_radix(bits, 2)
+                .map_err(|_| format!("无法识别的默认值 {}", default))?
+                .to_string()
+        }
+        ValueKind::Integer | ValueKind::Float | ValueKind::Decimal => default.clone(),
+        _ => pg_quote(default),
+    };
+    Ok(Some(value))
+}
+
+// 转换表结构
+pub fn translate_table_to_pg(table: &TableSchema, report: &mut TranslationReport) -> PgTableDdl {
+    let table_ident = quote_ident(&table.name);
+    let mut effective = table.clone();
+    effective.columns.clear();
+    let mut definitions = Vec::new();
+    let mut post_data = Vec::new();
+    let mut case_insensitive = false;
+
+    for column in &table.columns {
+        let object = format!("{}.{}", table.name, column.name);
+        let pg_type = match mysql_type_to_pg(column) {
+            Ok(pg_type) => pg_type,
+            Err(reason) => {
+                report.untranslated(&object, format!("{}，该列没有迁移", reason));
+                continue;
+            }
+        };
+        let mut definition = format!("{} {}", quote_ident(&column.name), pg_type);
+        if let Some(collation) = &column.collation {
+            if collation.ends_with("_bin") {
+                definition.push_str(" COLLATE \"C\"");
+            } else if collation.contains("_ci") {
+                case_insensitive = true;
+            }
+        }
+        if column.auto_increment {
+            definition.push_str(" GENERATED BY DEFAULT AS IDENTITY");
+            // 导入数据后把序列调整到最大值之后
+            post_data.push(format!(
+                "SELECT setval(pg_get_serial_sequence({}, {}), COALESCE(MAX({}), 0) + 1, false) FROM {}",
+                pg_quote(&table_ident),
+                pg_quote(&column.name),
+                quote_ident(&column.name),
+                table_ident
+            ));
+        } else {
+            match translate_default(column) {
+                Ok(Some(default)) => definition.push_str(&format!(" DEFAULT {}", default)),
+                Ok(None) => {}
+                Err(reason) => report.untranslated(&object, format!("{}没有迁移", reason)),
+            }
+        }
+        if !column.nullable {
+            definition.push_str(" NOT NULL");
+        }
+        match column.data_type.as_str() {
+            "enum" => {
+                let values = column
+                    .enum_values()
+                    .iter()
+                    .map(|v| pg_quote(v))
+                    .collect::<Vec<_>>()
+                    .join(", ");
+                definition.push_str(&format!(
+                    " CHECK ({} IN ({}))",
+                    quote_ident(&column.name),
+                    values
+                ));
+            }
+            "set" => report.warn(&object, "SET类型转换为text，没有取值约束"),
+            "bigint" if column.unsigned() => report.warn(
+                &object,
+                if column.auto_increment {
+                    "bigint unsigned 自增列转换为bigint，超过 2^63-1 的值无法导入"
+                } else {
+                    "bigint unsigned 转换为numeric(20,0)"
+                },
+            ),
+            "timestamp" => report.warn(&object, "timestamp转换为不带时区的timestamp"),
+            _ => {}
+        }
+        if column.extra.contains("on update") {
+            report.untranslated(&object, format!("{}（需要使用触发器实现）", column.extra));
+        }
+        if column.extra.contains("GENERATED") && !column.extra.contains("DEFAULT_GENERATED") {
+            report.untranslated(&object, "生成列转换为普通列，数据按源库的值导入");
+        }
+        if let Some(charset) = &column.charset
+            && !matches!(
+                charset.as_str(),
+                "utf8" | "utf8mb3" | "utf8mb4" | "ascii" | "binary"
+            )
+        {
+            report.warn(&object, format!("字符集 {} 转换为 UTF-8", charset));
+        }
+        if let Some(comment) = &column.comment {
+            post_data.push(format!(
+                "COMMENT ON COLUMN {}.{} IS {}",
+                table_ident,
+                quote_ident(&column.name),
+                pg_quote(comment)
+            ));
+        }
+        definitions.push(definition);
+        effective.columns.push(column.clone());
+    }
+
+    if case_insensitive {
+        report.warn(
+            &table.name,
+            "源表使用不区分大小写的排序规则，PostgreSQL中比较和唯一约束区分大小写",
+        );
+    }
+    if !table.primary_key.is_empty() {
+        definitions.push(format!(
+            "PRIMARY KEY ({})",
+            quote_idents(&table.primary_key)
+        ));
+    }
+
+    for index in &table.indexes {
+        let object = format!("{}.{}", table.name, index.name);
+        if index.index_type == "FULLTEXT" || index.index_type == "SPATIAL" {
+            report.untranslated(&object, format!("{} 索引没有迁移", index.index_type));
+            continue;
+        }
+        if index.columns.iter().any(|c| c.is_empty()) {
+            report.untranslated(&object, "函数索引没有迁移");
+            continue;
+        }
+        if index.sub_parts.iter().any(Option::is_some) {
+            report.warn(&object, "前缀索引转换为整列索引");
+        }
+        post_data.push(format!(
+            "CREATE {}INDEX {} ON {} ({})",
+            if index.unique { "UNIQUE " } else { "" },
+            quote_ident(&pg_object_name(&table.name, &index.name)),
+            table_ident,
+            quote_idents(&index.columns)
+        ));
+    }
+
+    if let Some(comment) = &table.comment {
+        post_data.push(format!(
+            "COMMENT ON TABLE {} IS {}",
+            table_ident,
+            pg_quote(comment)
+        ));
+    }
+
+    let foreign_keys = table
+        .foreign_keys
+        .iter()
+        .map(|fk| {
+            format!(
+                "ALTER TABLE {} ADD CONSTRAINT {} FOREIGN KEY ({}) REFERENCES {} ({}) ON UPDATE {} ON DELETE {}",
+                table_ident,
+                quote_ident(&pg_object_name(&table.name, &fk.name)),
+                quote_idents(&fk.columns),
+                quote_ident(&fk.ref_table),
+                quote_idents(&fk.ref_columns),
+                fk.on_update,
+                fk.on_delete
+            )
+        })
+        .collect();
+
+    PgTableDdl {
+        create_table: format!(
+            "CREATE TABLE {} (\n  {}\n)",
+            table_ident,
+            definitions.join(",\n  ")
+        ),
+        table: effective,
+        post_data,
+        foreign_keys,
+    }
+}
+
+// 一行数据转换为PostgreSQL的VALUES项，返回 (SQL, 写入NULL的值个数)
+pub fn pg_row_values(table: &TableSchema, row: &RowValues) -> (String, u64) {
+    let mut nulls = 0;
+    let values = table
+        .columns
+        .iter()
+        .zip(row)
+        .map(|(column, value)| {
+            pg_literal(column, value.as_deref()).unwrap_or_else(|| {
+                nulls += 1;
+                "NULL".to_string()
+            })
+        })
+        .collect::<Vec<_>>()
+        .join(", ");
+    (format!("({})", values), nulls)
+}
+
+// 单个值的字面量，源库的值在目标库中无效时返回None（写入NULL）
+fn pg_literal(column: &ColumnSchema, value: Option<&[u8]>) -> Option<String> {
+    let Some(value) = value else {
+        return Some("NULL".to_string());
+    };
+    let literal = match column.kind() {
+        ValueKind::Boolean => {
+            // bit(1) 返回的是原始字节
+            let truthy = if column.data_type == "bit" {
+                value.iter().any(|b| *b != 0)
+            } else {
+                value != b"0"
+            };
+            (if truthy { "true" } else { "false" }).to_string()
+        }
+        ValueKind::Bit => value
+            .iter()
+            .fold(0u64, |acc, b| (acc << 8) | *b as u64)
+            .to_string(),
+        ValueKind::Binary => format!("'\\x{}'::bytea", hex::encode(value)),
+        ValueKind::Date | ValueKind::DateTime if value.starts_with(b"0000-00-00") => return None,
+        _ => pg_quote(&String::from_utf8_lossy(value).replace('\0', "")),
+    };
+    Some(literal)
+}
+
+// 转换后的SQLite表：建表语句（外键在建表语句中）、导入数据之后创建的索引
+#[derive(Debug)]
+pub struct SqliteTableDdl {
+    pub table: TableSchema,
+    pub create_table: String,
+    pub post_data: Vec<String>,
+}
+
+// MySQL列类型转换为SQLite类型（按SQLite的类型亲和性）
+pub fn mysql_type_to_sqlite(column: &ColumnSchema) -> Result<&'static str, String> {
+    let sqlite_type = match column.kind() {
+        ValueKind::Integer | ValueKind::Boolean | ValueKind::Bit => "INTEGER",
+        ValueKind::Float => "REAL",
+        // 用TEXT保存，避免精度丢失
+        ValueKind::Decimal => "TEXT",
+        ValueKind::Binary => "BLOB",
+        ValueKind::Text
+        | ValueKind::Date
+        | ValueKind::DateTime
+        | ValueKind::Time
+        | ValueKind::Json => "TEXT",
+    };
+    if matches!(
+        column.data_type.as_str(),
+        "geometry"
+            | "point"
+            | "linestring"
+            | "polygon"
+            | "multipoint"
+            | "multilinestring"
+            | "multipolygon"
+            | "geometrycollection"
+    ) {
+        return Err(format!("不支持的类型 {}", column.data_type));
+    }
+    Ok(sqlite_type)
+}
+
+// 转换表结构
+pub fn translate_table_to_sqlite(
+    table: &TableSchema,
+    report: &mut TranslationReport,
+) -> SqliteTableDdl {
+    let table_ident = quote_ident(&table.name);
+    let mut effective = table.clone();
+    effective.columns.clear();
+    let mut definitions = Vec::new();
+
+    // 单列整数自增主键使用 INTEGER PRIMARY KEY AUTOINCREMENT
+    let rowid_pk = table.primary_key.len() == 1
+        && table
+            .columns
+            .iter()
+            .any(|c| c.name == table.primary_key[0] && c.auto_increment);
+
+    for column in &table.columns {
+        let object = format!("{}.{}", table.name, column.name);
+        let sqlite_type = match mysql_type_to_sqlite(column) {
+            Ok(sqlite_type) => sqlite_type,
+            Err(reason) => {
+                report.untranslated(&object, format!("{}，该列没有迁移", reason));
+                continue;
+            }
+        };
+        let mut definition = format!("{} {}", quote_ident(&column.name), sqlite_type);
+        if rowid_pk && column.name == table.primary_key[0] {
+            definition.push_str(" PRIMARY KEY AUTOINCREMENT");
+        } else if !column.nullable {
+            definition.push_str(" NOT NULL");
+        }
+        if !column.auto_increment {
+            match translate_default(column) {
+                Ok(Some(default)) => {
+                    let default = match default.as_str() {
+                        "true" => "1".to_string(),
+                        "false" => "0".to_string(),
+                        _ => default,
+                    };
+                    definition.push_str(&format!(" DEFAULT {}", default))
+                }
+                Ok(None) => {}
+                Err(reason) => report.untranslated(&object, format!("{}没有迁移", reason)),
+            }
+        }
+        if column.data_type == "enum" {
+            let values = column
+                .enum_values()
+                .iter()
+                .map(|v| pg_quote(v))
+                .collect::<Vec<_>>()
+                .join(", ");
+            definition.push_str(&format!(
+                " CHECK ({} IN ({}))",
+                quote_ident(&column.name),
+                values
+            ));
+        }
+        if let Some(collation) = &column.collation
+            && collation.contains("_ci")
+        {
+            definition.push_str(" COLLATE NOCASE");
+        }
+        definitions.push(definition);
+        effective.columns.push(column.clone());
+    }
+
+    if !rowid_pk && !table.primary_key.is_empty() {
+        definitions.push(format!(
+            "PRIMARY KEY ({})",
+            quote_idents(&table.primary_key)
+        ));
+    }
+    for fk in &table.foreign_keys {
+        definitions.push(format!(
+            "FOREIGN KEY ({}) REFERENCES {} ({}) ON UPDATE {} ON DELETE {}",
+            quote_idents(&fk.columns),
+            quote_ident(&fk.ref_table),
+            quote_idents(&fk.ref_columns),
+            fk.on_update,
+            fk.on_delete
+        ));
+    }
+
+    let mut post_data = Vec::new();
+    for index in &table.indexes {
+        let object = format!("{}.{}", table.name, index.name);
+        if index.index_type == "FULLTEXT" || index.index_type == "SPATIAL" {
+            report.untranslated(&object, format!("{} 索引没有迁移", index.index_type));
+            continue;
+        }
+        if index.columns.iter().any(|c| c.is_empty()) {
+            report.untranslated(&object, "函数索引没有迁移");
+            continue;
+        }
+        post_data.push(format!(
+            "CREATE {}INDEX {} ON {} ({})",
+            if index.unique { "UNIQUE " } else { "" },
+            quote_ident(&format!("{}_{}", table.name, index.name)),
+            table_ident,
+            quote_idents(&index.columns)
+        ));
+    }
+
+    SqliteTableDdl {
+        create_table: format!(
+            "CREATE TABLE {} (\n  {}\n)",
+            table_ident,
+            definitions.join(",\n  ")
+        ),
+        table: effective,
+        post_data,
+    }
+}
+
+// 一行数据转换为SQLite的VALUES项
+pub fn sqlite_row_values(table: &TableSchema, row: &RowValues) -> String {
+    let values = table
+        .columns
+        .iter()
+        .zip(row)
+        .map(|(column, value)| sqlite_literal(column, value.as_deref()))
+        .collect::<Vec<_>>()
+        .join(", ");
+    format!("({})", values)
+}
+
+fn sqlite_literal(column: &ColumnSchema, value: Option<&[u8]>) -> String {
+    let Some(value) = value else {
+        return "NULL".to_string();
+    };
+    match column.kind() {
+        ValueKind::Boolean if column.data_type == "bit" => {
+            (value.iter().any(|b| *b != 0) as u8).to_string()
+        }
+        ValueKind::Bit => value
+            .iter()
+            .fold(0u64, |acc, b| (acc << 8) | *b as u64)
+            .to_string(),
+        ValueKind::Binary => format!("X'{}'", hex::encode(value)),
+        _ => pg_quote(&String::from_utf8_lossy(value)),
+    }
+}
+
+// 一行数据转换为MySQL的VALUES项（MySQL -> MySQL 按行同步）
+// 文本按utf8mb4十六进制写入，不依赖转义规则和 sql_mode，由目标库转换为列的字符集
+// 数值也写成字符串字面量，由目标库转换，值不是数字时报错而不会拼接到SQL中
+pub fn mysql_row_values(table: &TableSchema, row: &RowValues) -> String {
+    let values = table
+        .columns
+        .iter()
+        .zip(row)
+        .map(|(column, value)| match value.as_deref() {
+            None => "NULL".to_string(),
+            Some(value) => match column.kind() {
+                ValueKind::Integer | ValueKind::Float | ValueKind::Decimal => {
+                    mysql_quote(&String::from_utf8_lossy(value))
+                }
+                ValueKind::Boolean if column.data_type != "bit" => {
+                    mysql_quote(&String::from_utf8_lossy(value))
+                }
+                ValueKind::Boolean | ValueKind::Bit | ValueKind::Binary => {
+                    format!("X'{}'", hex::encode(value))
+                }
+                _ => format!("_utf8mb4 X'{}'", hex::encode(value)),
+            },
+        })
+        .collect::<Vec<_>>()
+        .join(", ");
+    format!("({})", values)
+}
+
+// 由表结构生成MySQL建表语句（表结构经过 [handler] 转换，不能使用源库的建表语句时）
+pub fn translate_table_to_mysql(table: &TableSchema, report: &mut TranslationReport) -> String {
+    let mut definitions = Vec::new();
+    for column in &table.columns {
+        if column.extra.contains("GENERATED") && !column.extra.contains("DEFAULT_GENERATED") {
+            report.warn(
+                format!("{}.{}", table.name, column.name),
+                "生成列转换为普通列，数据按源库的值导入",
+            );
+        }
+        definitions.push(mysql_column_definition(column));
+    }
+    if !table.primary_key.is_empty() {
+        definitions.push(format!(
+            "PRIMARY KEY ({})",
+            mysql_idents(&table.primary_key)
+        ));
+    }
+    for index in &table.indexes {
+        if index.columns.iter().any(|c| c.is_empty()) {
+            report.untranslated(format!("{}.{}", table.name, index.name), "函数索引没有创建");
+            continue;
+        }
+        definitions.push(mysql_index_definition(index));
+    }
+    for fk in &table.foreign_keys {
+        definitions.push(mysql_foreign_key_definition(fk));
+    }
+
+    let mut options = String::new();
+    if let Some(charset) = &table.charset {
+        options.push_str(&format!(" DEFAULT CHARSET={}", charset));
+    }
+    if let Some(collation) = &table.collation {
+        options.push_str(&format!(" COLLATE={}", collation));
+    }
+    if let Some(comment) = &table.comment {
+        options.push_str(&format!(" COMMENT={}", mysql_quote(comment)));
+    }
+    format!(
+        "CREATE TABLE {} (\n  {}\n){}",
+        mysql_ident(&table.name),
+        definitions.join(",\n  "),
+        options
+    )
+}
+
+// MySQL列定义，例如 `name` varchar(32) CHARACTER SET utf8mb4 COLLATE utf8mb4_general_ci NOT NULL DEFAULT ''
+pub fn mysql_column_definition(column: &ColumnSchema) -> String {
+    let mut definition = format!("{} {}", mysql_ident(&column.name), column.column_type);
+    if let (Some(charset), Some(collation)) = (&column.charset, &column.collation) {
+        definition.push_str(&format!(" CHARACTER SET {} COLLATE {}", charset, collation));
+    }
+    definition.push_str(if column.nullable {
+        " NULL"
+    } else {
+        " NOT NULL"
+    });
+    if let Some(default) = &column.default {
+        let lower = default.to_lowercase();
+        let value = if lower.starts_with("current_timestamp") || lower == "now()" {
+            default.clone()
+        } else if column.extra.contains("DEFAULT_GENERATED") {
+            // MySQL 8 的表达式默认值
+            format!("({})", default)
+        } else {
+            match column.kind() {
+                ValueKind::Integer
+                | ValueKind::Float
+                | ValueKind::Decimal
+                | ValueKind::Boolean
+                | ValueKind::Bit => default.clone(),
+                _ => mysql_quote(default),
+            }
+        };
+        definition.push_str(&format!(" DEFAULT {}", value));
+    }
+    if column.auto_increment {
+        definition.push_str(" AUTO_INCREMENT");
+    }
+    if let Some(pos) = column.extra.to_lowercase().find("on update ") {
+        definition.push_str(&format!(" ON UPDATE {}", &column.extra[pos + 10..]));
+    }
+    if let Some(comment) = &column.comment {
+        definition.push_str(&format!(" COMMENT {}", mysql_quote(comment)));
+    }
+    definition
+}
+
+// MySQL索引定义，例如 UNIQUE KEY `uk_code` (`code`(16))
+pub fn mysql_index_definition(index: &IndexSchema) -> String {
+    let kind = match index.index_type.as_str() {
+        "FULLTEXT" => "FULLTEXT KEY",
+        "SPATIAL" => "SPATIAL KEY",
+        _ if index.unique => "UNIQUE KEY",
+        _ => "KEY",
+    };
+    let columns = index
+        .columns
+        .iter()
+        .zip(index.sub_parts.iter().chain(std::iter::repeat(&None)))
+        .map(|(column, sub_part)| match sub_part {
+            Some(len) => format!("{}({})", mysql_ident(column), len),
+            None => mysql_ident(column),
+        })
+        .collect::<Vec<_>>()
+        .join(", ");
+    format!(
+        "{} {} ({}){}",
+        kind,
+        mysql_ident(&index.name),
+        columns,
+        if index.index_type == "HASH" {
+            " USING HASH"
+        } else {
+            ""
+        }
+    )
+}
+
+// MySQL外键定义
+pub fn mysql_foreign_key_definition(fk: &ForeignKeySchema) -> String {
+    format!(
+        "CONSTRAINT {} FOREIGN KEY ({}) REFERENCES {} ({}) ON UPDATE {} ON DELETE {}",
+        mysql_ident(&fk.name),
+        mysql_idents(&fk.columns),
+        mysql_ident(&fk.ref_table),
+        mysql_idents(&fk.ref_columns),
+        fk.on_update,
+        fk.on_delete
+    )
+}
+
+// MySQL字符串字面量
+pub fn mysql_quote(value: &str) -> String {
+    format!("'{}'", value.replace('\\', "\\\\").replace('\'', "''"))
+}
+
+pub fn mysql_idents(names: &[String]) -> String {
+    names
+        .iter()
+        .map(|n| mysql_ident(n))
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
+// 字符串字面量
+pub fn pg_quote(value: &str) -> String {
+    format!("'{}'", value.replace('\'', "''"))
+}
+
+fn quote_idents(names: &[String]) -> String {
+    names
+        .iter()
+        .map(|n| quote_ident(n))
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
+// 索引、约束名在PostgreSQL中同一schema内唯一，加上表名前缀
+fn pg_object_name(table: &str, name: &str) -> String {
+    let mut name = format!("{}_{}", table, name);
+    while name.len() > PG_MAX_IDENT_LEN {
+        name.pop();
+    }
+    name
+}
+
+#[cfg(test)]
+mod test_type_map {
+    use super::*;
+
+    #[test]
+    fn test_translate_table() {
+        let mut id = ColumnSchema::new("id", "int", "int unsigned");
+        id.auto_increment = true;
+        id.nullable = false;
+        let mut status = ColumnSchema::new("status", "enum", "enum('new','it''s done')");
+        status.default = Some("new".to_string());
+        let mut deleted = ColumnSchema::new("deleted", "tinyint", "tinyint(1)");
+        deleted.default = Some("0".to_string());
+        let mut created_at = ColumnSchema::new("created_at", "datetime", "datetime");
+        created_at.default = Some("CURRENT_TIMESTAMP".to_string());
+        created_at.extra = "DEFAULT_GENERATED on update CURRENT_TIMESTAMP".to_string();
+        let table = TableSchema {
+            name: "orders".to_string(),
+            columns: vec![
+                id,
+                status,
+                deleted,
+                created_at,
+                ColumnSchema::new("area", "geometry", "geometry"),
+            ],
+            primary_key: vec!["id".to_string()],
+            indexes: vec![IndexSchema {
+                name: "ft_note".to_string(),
+                columns: vec!["note".to_string()],
+                unique: false,
+                index_type: "FULLTEXT".to_string(),
+                sub_parts: vec![None],
+            }],
+            foreign_keys: Vec::new(),
+            comment: None,
+            charset: None,
+            collation: None,
+        };
+
+        let mut report = TranslationReport::default();
+        let ddl = translate_table_to_pg(&table, &mut report);
+        assert!(
+            ddl.create_table
+                .contains("\"id\" bigint GENERATED BY DEFAULT AS IDENTITY NOT NULL")
+        );
+        assert!(ddl.create_table.contains(
+            "\"status\" varchar(9) DEFAULT 'new' CHECK (\"status\" IN ('new', 'it''s done'))"
+        ));
+        assert!(
+            ddl.create_table
+                .contains("\"deleted\" boolean DEFAULT false")
+        );
+        assert!(
+            ddl.create_table
+                .contains("\"created_at\" timestamp DEFAULT CURRENT_TIMESTAMP")
+        );
+        assert!(ddl.create_table.contains("PRIMARY KEY (\"id\")"));
+        assert!(!ddl.create_table.contains("area"));
+        assert_eq!(ddl.table.columns.len(), 4);
+        let untranslated: Vec<&str> = report
+            .untranslated
+            .iter()
+            .map(|i| i.object.as_str())
+            .collect();
+        assert_eq!(
+            untranslated,
+            vec!["orders.created_at", "orders.area", "orders.ft_note"]
+        );
+
+        let row = vec![
+            Some(b"1".to_vec()),
+            Some(b"it's done".to_vec()),
+            Some(b"1".to_vec()),
+            Some(b"0000-00-00 00:00:00".to_vec()),
+        ];
+        let (values, nulls) = pg_row_values(&ddl.table, &row);
+        assert_eq!(values, "('1', 'it''s done', true, NULL)");
+        assert_eq!(nulls, 1);
+    }
+
+    #[test]
+    fn test_mysql_row_values() {
+        let table = TableSchema {
+            name: "t".to_string(),
+            columns: vec![
+                ColumnSchema::new("id", "int", "int"),
+                ColumnSchema::new("name", "varchar", "varchar(20)"),
+                ColumnSchema::new("data", "blob", "blob"),
+                ColumnSchema::new("flag", "bit", "bit(1)"),
+            ],
+            primary_key: Vec::new(),
+            indexes: Vec::new(),
+            foreign_keys: Vec::new(),
+            comment: None,
+            charset: None,
+            collation: None,
+        };
+        let row = vec![
+            Some(b"7".to_vec()),
+            Some("it's".as_bytes().to_vec()),
+            Some(vec![0, 255]),
+            None,
+        ];
+        assert_eq!(
+            mysql_row_values(&table, &row),
+            "('7', _utf8mb4 X'69742773', X'00ff', NULL)"
+        );
+        // 数值列中的非数字按字符串字面量写入，由目标库报错
+        let row = vec![Some(b"1); DROP TABLE t; --'".to_vec()), None, None, None];
+        assert_eq!(
+            mysql_row_values(&table, &row),
+            "('1); DROP TABLE t; --''', NULL, NULL, NULL)"
+        );
+    }
+
+    #[test]
+    fn test_translate_table_to_sqlite() {
+        let mut id = ColumnSchema::new("id", "int", "int unsigned");
+        id.auto_increment = true;
+        id.nullable = false;
+        let mut status = ColumnSchema::new("status", "enum", "enum('new','done')");
+        status.default = Some("new".to_string());
+        let mut deleted = ColumnSchema::new("deleted", "tinyint", "tinyint(1)");
+        deleted.default = Some("0".to_string());
+        let mut name = ColumnSchema::new("name", "varchar", "varchar(20)");
+        name.collation = Some("utf8mb4_general_ci".to_string());
+        let table = TableSchema {
+            name: "orders".to_string(),
+            columns: vec![
+                id,
+                status,
+                deleted,
+                name,
+                ColumnSchema::new("price", "decimal", "decimal(10,2)"),
+                ColumnSchema::new("user_id", "int", "int"),
+                ColumnSchema::new("area", "geometry", "geometry"),
+            ],
+            primary_key: vec!["id".to_string()],
+            indexes: vec![
+                IndexSchema {
+                    name: "uk_name".to_string(),
+                    columns: vec!["name".to_string()],
+                    unique: true,
+                    index_type: "BTREE".to_string(),
+                    sub_parts: vec![None],
+                },
+                IndexSchema {
+                    name: "ft_name".to_string(),
+                    columns: vec!["name".to_string()],
+                    unique: false,
+                    index_type: "FULLTEXT".to_string(),
+                    sub_parts: vec![None],
+                },
+            ],
+            foreign_keys: vec![ForeignKeySchema {
+                name: "fk_user".to_string(),
+                columns: vec!["user_id".to_string()],
+                ref_table: "users".to_string(),
+                ref_columns: vec!["id".to_string()],
+                on_update: "CASCADE".to_string(),
+                on_delete: "RESTRICT".to_string(),
+            }],
+            comment: None,
+            charset: None,
+            collation: None,
+        };
+
+        let mut report = TranslationReport::default();
+        let ddl = translate_table_to_sqlite(&table, &mut report);
+        assert_eq!(
+            ddl.create_table,
+            "CREATE TABLE \"orders\" (\n  \
+             \"id\" INTEGER PRIMARY KEY AUTOINCREMENT,\n  \
+             \"status\" TEXT DEFAULT 'new' CHECK (\"status\" IN ('new', 'done')),\n  \
+             \"deleted\" INTEGER DEFAULT 0,\n  \
+             \"name\" TEXT COLLATE NOCASE,\n  \
+             \"price\" TEXT,\n  \
+             \"user_id\" INTEGER,\n  \
+             FOREIGN KEY (\"user_id\") REFERENCES \"users\" (\"id\") ON UPDATE CASCADE ON DELETE RESTRICT\n)"
+        );
+        assert_eq!(
+            ddl.post_data,
+            vec!["CREATE UNIQUE INDEX \"orders_uk_name\" ON \"orders\" (\"name\")"]
+        );
+        let untranslated: Vec<&str> = report
+            .untranslated
+            .iter()
+            .map(|i| i.object.as_str())
+            .collect();
+        assert_eq!(untranslated, vec!["orders.area", "orders.ft_name"]);
+
+        // 没有迁移的列不导入
+        assert_eq!(ddl.table.columns.len(), 6);
+        let row = vec![
+            Some(b"1".to_vec()),
+            Some(b"done".to_vec()),
+            Some(b"1".to_vec()),
+            Some("it's".as_bytes().to_vec()),
+            Some(b"9.90".to_vec()),
+            None,
+        ];
+        assert_eq!(
+            sqlite_row_values(&ddl.table, &row),
+            "('1', 'done', '1', 'it''s', '9.90', NULL)"
+        );
+    }
+
+    #[test]
+    fn test_sqlite_literal() {
+        let flag = ColumnSchema::new("flag", "bit", "bit(1)");
+        assert_eq!(sqlite_literal(&flag, Some(&[1])), "1");
+        assert_eq!(sqlite_literal(&flag, Some(&[0])), "0");
+        let bits = ColumnSchema::new("bits", "bit", "bit(16)");
+        assert_eq!(sqlite_literal(&bits, Some(&[1, 2])), "258");
+        let data = ColumnSchema::new("data", "blob", "blob");
+        assert_eq!(sqlite_literal(&data, Some(&[0, 255])), "X'00ff'");
+        assert_eq!(sqlite_literal(&data, None), "NULL");
+    }
+}
@@ -0,0 +1,222 @@
+// users_sync 任务：把源库选定的用户和授权复制到目标库，还原的视图、触发器和存储过程的 DEFINER 用户需要在目标库中存在
+// 用户的认证信息来自 SHOW CREATE USER（包括密码哈希），授权来自 SHOW GRANTS
+
+use sqlx::{MySql, Pool, Row};
+
+use crate::{
+    handle::{sync::BoxError, type_map::mysql_quote},
+    model::job::UsersSync,
+};
+
+// * 不包括的用户：root、匿名用户和服务器内部使用的用户
+const SYSTEM_USERS: [&str; 6] = [
+    "root",
+    "",
+    "mysql.sys",
+    "mysql.session",
+    "mysql.infoschema",
+    "mariadb.sys",
+];
+
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord)]
+pub struct Account {
+    pub user: String,
+    pub host: String,
+}
+
+impl Account {
+    // 'user'@'host'
+    fn quoted(&self) -> String {
+        format!("{}@{}", mysql_quote(&self.user), mysql_quote(&self.host))
+    }
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct UsersReport {
+    pub created: Vec<String>,
+    pub updated: Vec<String>,
+    pub skipped: Vec<String>,
+    pub failed: Vec<String>,
+}
+
+// 按配置选择用户：user@host 精确匹配，只写用户名时匹配该用户的所有 host，* 为所有非系统用户
+fn select_accounts(all: &[Account], patterns: &[String]) -> Vec<Account> {
+    let mut selected = all
+        .iter()
+        .filter(|account| {
+            patterns.iter().any(|pattern| {
+                let pattern = pattern.trim();
+                if pattern == "*" {
+                    return !SYSTEM_USERS.contains(&account.user.as_str());
+                }
+                match pattern.rsplit_once('@') {
+                    Some((user, host)) => {
+                        account.user == unquote(user) && account.host == unquote(host)
+                    }
+                    None => account.user == unquote(pattern),
+                }
+            })
+        })
+        .cloned()
+        .collect::<Vec<_>>();
+    selected.sort();
+    selected
+}
+
+fn unquote(name: &str) -> &str {
+    name.trim_matches(|c| c == '`' || c == '\'' || c == '"')
+}
+
+// 目标库已有用户时用 ALTER USER 修改认证信息、密码策略和锁定状态
+fn alter_statement(create: &str) -> String {
+    match create.strip_prefix("CREATE USER") {
+        Some(rest) => format!("ALTER USER{}", rest),
+        None => create.to_string(),
+    }
+}
+
+async fn list_accounts(pool: &Pool<MySql>) -> Result<Vec<Account>, sqlx::Error> {
+    let rows = sqlx::query("SELECT CAST(User AS CHAR), CAST(Host AS CHAR) FROM mysql.user")
+        .fetch_all(pool)
+        .await?;
+    rows.iter()
+        .map(|row| {
+            Ok(Account {
+                user: row.try_get(0)?,
+                host: row.try_get(1)?,
+            })
+        })
+        .collect()
+}
+
+// 源库中一个用户的建用户语句和授权语句
+async fn read_account(
+    pool: &Pool<MySql>,
+    account: &Account,
+    grants: bool,
+) -> Result<Vec<String>, sqlx::Error> {
+    // 8.0.17 起密码哈希以十六进制输出，caching_sha2_password 的哈希包含不可打印字符；旧版本没有这个变量
+    let mut conn = pool.acquire().await?;
+    let _ = sqlx::raw_sql("SET SESSION print_identified_with_as_hex = ON")
+        .execute(&mut *conn)
+        .await;
+    let row = sqlx::raw_sql(&format!("SHOW CREATE USER {}", account.quoted()))
+        .fetch_one(&mut *conn)
+        .await?;
+    let mut statements = vec![row.try_get::<String, _>(0)?];
+    if grants {
+        let rows = sqlx::raw_sql(&format!("SHOW GRANTS FOR {}", account.quoted()))
+            .fetch_all(&mut *conn)
+            .await?;
+        for row in rows {
+            statements.push(row.try_get(0)?);
+        }
+    }
+    Ok(statements)
+}
+
+// 复制 [users] accounts 选择的用户；一个用户失败不影响其它用户，返回各用户的结果
+pub async fn users_sync_job(
+    source_pool: &Pool<MySql>,
+    target_pool: &Pool<MySql>,
+    users: &UsersSync,
+) -> Result<UsersReport, BoxError> {
+    if users.accounts.is_empty() {
+        return Err("users_sync任务需要配置 [users] accounts".into());
+    }
+    let update = match users.existing.as_deref().unwrap_or("skip") {
+        "skip" => false,
+        "update" => true,
+        other => return Err(format!("不支持的 existing {}", other).into()),
+    };
+    let accounts = select_accounts(&list_accounts(source_pool).await?, &users.accounts);
+    if accounts.is_empty() {
+        return Err(format!("源库中没有匹配 {:?} 的用户", users.accounts).into());
+    }
+    let existing = list_accounts(target_pool).await?;
+    let mut report = UsersReport::default();
+    for account in accounts {
+        let name = account.quoted();
+        let exists = existing.contains(&account);
+        if exists && !update {
+            println!("[skip] 目标库已有用户 {}", name);
+            report.skipped.push(name);
+            continue;
+        }
+        let mut statements =
+            match read_account(source_pool, &account, users.grants.unwrap_or(true)).await {
+                Ok(statements) => statements,
+                Err(e) => {
+                    eprintln!("读取用户 {} 失败: {}", name, e);
+                    report.failed.push(name);
+                    continue;
+                }
+            };
+        if exists {
+            statements[0] = alter_statement(&statements[0]);
+            // 授权替换为源库的：先收回所有权限
+            statements.insert(
+                1,
+                format!("REVOKE ALL PRIVILEGES, GRANT OPTION FROM {}", name),
+            );
+        }
+        let mut result = Ok(());
+        for statement in &statements {
+            if let Err(e) = sqlx::raw_sql(statement).execute(target_pool).await {
+                result = Err(format!("{}: {}", statement, e));
+                break;
+            }
+        }
+        match result {
+            Ok(()) if exists => {
+                println!("[ok] 用户 {} 已更新，{} 条授权", name, statements.len() - 2);
+                report.updated.push(name);
+            }
+            Ok(()) => {
+                println!("[ok] 用户 {} 已创建，{} 条授权", name, statements.len() - 1);
+                report.created.push(name);
+            }
+            Err(e) => {
+                eprintln!("同步用户 {} 失败: {}", name, e);
+                report.failed.push(name);
+            }
+        }
+    }
+    Ok(report)
+}
+
+#[cfg(test)]
+mod test_users {
+    use super::*;
+
+    #[test]
+    fn test_select_accounts() {
+        let account = |user: &str, host: &str| Account {
+            user: user.to_string(),
+            host: host.to_string(),
+        };
+        let all = vec![
+            account("root", "localhost"),
+            account("mysql.sys", "localhost"),
+            account("app", "%"),
+            account("app", "10.0.%"),
+            account("report", "localhost"),
+        ];
+        assert_eq!(
+            select_accounts(&all, &["app".to_string()]),
+            vec![account("app", "%"), account("app", "10.0.%")]
+        );
+        assert_eq!(
+            select_accounts(&all, &["'report'@'localhost'".to_string()]),
+            vec![account("report", "localhost")]
+        );
+        assert_eq!(select_accounts(&all, &["*".to_string()]).len(), 3);
+        assert_eq!(account("it's", "%").quoted(), "'it''s'@'%'");
+        assert_eq!(
+            alter_statement(
+                "CREATE USER `app`@`%` IDENTIFIED WITH 'mysql_native_password' AS '*AB'"
+            ),
+            "ALTER USER `app`@`%` IDENTIFIED WITH 'mysql_native_password' AS '*AB'"
+        );
+    }
+}
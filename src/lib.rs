@@ -3,6 +3,7 @@ pub mod db;
 pub mod demo;
 pub mod handle;
 pub mod model;
+pub mod storage;
 pub mod util;
 
 #[cfg(test)]
@@ -2,10 +2,21 @@ use std::env;
 
 use datasync::{
     args::args_handle::{ArgsConfig, PrintMe},
-    db::mysql_db::{MYSQL_DB_POOLS, init_mysql_db_pool},
-    handle::help::MysqlHelp,
-    model::job::JobModel,
-    util::common as util_common,
+    handle::{
+        backup::{backup_job, pre_sync_backup, restore_job, rollback_job},
+        conflict::{Resolution, check_same_target, confirm_destructive, resolve_conflict},
+        export::export_job,
+        help::MysqlHelp,
+        import::import_job,
+        migrate::{PgSource, PgTarget, migrate_database, print_report, write_report},
+        mysql_sync::{MysqlSource, MysqlTarget},
+        pg_help::PostgresHelp,
+        schema_diff::{diff_database, print_diff, write_diff},
+        sync::{BoxError, SyncOptions, SyncSource, SyncTarget, open_source, open_target},
+        users::users_sync_job,
+    },
+    model::job::{Backup, JobModel, Source, Target},
+    util::{common as util_common, metrics, progress},
 };
 
 #[tokio::main]
@@ -26,12 +37,50 @@ async fn main() {
         .expect("Failed to load job config");
     println!("任务配置内容：{:?}", job);
 
+    // 配置了 metrics_addr 时在任务运行期间提供 /metrics
+    if let Some(addr) = &job.job.metrics_addr
+        && let Err(e) = metrics::serve(addr, progress::snapshot).await
+    {
+        println!("{}", e);
+        return;
+    }
+
+    if let Some(run_id) = &args_config.rollback {
+        rollback_job_handle(job, run_id).await;
+        return;
+    }
+
+    // 源库和目标库类型不同时为跨数据库迁移
+    let source_type = source_type(&job);
+    let target_type = target_type(&job);
+    if source_type != target_type {
+        println!("--- {} -> {} 迁移任务 ---", source_type, target_type);
+        migrate_job_handle(job).await;
+        return;
+    }
+    // 配置了 [handler] 数据转换的同步任务不能使用 mysqldump，按行同步
+    let has_handler = job.handler.as_ref().is_some_and(|h| !h.is_empty());
+    // users_sync 只复制用户和授权，不涉及表数据
+    if has_handler
+        && matches!(
+            job.job.job_type.as_str(),
+            "all_database_sync" | "database_sync" | "table_sync"
+        )
+    {
+        println!("--- {} 按行同步任务（[handler] 数据转换） ---", source_type);
+        migrate_job_handle(job).await;
+        return;
+    }
+
     // 匹配任务数据库类型
     match job.job.database_type.as_str() {
         "mysql" => {
             println!("--- mysql任务 ---");
             mysql_job_handle(job).await;
-            return;
+        }
+        "postgres" => {
+            println!("--- postgres任务 ---");
+            postgres_job_handle(job).await;
         }
         _ => {
             println!("暂不支持的数据库类型");
@@ -41,90 +90,234 @@ async fn main() {
 
 // 处理mysql任务
 async fn mysql_job_handle(job: JobModel) {
-    let job_name = job.job.name;
+    let job_name = job.job.name.clone();
     match job.job.job_type.as_str() {
-        "all_database_sync" => {
-            println!("--- 全库同步任务 ---");
+        job_type @ ("all_database_sync" | "database_sync" | "table_sync") => {
+            println!("--- {} ---", sync_job_title(job_type));
             println!("任务名称：{}", job_name);
-            let source_dns = format!(
-                "mysql://{}:{}@{}:{}/",
-                job.source.user, job.source.password, job.source.host, job.source.port
-            );
-            let source_pool_name = if job.source.db_name.is_some() {
-                format!(
-                    "source_{}_{}",
-                    job_name,
-                    job.source.db_name.as_ref().unwrap()
-                )
-            } else {
-                format!("source_{}_{}", job_name, "all")
-            };
-            let source_init_pool_result = init_mysql_db_pool(&source_dns, &source_pool_name).await;
-
-            let target_dns = format!(
-                "mysql://{}:{}@{}:{}/",
-                job.target.user, job.target.password, job.target.host, job.target.port
-            );
-            let target_pool_name = if job.target.db_name.is_some() {
-                format!(
-                    "target_{}_{}",
-                    job_name,
-                    job.target.db_name.as_ref().unwrap()
-                )
-            } else {
-                format!("target_{}_{}", job_name, "all")
-            };
-            let target_init_pool_result = init_mysql_db_pool(&target_dns, &target_pool_name).await;
-
-            if source_init_pool_result.is_ok() && target_init_pool_result.is_ok() {
-                println!(
-                    "数据库连接池创建成功: source:{}, target:{}",
-                    source_pool_name, target_pool_name
-                );
-                // 连接池创建成功，执行后续操作
-                let pool_map = MYSQL_DB_POOLS.lock().await;
-                let source_pool_result = pool_map.get(&source_pool_name);
-                if source_pool_result.is_none() {
-                    println!("源数据库连接池获取失败: {}", source_pool_name);
+            let options = match SyncOptions::from_job(&job.job) {
+                Ok(options) => options,
+                Err(e) => {
+                    println!("{}", e);
                     return;
                 }
-                let source_pool = source_pool_result.unwrap();
+            };
+            let title = sync_job_title(job_type);
+            let Some((source, source_db)) = connect_source_as::<MysqlSource>(&job, title).await
+            else {
+                return;
+            };
+            let Some((target, target_db)) = connect_target_as::<MysqlTarget>(&job, title).await
+            else {
+                return;
+            };
 
-                let target_pool_result = pool_map.get(&target_pool_name);
-                if target_pool_result.is_none() {
-                    println!("目标数据库连接池获取失败: {}", source_pool_name);
+            // 查询数据库版本信息，检查版本兼容
+            let help = match MysqlHelp::from_endpoints(&source_db, &target_db)
+                .with_options(options)
+                .with_backup(&job.backup.clone().unwrap_or_default())
+            {
+                Ok(help) => help,
+                Err(e) => {
+                    println!("{}", e);
                     return;
                 }
-                let target_pool = target_pool_result.unwrap();
-
-                // println!("===> pool: {:?}", pool);
-                // 查询数据库版本信息
-                let help = MysqlHelp::new(source_pool.clone(), target_pool.clone());
-                let versions = help.get_mysql_version().await;
-                match versions {
-                    Ok(ver) => println!("数据库版本信息: {:?}", ver),
-                    Err(e) => {
-                        println!("获取源数据库版本失败: {}", e);
-                        return;
-                    }
+            };
+            let help = match help.check_versions(target).await {
+                Ok(help) => help,
+                Err(e) => {
+                    println!("版本检查失败: {}", e);
+                    return;
                 }
+            };
+
+            let databases = match database_pairs(job_type, &source_db, source, target).await {
+                Ok(databases) => databases,
+                Err(e) => {
+                    println!("{}", e);
+                    return;
+                }
+            };
+            if let Err(e) = check_endpoints(&source_db, &target_db, &databases).await {
+                println!("{}", e);
+                return;
+            }
+            let run_id = match backup_before_sync(&job, target, "mysql", &databases).await {
+                Ok(run_id) => run_id,
+                Err(e) => {
+                    println!("{}", e);
+                    return;
+                }
+            };
 
-                // 同步所有数据库
-                println!("--- 开始同步所有数据库...");
-                let backup_result = help.sync_all_db(&job.source, &job.target).await;
-                match backup_result {
-                    Ok(_) => println!("同步所有数据库成功"),
+            // 按源库的估计大小输出进度和剩余时间
+            let tables = if job_type == "table_sync" {
+                source.table_names()
+            } else {
+                Vec::new()
+            };
+            if let Err(e) = help.plan_progress(&databases, &tables).await {
+                eprintln!("[warn] 查询表大小失败，不输出进度: {}", e);
+            }
+            let reporter = progress::spawn_reporter();
+
+            println!("--- 开始同步（{}）...", help.options.mode());
+            let sync_result = match job_type {
+                "all_database_sync" => help.sync_all_db(source, target).await.map_err(|e| e.into()),
+                "database_sync" => help.sync_db(source, target).await,
+                _ => help.sync_table(source, target).await,
+            };
+            progress::finish_reporter(reporter);
+            match sync_result {
+                Ok(_) => println!("同步成功"),
+                Err(e) => {
+                    println!("同步失败: {}", e);
+                    print_rollback_hint(run_id.as_deref());
+                }
+            }
+        }
+        "schema_diff" => {
+            println!("--- 结构对比任务 ---");
+            println!("任务名称：{}", job_name);
+            let Some((source, source_db)) =
+                connect_source_as::<MysqlSource>(&job, "结构对比任务").await
+            else {
+                return;
+            };
+            let Some((target, target_db)) =
+                connect_target_as::<MysqlTarget>(&job, "结构对比任务").await
+            else {
+                return;
+            };
+            // (源库, 目标库)，未配置 source.db_name 时对比所有库，目标库与源库同名
+            let databases = match &source.db_name {
+                Some(db_name) => vec![(
+                    db_name.clone(),
+                    target.db_name.clone().unwrap_or_else(|| db_name.clone()),
+                )],
+                None => match source_db.list_schemas().await {
+                    Ok(dbs) => dbs.into_iter().map(|db| (db.clone(), db)).collect(),
                     Err(e) => {
-                        println!("同步所有数据库成功: {}", e);
+                        println!("查询源数据库失败: {}", e);
                         return;
                     }
+                },
+            };
+            let tables = source.table_names();
+            for (db_name, target_db_name) in databases {
+                match diff_database(
+                    &source_db.pool,
+                    &target_db.pool,
+                    &db_name,
+                    &target_db_name,
+                    &tables,
+                )
+                .await
+                {
+                    Ok(diff) => {
+                        print_diff(&diff);
+                        match write_diff(&diff) {
+                            Ok((json_path, sql_path)) => {
+                                println!("对比结果：{}，变更脚本：{}", json_path, sql_path)
+                            }
+                            Err(e) => println!("写入对比结果失败: {}", e),
+                        }
+                    }
+                    Err(e) => println!("数据库 {} 结构对比失败: {}", db_name, e),
                 }
-            } else {
-                println!(
-                    "源数据库创建连接池失败: {}",
-                    source_init_pool_result.err().unwrap()
-                );
+            }
+        }
+        "backup" => {
+            println!("--- 备份任务 ---");
+            println!("任务名称：{}", job_name);
+            let Some((source, source_db)) =
+                connect_source_as::<MysqlSource>(&job, "备份任务").await
+            else {
                 return;
+            };
+            let backup = job.backup.clone().unwrap_or_default();
+            match backup_job(source_db.pool, &job_name, source, &backup).await {
+                Ok(manifest) => println!("备份成功: {}", manifest.id),
+                Err(e) => println!("备份失败: {}", e),
+            }
+        }
+        "export" => {
+            println!("--- 导出任务 ---");
+            println!("任务名称：{}", job_name);
+            let Some((source, source_db)) =
+                connect_source_as::<MysqlSource>(&job, "导出任务").await
+            else {
+                return;
+            };
+            let export = job.export.clone().unwrap_or_default();
+            match export_job(&source_db.pool, source, job.handler.as_ref(), &export).await {
+                Ok(files) => println!("导出成功: {} 个文件，目录 {}", files.len(), export.dir()),
+                Err(e) => println!("导出失败: {}", e),
+            }
+        }
+        "users_sync" => {
+            println!("--- 用户同步任务 ---");
+            println!("任务名称：{}", job_name);
+            let Some(users) = &job.users else {
+                println!("用户同步任务需要配置 [users]");
+                return;
+            };
+            let Some((_, source_db)) = connect_source_as::<MysqlSource>(&job, "用户同步任务").await
+            else {
+                return;
+            };
+            let Some((_, target_db)) = connect_target_as::<MysqlTarget>(&job, "用户同步任务").await
+            else {
+                return;
+            };
+            match users_sync_job(&source_db.pool, &target_db.pool, users).await {
+                Ok(report) if report.failed.is_empty() => println!(
+                    "用户同步成功: 创建 {}，更新 {}，跳过 {}",
+                    report.created.len(),
+                    report.updated.len(),
+                    report.skipped.len()
+                ),
+                Ok(report) => println!("用户同步失败: {:?}", report.failed),
+                Err(e) => println!("用户同步失败: {}", e),
+            }
+        }
+        "import" => {
+            println!("--- 导入任务 ---");
+            println!("任务名称：{}", job_name);
+            let Some(import) = &job.import else {
+                println!("导入任务需要配置 [import]");
+                return;
+            };
+            let Some((target, target_db)) =
+                connect_target_as::<MysqlTarget>(&job, "导入任务").await
+            else {
+                return;
+            };
+            match import_job(&target_db.pool, target, import).await {
+                Ok(report) => {
+                    println!(
+                        "导入成功: {} 个文件，{} 行，错误行 {}",
+                        report.files, report.rows, report.rejected
+                    );
+                    if let Some(quarantine) = report.quarantine {
+                        println!("[warn] 错误行已写入 {}", quarantine);
+                    }
+                }
+                Err(e) => println!("导入失败: {}", e),
+            }
+        }
+        "restore" => {
+            println!("--- 还原任务 ---");
+            println!("任务名称：{}", job_name);
+            let Some((target, target_db)) =
+                connect_target_as::<MysqlTarget>(&job, "还原任务").await
+            else {
+                return;
+            };
+            let backup = job.backup.clone().unwrap_or_default();
+            match restore_job(target_db.pool, target, &backup).await {
+                Ok(manifest) => println!("还原成功: {}", manifest.id),
+                Err(e) => println!("还原失败: {}", e),
             }
         }
         _ => {
@@ -132,3 +325,373 @@ async fn mysql_job_handle(job: JobModel) {
         }
     }
 }
+
+// 处理postgres任务
+async fn postgres_job_handle(job: JobModel) {
+    let job_name = job.job.name.clone();
+    let job_type = job.job.job_type.as_str();
+    if !matches!(
+        job_type,
+        "all_database_sync" | "database_sync" | "table_sync"
+    ) {
+        println!("暂不支持的任务类型");
+        return;
+    }
+    println!("--- {} ---", sync_job_title(job_type));
+    println!("任务名称：{}", job_name);
+    let Some((source, source_db)) =
+        connect_source_as::<PgSource>(&job, sync_job_title(job_type)).await
+    else {
+        return;
+    };
+    let Some((target, target_db)) =
+        connect_target_as::<PgTarget>(&job, sync_job_title(job_type)).await
+    else {
+        return;
+    };
+    if job.job.mode.as_deref().is_some_and(|mode| mode != "full") {
+        println!("PostgreSQL同步暂不支持 mode 配置，按 full 同步");
+    }
+    if target.conflict_policy.is_some() {
+        println!("PostgreSQL同步暂不支持 conflict_policy 配置");
+    }
+
+    // 查询数据库版本信息
+    let help = PostgresHelp::new(source_db.admin_pool, target_db.admin_pool);
+    match help.get_postgres_version().await {
+        Ok(ver) => println!("数据库版本信息: {:?}", ver),
+        Err(e) => {
+            println!("获取源数据库版本失败: {}", e);
+            return;
+        }
+    }
+
+    println!("--- 开始同步...");
+    let sync_result = match job_type {
+        "all_database_sync" => help.sync_all_db(source, target).await.map_err(|e| e.into()),
+        "database_sync" => help.sync_db(source, target).await,
+        _ => help.sync_table(source, target).await,
+    };
+    match sync_result {
+        Ok(_) => println!("同步成功"),
+        Err(e) => println!("同步失败: {}", e),
+    }
+}
+
+// 处理跨数据库迁移任务：按行读取源库数据写入目标库
+async fn migrate_job_handle(job: JobModel) {
+    let job_name = job.job.name.clone();
+    let job_type = job.job.job_type.as_str();
+    if !matches!(
+        job_type,
+        "all_database_sync" | "database_sync" | "table_sync"
+    ) {
+        println!("暂不支持的任务类型");
+        return;
+    }
+    println!("--- {} ---", sync_job_title(job_type));
+    println!("任务名称：{}", job_name);
+    let options = match SyncOptions::from_job(&job.job) {
+        Ok(options) => options,
+        Err(e) => {
+            println!("{}", e);
+            return;
+        }
+    };
+    let Some((source_cfg, source)) = connect_source(&job, "迁移任务").await else {
+        return;
+    };
+    let Some((target_cfg, target)) = connect_target(&job, "迁移任务").await else {
+        return;
+    };
+    match (source.version().await, target.version().await) {
+        (Ok(source_version), Ok(target_version)) => println!(
+            "数据库版本信息: source version: {}, target version: {}",
+            source_version, target_version
+        ),
+        (Err(e), _) | (_, Err(e)) => {
+            println!("获取数据库版本失败: {}", e);
+            return;
+        }
+    }
+
+    let databases = match database_pairs(job_type, &*source, source_cfg, target_cfg).await {
+        Ok(databases) => databases,
+        Err(e) => {
+            println!("{}", e);
+            return;
+        }
+    };
+    if let Err(e) = check_endpoints(&*source, &*target, &databases).await {
+        println!("{}", e);
+        return;
+    }
+    let run_id = match backup_before_sync(&job, target_cfg, target.engine(), &databases).await {
+        Ok(run_id) => run_id,
+        Err(e) => {
+            println!("{}", e);
+            return;
+        }
+    };
+    let tables = if job_type == "table_sync" {
+        source_cfg.table_names()
+    } else {
+        Vec::new()
+    };
+    if job_type == "table_sync" && tables.is_empty() {
+        println!("table_sync任务需要配置 source.table_name");
+        return;
+    }
+
+    for (db_name, target_db_name) in databases {
+        let target_db_name =
+            match resolve_row_target(&*target, target_cfg, &options, &target_db_name).await {
+                Ok(Some(target_db_name)) => target_db_name,
+                Ok(None) => continue,
+                Err(e) => {
+                    println!("数据库 {} 迁移失败: {}", db_name, e);
+                    print_rollback_hint(run_id.as_deref());
+                    continue;
+                }
+            };
+        match migrate_database(
+            &*source,
+            &*target,
+            job.handler.as_ref(),
+            &options,
+            &db_name,
+            &target_db_name,
+            &tables,
+        )
+        .await
+        {
+            Ok(report) => {
+                print_report(&report);
+                match write_report(&report, &db_name) {
+                    Ok(path) => println!("迁移报告：{}", path),
+                    Err(e) => println!("写入迁移报告失败: {}", e),
+                }
+            }
+            Err(e) => {
+                println!("数据库 {} 迁移失败: {}", db_name, e);
+                print_rollback_hint(run_id.as_deref());
+            }
+        }
+    }
+}
+
+// 同步前备份目标库（[target] pre_sync_backup），返回回滚使用的 run-id
+async fn backup_before_sync(
+    job: &JobModel,
+    target_cfg: &Target,
+    target_type: &str,
+    databases: &[(String, String)],
+) -> Result<Option<String>, BoxError> {
+    if !target_cfg.pre_sync_backup.unwrap_or(false) {
+        return Ok(None);
+    }
+    if target_type != "mysql" {
+        return Err(format!("{} 目标暂不支持 pre_sync_backup", target_type).into());
+    }
+    let target_db = MysqlTarget::connect(&job.job.name, target_cfg).await?;
+    let backup = job.backup.clone().unwrap_or_default();
+    let target_dbs = databases.iter().map(|(_, db)| db.clone()).collect();
+    let manifest = pre_sync_backup(
+        target_db.pool,
+        &job.job.name,
+        target_cfg,
+        &backup,
+        target_dbs,
+    )
+    .await?;
+    Ok(manifest.map(|m| m.id))
+}
+
+fn print_rollback_hint(run_id: Option<&str>) {
+    if let Some(run_id) = run_id {
+        println!("可执行 rollback {} 回滚到同步前的备份", run_id);
+    }
+}
+
+// 回滚到同步前备份：删除备份集中的目标库后还原
+async fn rollback_job_handle(job: JobModel, run_id: &str) {
+    println!("--- 回滚任务 ---");
+    println!("任务名称：{}，run-id：{}", job.job.name, run_id);
+    let Some((target, target_db)) = connect_target_as::<MysqlTarget>(&job, "回滚任务").await
+    else {
+        return;
+    };
+    let backup = Backup {
+        set_id: Some(run_id.to_string()),
+        ..job.backup.clone().unwrap_or_default()
+    };
+    match rollback_job(target_db.pool, target, &backup).await {
+        Ok(manifest) => println!("回滚成功: {}", manifest.id),
+        Err(e) => println!("回滚失败: {}", e),
+    }
+}
+
+// 源库/目标库的数据库类型，未单独配置时为 [job] database_type
+fn source_type(job: &JobModel) -> String {
+    job.source
+        .as_ref()
+        .and_then(|s| s.database_type.clone())
+        .unwrap_or_else(|| job.job.database_type.clone())
+}
+
+fn target_type(job: &JobModel) -> String {
+    job.target
+        .as_ref()
+        .and_then(|t| t.database_type.clone())
+        .unwrap_or_else(|| job.job.database_type.clone())
+}
+
+// 按数据库类型连接任务的源库，缺少 [source] 或连接失败时输出错误
+async fn connect_source<'a>(
+    job: &'a JobModel,
+    title: &str,
+) -> Option<(&'a Source, Box<dyn SyncSource>)> {
+    let Some(source_cfg) = &job.source else {
+        println!("{}需要配置 [source]", title);
+        return None;
+    };
+    match open_source(&job.job.name, &source_type(job), source_cfg).await {
+        Ok(source) => Some((source_cfg, source)),
+        Err(e) => {
+            println!("源数据库创建连接失败: {}", e);
+            None
+        }
+    }
+}
+
+// 按数据库类型连接任务的目标库，缺少 [target] 或连接失败时输出错误
+async fn connect_target<'a>(
+    job: &'a JobModel,
+    title: &str,
+) -> Option<(&'a Target, Box<dyn SyncTarget>)> {
+    let Some(target_cfg) = &job.target else {
+        println!("{}需要配置 [target]", title);
+        return None;
+    };
+    match open_target(&job.job.name, &target_type(job), target_cfg).await {
+        Ok(target) => Some((target_cfg, target)),
+        Err(e) => {
+            println!("目标数据库创建连接失败: {}", e);
+            None
+        }
+    }
+}
+
+// 只支持一种数据库的任务（mysqldump、pg_dump 等）：连接源库并取得具体类型
+async fn connect_source_as<'a, T: Clone + 'static>(
+    job: &'a JobModel,
+    title: &str,
+) -> Option<(&'a Source, T)> {
+    let (source_cfg, source) = connect_source(job, title).await?;
+    match source.as_any().downcast_ref::<T>() {
+        Some(concrete) => Some((source_cfg, concrete.clone())),
+        None => {
+            println!("{}暂不支持 {} 源库", title, source.engine());
+            None
+        }
+    }
+}
+
+async fn connect_target_as<'a, T: Clone + 'static>(
+    job: &'a JobModel,
+    title: &str,
+) -> Option<(&'a Target, T)> {
+    let (target_cfg, target) = connect_target(job, title).await?;
+    match target.as_any().downcast_ref::<T>() {
+        Some(concrete) => Some((target_cfg, concrete.clone())),
+        None => {
+            println!("{}暂不支持 {} 目标库", title, target.engine());
+            None
+        }
+    }
+}
+
+// (源库, 目标库)，整库同步时目标库与源库同名
+async fn database_pairs(
+    job_type: &str,
+    source: &dyn SyncSource,
+    source_cfg: &Source,
+    target_cfg: &Target,
+) -> Result<Vec<(String, String)>, String> {
+    if job_type == "all_database_sync" {
+        let dbs = source
+            .list_schemas()
+            .await
+            .map_err(|e| format!("查询源数据库失败: {}", e))?;
+        return Ok(dbs.into_iter().map(|db| (db.clone(), db)).collect());
+    }
+    let Some(db_name) = &source_cfg.db_name else {
+        return Err(format!(
+            "{}需要配置 source.db_name",
+            sync_job_title(job_type)
+        ));
+    };
+    let target_db_name = target_cfg
+        .db_name
+        .clone()
+        .unwrap_or_else(|| db_name.clone());
+    Ok(vec![(db_name.clone(), target_db_name)])
+}
+
+// 源库和目标库是同一个服务器上的同一个库时拒绝执行
+async fn check_endpoints(
+    source: &dyn SyncSource,
+    target: &dyn SyncTarget,
+    databases: &[(String, String)],
+) -> Result<(), BoxError> {
+    let source_id = source.server_id().await?;
+    let target_id = target.server_id().await?;
+    Ok(check_same_target(&source_id, &target_id, databases)?)
+}
+
+// 按行同步时按 conflict_policy 处理目标库，返回实际写入的库，None 为跳过
+async fn resolve_row_target(
+    target: &dyn SyncTarget,
+    target_cfg: &Target,
+    options: &SyncOptions,
+    target_db_name: &str,
+) -> Result<Option<String>, BoxError> {
+    let existing = if target
+        .list_schemas()
+        .await?
+        .iter()
+        .any(|s| s == target_db_name)
+    {
+        target.list_tables(target_db_name).await?
+    } else {
+        Vec::new()
+    };
+    if options.data_only() {
+        if !existing.is_empty() {
+            confirm_destructive(
+                target_cfg,
+                target_db_name,
+                &format!("data_only 模式将清空目标库 {} 中同步的表", target_db_name),
+            )?;
+        }
+        return Ok(Some(target_db_name.to_string()));
+    }
+    match resolve_conflict(target_cfg, target_db_name, &existing)? {
+        Resolution::Skip => Ok(None),
+        Resolution::Restore(name) => Ok(Some(name)),
+        Resolution::Recreate(name) => {
+            target.drop_schema(&name).await?;
+            println!("目标库 {} 已删除", name);
+            Ok(Some(name))
+        }
+        Resolution::Merge(_) => Err("按行同步暂不支持 conflict_policy = merge".into()),
+    }
+}
+
+fn sync_job_title(job_type: &str) -> &'static str {
+    match job_type {
+        "all_database_sync" => "全库同步任务",
+        "database_sync" => "单库同步任务",
+        _ => "数据表同步任务",
+    }
+}
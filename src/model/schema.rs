@@ -0,0 +1,154 @@
+// 表结构（与数据库无关），用于跨数据库迁移时的结构转换
+
+use serde::Serialize;
+
+#[derive(Debug, Clone, Serialize)]
+pub struct TableSchema {
+    pub name: String,
+    pub columns: Vec<ColumnSchema>,
+    pub primary_key: Vec<String>,
+    pub indexes: Vec<IndexSchema>,
+    pub foreign_keys: Vec<ForeignKeySchema>,
+    pub comment: Option<String>,
+    pub charset: Option<String>,
+    pub collation: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct ColumnSchema {
+    pub name: String,
+    pub data_type: String,   // 例如 int、varchar（小写）
+    pub column_type: String, // 完整类型，例如 tinyint(1) unsigned、enum('a','b')
+    pub nullable: bool,
+    pub default: Option<String>,
+    pub auto_increment: bool,
+    pub extra: String, // 例如 on update CURRENT_TIMESTAMP、VIRTUAL GENERATED
+    pub charset: Option<String>,
+    pub collation: Option<String>,
+    pub comment: Option<String>,
+    pub char_length: Option<u64>,
+    pub numeric_precision: Option<u64>,
+    pub numeric_scale: Option<u64>,
+    pub datetime_precision: Option<u64>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct IndexSchema {
+    pub name: String,
+    pub columns: Vec<String>,
+    pub unique: bool,
+    pub index_type: String,          // BTREE、HASH、FULLTEXT、SPATIAL
+    pub sub_parts: Vec<Option<u64>>, // 与 columns 对应的前缀长度
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct ForeignKeySchema {
+    pub name: String,
+    pub columns: Vec<String>,
+    pub ref_table: String,
+    pub ref_columns: Vec<String>,
+    pub on_update: String,
+    pub on_delete: String,
+}
+
+// 列值的类别，决定不同数据库中字面量的写法
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ValueKind {
+    Integer,
+    Float,
+    Decimal,
+    Boolean,
+    Bit,
+    Text,
+    Binary,
+    Date,
+    DateTime,
+    Time,
+    Json,
+}
+
+impl ColumnSchema {
+    // 可为空、没有默认值等附加属性的列，其余属性按需再修改
+    pub fn new(name: &str, data_type: &str, column_type: &str) -> Self {
+        ColumnSchema {
+            name: name.to_string(),
+            data_type: data_type.to_string(),
+            column_type: column_type.to_string(),
+            nullable: true,
+            default: None,
+            auto_increment: false,
+            extra: String::new(),
+            charset: None,
+            collation: None,
+            comment: None,
+            char_length: None,
+            numeric_precision: None,
+            numeric_scale: None,
+            datetime_precision: None,
+        }
+    }
+
+    pub fn unsigned(&self) -> bool {
+        self.column_type.contains("unsigned")
+    }
+
+    // tinyint(1) 通常表示布尔值
+    pub fn is_bool(&self) -> bool {
+        (self.data_type == "tinyint" && self.column_type.starts_with("tinyint(1)"))
+            || (self.data_type == "bit" && self.numeric_precision == Some(1))
+    }
+
+    pub fn kind(&self) -> ValueKind {
+        if self.is_bool() {
+            return ValueKind::Boolean;
+        }
+        match self.data_type.as_str() {
+            "tinyint" | "smallint" | "mediumint" | "int" | "integer" | "bigint" | "year" => {
+                ValueKind::Integer
+            }
+            "float" | "double" | "real" => ValueKind::Float,
+            "decimal" | "numeric" => ValueKind::Decimal,
+            "bit" => ValueKind::Bit,
+            "binary" | "varbinary" | "tinyblob" | "blob" | "mediumblob" | "longblob" => {
+                ValueKind::Binary
+            }
+            "date" => ValueKind::Date,
+            "datetime" | "timestamp" => ValueKind::DateTime,
+            "time" => ValueKind::Time,
+            "json" => ValueKind::Json,
+            _ => ValueKind::Text,
+        }
+    }
+
+    // enum/set 的可选值
+    pub fn enum_values(&self) -> Vec<String> {
+        let Some(start) = self.column_type.find('(') else {
+            return Vec::new();
+        };
+        let inner = self.column_type[start + 1..].trim_end_matches(')');
+        let mut values = Vec::new();
+        let mut current = String::new();
+        let mut in_quote = false;
+        let mut chars = inner.chars().peekable();
+        while let Some(c) = chars.next() {
+            match c {
+                '\'' if in_quote && chars.peek() == Some(&'\'') => {
+                    current.push('\'');
+                    chars.next();
+                }
+                '\'' => {
+                    if in_quote {
+                        values.push(std::mem::take(&mut current));
+                    }
+                    in_quote = !in_quote;
+                }
+                _ if in_quote => current.push(c),
+                _ => {}
+            }
+        }
+        values
+    }
+}
+
+// 一行数据，按列顺序保存文本协议返回的原始值，None 表示 NULL
+pub type RowValues = Vec<Option<Vec<u8>>>;
@@ -0,0 +1,125 @@
+// 备份存储
+// 备份文件和备份集清单都通过 BackupStorage 读写，路径使用 / 分隔的相对路径（相对于存储的根目录）
+
+use std::{
+    io::{self, Read, Write},
+    sync::Arc,
+};
+
+use crate::{
+    model::job::Storage,
+    storage::{local::LocalStorage, s3::S3Storage, sftp::SftpStorage},
+};
+
+pub trait BackupStorage: Send + Sync + std::fmt::Debug {
+    // 用于日志输出的完整位置
+    fn location(&self, path: &str) -> String;
+
+    // 创建文件并流式写入，调用 finish 之后文件才算写入完成
+    fn create(&self, path: &str) -> io::Result<Box<dyn StorageWriter>>;
+
+    // 流式读取文件
+    fn open(&self, path: &str) -> io::Result<Box<dyn Read + Send>>;
+
+    // 判断文件是否存在
+    fn exists(&self, path: &str) -> io::Result<bool>;
+
+    // 列出目录下的一级子目录名
+    fn list_dirs(&self, dir: &str) -> io::Result<Vec<String>>;
+
+    fn write_all(&self, path: &str, data: &[u8]) -> io::Result<()> {
+        let mut writer = self.create(path)?;
+        writer.write_all(data)?;
+        writer.finish()
+    }
+
+    fn read_to_string(&self, path: &str) -> io::Result<String> {
+        let mut content = String::new();
+        self.open(path)?.read_to_string(&mut content)?;
+        Ok(content)
+    }
+}
+
+pub trait StorageWriter: Write + Send {
+    // 提交写入（本地文件重命名、S3完成分片上传等）
+    fn finish(self: Box<Self>) -> io::Result<()>;
+}
+
+// 根据任务配置创建存储，未配置时使用本地目录
+pub fn open_storage(storage: Option<&Storage>) -> io::Result<Arc<dyn BackupStorage>> {
+    let storage_type = storage
+        .and_then(|s| s.storage_type.as_deref())
+        .unwrap_or("local");
+    match (storage_type, storage) {
+        ("local", _) => Ok(Arc::new(LocalStorage::new("."))),
+        ("s3", Some(storage)) => Ok(Arc::new(S3Storage::from_config(storage)?)),
+        ("sftp", Some(storage)) => Ok(Arc::new(SftpStorage::from_config(storage)?)),
+        _ => Err(io::Error::other(format!(
+            "暂不支持的存储类型: {}",
+            storage_type
+        ))),
+    }
+}
+
+// 拼接存储路径
+pub fn join_path(dir: &str, name: &str) -> String {
+    let dir = dir.trim_end_matches('/');
+    if dir.is_empty() || dir == "." {
+        name.to_string()
+    } else {
+        format!("{}/{}", dir, name)
+    }
+}
+
+// 缺少必填配置项时的错误
+pub(crate) fn required<'a>(value: &'a Option<String>, name: &str) -> io::Result<&'a str> {
+    value
+        .as_deref()
+        .ok_or_else(|| io::Error::other(format!("存储配置缺少 {}", name)))
+}
+
+// 各存储共用的读写测试
+#[cfg(test)]
+pub(crate) mod test_storage {
+    use super::*;
+
+    // 写入、读取、判断存在、列出目录，未 finish 的写入不留下文件
+    pub(crate) fn check_round_trip(storage: &dyn BackupStorage) {
+        let data = (0..100_000u32)
+            .flat_map(|i| i.to_le_bytes())
+            .collect::<Vec<_>>();
+        let mut writer = storage.create("sql/set_1/a.sql").unwrap();
+        for chunk in data.chunks(1000) {
+            writer.write_all(chunk).unwrap();
+        }
+        writer.finish().unwrap();
+        storage
+            .write_all("sql/set_2/manifest.json", b"{\"id\":\"set_2\"}")
+            .unwrap();
+
+        let mut read = Vec::new();
+        storage
+            .open("sql/set_1/a.sql")
+            .unwrap()
+            .read_to_end(&mut read)
+            .unwrap();
+        assert_eq!(read, data);
+        assert_eq!(
+            storage.read_to_string("sql/set_2/manifest.json").unwrap(),
+            "{\"id\":\"set_2\"}"
+        );
+        assert!(storage.exists("sql/set_1/a.sql").unwrap());
+        assert!(!storage.exists("sql/set_1/b.sql").unwrap());
+        assert!(storage.open("sql/set_1/b.sql").is_err());
+
+        let mut dirs = storage.list_dirs("sql").unwrap();
+        dirs.sort();
+        assert_eq!(dirs, vec!["set_1", "set_2"]);
+        assert!(storage.list_dirs("missing").unwrap().is_empty());
+
+        let mut writer = storage.create("sql/set_3/a.sql").unwrap();
+        writer.write_all(&data).unwrap();
+        drop(writer);
+        assert!(!storage.exists("sql/set_3/a.sql").unwrap());
+    }
+}
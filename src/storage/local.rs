@@ -0,0 +1,123 @@
+// 本地文件系统存储
+
+use std::{
+    fs::{self, File},
+    io::{self, BufWriter, Read, Write},
+    path::{Path, PathBuf},
+};
+
+use crate::storage::backend::{BackupStorage, StorageWriter};
+
+#[derive(Debug, Clone)]
+pub struct LocalStorage {
+    pub root: PathBuf,
+}
+
+impl LocalStorage {
+    pub fn new<P: AsRef<Path>>(root: P) -> Self {
+        LocalStorage {
+            root: root.as_ref().to_path_buf(),
+        }
+    }
+
+    fn full_path(&self, path: &str) -> PathBuf {
+        self.root.join(path)
+    }
+}
+
+impl BackupStorage for LocalStorage {
+    fn location(&self, path: &str) -> String {
+        self.full_path(path).display().to_string()
+    }
+
+    fn create(&self, path: &str) -> io::Result<Box<dyn StorageWriter>> {
+        let path = self.full_path(path);
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        // 先写入临时文件，finish 时再重命名，避免留下不完整的文件
+        let mut tmp_path = path.clone().into_os_string();
+        tmp_path.push(".part");
+        let tmp_path = PathBuf::from(tmp_path);
+        let file = File::create(&tmp_path)?;
+        Ok(Box::new(LocalWriter {
+            file: BufWriter::new(file),
+            tmp_path,
+            path,
+            finished: false,
+        }))
+    }
+
+    fn open(&self, path: &str) -> io::Result<Box<dyn Read + Send>> {
+        Ok(Box::new(File::open(self.full_path(path))?))
+    }
+
+    fn exists(&self, path: &str) -> io::Result<bool> {
+        Ok(self.full_path(path).is_file())
+    }
+
+    fn list_dirs(&self, dir: &str) -> io::Result<Vec<String>> {
+        let dir = self.full_path(dir);
+        let mut dirs = Vec::new();
+        if !dir.is_dir() {
+            return Ok(dirs);
+        }
+        for entry in fs::read_dir(dir)? {
+            let entry = entry?;
+            if entry.file_type()?.is_dir() {
+                dirs.push(entry.file_name().to_string_lossy().to_string());
+            }
+        }
+        Ok(dirs)
+    }
+}
+
+struct LocalWriter {
+    file: BufWriter<File>,
+    tmp_path: PathBuf,
+    path: PathBuf,
+    finished: bool,
+}
+
+impl Write for LocalWriter {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.file.write(buf)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.file.flush()
+    }
+}
+
+impl StorageWriter for LocalWriter {
+    fn finish(mut self: Box<Self>) -> io::Result<()> {
+        self.file.flush()?;
+        self.file.get_ref().sync_all()?;
+        fs::rename(&self.tmp_path, &self.path)?;
+        self.finished = true;
+        Ok(())
+    }
+}
+
+impl Drop for LocalWriter {
+    // 未调用 finish（写入出错）时删除临时文件
+    fn drop(&mut self) {
+        if !self.finished {
+            let _ = fs::remove_file(&self.tmp_path);
+        }
+    }
+}
+
+#[cfg(test)]
+mod test_local {
+    use super::*;
+    use crate::storage::backend::test_storage::check_round_trip;
+
+    #[test]
+    fn test_round_trip() {
+        let dir = std::env::temp_dir().join("datasync_test_local_storage");
+        let _ = fs::remove_dir_all(&dir);
+        check_round_trip(&LocalStorage::new(&dir));
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
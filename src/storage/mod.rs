@@ -0,0 +1,4 @@
+pub mod backend;
+pub mod local;
+pub mod s3;
+pub mod sftp;
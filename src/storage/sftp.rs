@@ -0,0 +1,435 @@
+// SFTP存储
+// 每次读写单独建立ssh会话，多个数据库并发备份时互不影响
+
+use std::{
+    io::{self, Read, Write},
+    net::TcpStream,
+    path::{Path, PathBuf},
+    sync::Arc,
+};
+
+use base64::{Engine, engine::general_purpose::STANDARD_NO_PAD};
+use ssh2::{CheckResult, HashType, KnownHostFileKind, RenameFlags, Session, Sftp};
+
+use crate::{
+    model::job::Storage,
+    storage::backend::{BackupStorage, StorageWriter, required},
+};
+
+// 建立SFTP会话，测试中可以用本地目录代替SFTP服务器
+pub trait SftpConnect: Send + Sync + std::fmt::Debug {
+    // 用于日志输出，例如 user@host:22
+    fn address(&self) -> String;
+
+    fn connect(&self) -> io::Result<Box<dyn SftpSession>>;
+}
+
+// SFTP会话中用到的文件操作
+pub trait SftpSession: Send {
+    // 路径是否为目录，不存在时为 None
+    fn is_dir(&self, path: &Path) -> Option<bool>;
+
+    fn mkdir(&self, path: &Path) -> io::Result<()>;
+
+    fn create(&self, path: &Path) -> io::Result<Box<dyn SftpFile>>;
+
+    fn open(&self, path: &Path) -> io::Result<Box<dyn Read + Send>>;
+
+    // 目录下的 (路径, 是否为目录)
+    fn readdir(&self, path: &Path) -> io::Result<Vec<(PathBuf, bool)>>;
+
+    // 目标文件已存在时覆盖
+    fn rename(&self, from: &Path, to: &Path) -> io::Result<()>;
+
+    fn unlink(&self, path: &Path) -> io::Result<()>;
+}
+
+pub trait SftpFile: Write + Send {
+    fn close(&mut self) -> io::Result<()>;
+}
+
+#[derive(Debug, Clone)]
+pub struct SftpStorage {
+    pub root: String, // 远程根目录
+    connector: Arc<dyn SftpConnect>,
+}
+
+impl SftpStorage {
+    pub fn new<C: SftpConnect + 'static>(connector: C, root: &str) -> Self {
+        SftpStorage {
+            root: root.to_string(),
+            connector: Arc::new(connector),
+        }
+    }
+
+    pub fn from_config(storage: &Storage) -> io::Result<Self> {
+        let connector = SshConnector {
+            host: required(&storage.host, "host")?.to_string(),
+            port: storage.port.clone().unwrap_or_else(|| "22".to_string()),
+            user: required(&storage.user, "user")?.to_string(),
+            password: storage.password.clone(),
+            private_key: storage.private_key.clone(),
+            known_hosts: storage.known_hosts.clone(),
+            host_key_fingerprint: storage.host_key_fingerprint.clone(),
+        };
+        Ok(SftpStorage::new(
+            connector,
+            storage.prefix.as_deref().unwrap_or("."),
+        ))
+    }
+
+    fn full_path(&self, path: &str) -> PathBuf {
+        Path::new(&self.root).join(path)
+    }
+
+    // 逐级创建远程目录
+    fn mkdir_all(session: &dyn SftpSession, dir: &Path) -> io::Result<()> {
+        let mut current = PathBuf::new();
+        for component in dir.components() {
+            current.push(component);
+            if session.is_dir(&current).is_none() {
+                session.mkdir(&current)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+impl BackupStorage for SftpStorage {
+    fn location(&self, path: &str) -> String {
+        format!(
+            "sftp://{}/{}",
+            self.connector.address(),
+            self.full_path(path).display()
+        )
+    }
+
+    fn create(&self, path: &str) -> io::Result<Box<dyn StorageWriter>> {
+        let session = self.connector.connect()?;
+        let path = self.full_path(path);
+        if let Some(parent) = path.parent() {
+            Self::mkdir_all(&*session, parent)?;
+        }
+        let mut tmp_path = path.clone().into_os_string();
+        tmp_path.push(".part");
+        let tmp_path = PathBuf::from(tmp_path);
+        let file = session.create(&tmp_path)?;
+        Ok(Box::new(SftpWriter {
+            file: Some(file),
+            session,
+            tmp_path,
+            path,
+        }))
+    }
+
+    fn open(&self, path: &str) -> io::Result<Box<dyn Read + Send>> {
+        self.connector.connect()?.open(&self.full_path(path))
+    }
+
+    fn exists(&self, path: &str) -> io::Result<bool> {
+        let session = self.connector.connect()?;
+        Ok(session.is_dir(&self.full_path(path)) == Some(false))
+    }
+
+    fn list_dirs(&self, dir: &str) -> io::Result<Vec<String>> {
+        let session = self.connector.connect()?;
+        let dir = self.full_path(dir);
+        if session.is_dir(&dir).is_none() {
+            return Ok(Vec::new());
+        }
+        Ok(session
+            .readdir(&dir)?
+            .into_iter()
+            .filter(|(_, is_dir)| *is_dir)
+            .filter_map(|(path, _)| path.file_name().map(|n| n.to_string_lossy().to_string()))
+            .collect())
+    }
+}
+
+struct SftpWriter {
+    file: Option<Box<dyn SftpFile>>,
+    session: Box<dyn SftpSession>,
+    tmp_path: PathBuf,
+    path: PathBuf,
+}
+
+impl Write for SftpWriter {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.file.as_mut().unwrap().write(buf)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.file.as_mut().unwrap().flush()
+    }
+}
+
+impl StorageWriter for SftpWriter {
+    fn finish(mut self: Box<Self>) -> io::Result<()> {
+        let mut file = self.file.take().unwrap();
+        file.flush()?;
+        file.close()?;
+        self.session.rename(&self.tmp_path, &self.path)
+    }
+}
+
+impl Drop for SftpWriter {
+    // 未调用 finish（写入出错）时删除远程临时文件
+    fn drop(&mut self) {
+        if self.file.take().is_some() {
+            let _ = self.session.unlink(&self.tmp_path);
+        }
+    }
+}
+
+// 通过ssh连接SFTP服务器，使用密码、私钥或 ssh-agent 认证
+// 认证前校验主机密钥：配置了 host_key_fingerprint 时比较指纹，否则查找 known_hosts，不匹配或未知主机时拒绝连接
+#[derive(Debug, Clone)]
+pub struct SshConnector {
+    pub host: String,
+    pub port: String,
+    pub user: String,
+    pub password: Option<String>,
+    pub private_key: Option<String>,          // 私钥文件路径
+    pub known_hosts: Option<String>,          // 默认 ~/.ssh/known_hosts
+    pub host_key_fingerprint: Option<String>, // 例如 SHA256:nThbg6kXUpJWGl7E1IGOCspRomTxdCARLviKw6E5SY8
+}
+
+impl SshConnector {
+    fn verify_host_key(&self, session: &Session) -> io::Result<()> {
+        let rejected = |reason: String| {
+            io::Error::new(
+                io::ErrorKind::PermissionDenied,
+                format!("SFTP服务器 {}:{} {}", self.host, self.port, reason),
+            )
+        };
+        if let Some(expected) = &self.host_key_fingerprint {
+            let hash = session
+                .host_key_hash(HashType::Sha256)
+                .ok_or_else(|| rejected("没有返回主机密钥".to_string()))?;
+            if !fingerprint_matches(expected, hash) {
+                return Err(rejected(format!(
+                    "的主机密钥指纹 {} 与配置的 host_key_fingerprint 不一致",
+                    fingerprint(hash)
+                )));
+            }
+            return Ok(());
+        }
+        let path = match &self.known_hosts {
+            Some(path) => PathBuf::from(path),
+            None => std::env::var_os("HOME")
+                .map(|home| Path::new(&home).join(".ssh/known_hosts"))
+                .ok_or_else(|| {
+                    rejected("无法确定 known_hosts 路径，请配置 known_hosts".to_string())
+                })?,
+        };
+        let (key, _) = session
+            .host_key()
+            .ok_or_else(|| rejected("没有返回主机密钥".to_string()))?;
+        let mut known_hosts = session.known_hosts()?;
+        known_hosts
+            .read_file(&path, KnownHostFileKind::OpenSSH)
+            .map_err(|e| rejected(format!("无法读取 known_hosts {}：{}", path.display(), e)))?;
+        let port = self.port.parse().unwrap_or(22);
+        match known_hosts.check_port(&self.host, port, key) {
+            CheckResult::Match => Ok(()),
+            CheckResult::Mismatch => Err(rejected(format!(
+                "的主机密钥与 {} 中记录的不一致，可能存在中间人攻击",
+                path.display()
+            ))),
+            CheckResult::NotFound => Err(rejected(format!(
+                "不在 {} 中，请先用 ssh-keyscan 添加或配置 host_key_fingerprint",
+                path.display()
+            ))),
+            CheckResult::Failure => Err(rejected("校验主机密钥失败".to_string())),
+        }
+    }
+}
+
+// OpenSSH 格式的 SHA256 指纹：SHA256: 加不带填充的 base64
+fn fingerprint(hash: &[u8]) -> String {
+    format!("SHA256:{}", STANDARD_NO_PAD.encode(hash))
+}
+
+// 配置的指纹可以省略 SHA256: 前缀和 base64 填充
+fn fingerprint_matches(expected: &str, hash: &[u8]) -> bool {
+    let expected = expected.trim();
+    let expected = expected.strip_prefix("SHA256:").unwrap_or(expected);
+    expected.trim_end_matches('=') == STANDARD_NO_PAD.encode(hash)
+}
+
+impl SftpConnect for SshConnector {
+    fn address(&self) -> String {
+        format!("{}@{}:{}", self.user, self.host, self.port)
+    }
+
+    fn connect(&self) -> io::Result<Box<dyn SftpSession>> {
+        let tcp = TcpStream::connect(format!("{}:{}", self.host, self.port))?;
+        let mut session = Session::new()?;
+        session.set_tcp_stream(tcp);
+        session.handshake()?;
+        // 先校验主机密钥，再发送密码等认证信息
+        self.verify_host_key(&session)?;
+        match (&self.private_key, &self.password) {
+            (Some(private_key), passphrase) => session.userauth_pubkey_file(
+                &self.user,
+                None,
+                Path::new(private_key),
+                passphrase.as_deref(),
+            )?,
+            (None, Some(password)) => session.userauth_password(&self.user, password)?,
+            (None, None) => session.userauth_agent(&self.user)?,
+        }
+        Ok(Box::new(SshSession(session.sftp()?)))
+    }
+}
+
+struct SshSession(Sftp);
+
+impl SftpSession for SshSession {
+    fn is_dir(&self, path: &Path) -> Option<bool> {
+        self.0.stat(path).ok().map(|stat| stat.is_dir())
+    }
+
+    fn mkdir(&self, path: &Path) -> io::Result<()> {
+        Ok(self.0.mkdir(path, 0o755)?)
+    }
+
+    fn create(&self, path: &Path) -> io::Result<Box<dyn SftpFile>> {
+        Ok(Box::new(self.0.create(path)?))
+    }
+
+    fn open(&self, path: &Path) -> io::Result<Box<dyn Read + Send>> {
+        Ok(Box::new(self.0.open(path)?))
+    }
+
+    fn readdir(&self, path: &Path) -> io::Result<Vec<(PathBuf, bool)>> {
+        Ok(self
+            .0
+            .readdir(path)?
+            .into_iter()
+            .map(|(path, stat)| (path, stat.is_dir()))
+            .collect())
+    }
+
+    fn rename(&self, from: &Path, to: &Path) -> io::Result<()> {
+        // 部分服务器（SFTP v3）不支持覆盖，先删除已存在的目标文件
+        let _ = self.0.unlink(to);
+        Ok(self.0.rename(
+            from,
+            to,
+            Some(RenameFlags::OVERWRITE | RenameFlags::ATOMIC | RenameFlags::NATIVE),
+        )?)
+    }
+
+    fn unlink(&self, path: &Path) -> io::Result<()> {
+        Ok(self.0.unlink(path)?)
+    }
+}
+
+impl SftpFile for ssh2::File {
+    fn close(&mut self) -> io::Result<()> {
+        Ok(ssh2::File::close(self)?)
+    }
+}
+
+#[cfg(test)]
+mod test_sftp {
+    use std::fs::{self, File};
+
+    use super::*;
+    use crate::storage::backend::test_storage::check_round_trip;
+
+    // 用本地目录代替SFTP服务器，路径按服务器上的相对路径映射到目录中
+    #[derive(Debug)]
+    struct LocalSftp(PathBuf);
+
+    impl SftpConnect for LocalSftp {
+        fn address(&self) -> String {
+            "test@localhost:22".to_string()
+        }
+
+        fn connect(&self) -> io::Result<Box<dyn SftpSession>> {
+            Ok(Box::new(LocalSftp(self.0.clone())))
+        }
+    }
+
+    impl SftpSession for LocalSftp {
+        fn is_dir(&self, path: &Path) -> Option<bool> {
+            fs::metadata(self.0.join(path)).ok().map(|m| m.is_dir())
+        }
+
+        fn mkdir(&self, path: &Path) -> io::Result<()> {
+            fs::create_dir(self.0.join(path))
+        }
+
+        fn create(&self, path: &Path) -> io::Result<Box<dyn SftpFile>> {
+            Ok(Box::new(File::create(self.0.join(path))?))
+        }
+
+        fn open(&self, path: &Path) -> io::Result<Box<dyn Read + Send>> {
+            Ok(Box::new(File::open(self.0.join(path))?))
+        }
+
+        fn readdir(&self, path: &Path) -> io::Result<Vec<(PathBuf, bool)>> {
+            fs::read_dir(self.0.join(path))?
+                .map(|entry| {
+                    let entry = entry?;
+                    Ok((path.join(entry.file_name()), entry.file_type()?.is_dir()))
+                })
+                .collect()
+        }
+
+        fn rename(&self, from: &Path, to: &Path) -> io::Result<()> {
+            fs::rename(self.0.join(from), self.0.join(to))
+        }
+
+        fn unlink(&self, path: &Path) -> io::Result<()> {
+            fs::remove_file(self.0.join(path))
+        }
+    }
+
+    impl SftpFile for File {
+        fn close(&mut self) -> io::Result<()> {
+            self.sync_all()
+        }
+    }
+
+    #[test]
+    fn test_round_trip() {
+        let dir = std::env::temp_dir().join("datasync_test_sftp");
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        let storage = SftpStorage::new(LocalSftp(dir.clone()), "data/datasync");
+        check_round_trip(&storage);
+        assert_eq!(
+            storage.location("sql/a.sql"),
+            "sftp://test@localhost:22/data/datasync/sql/a.sql"
+        );
+        // 临时文件在 finish 后重命名，没有留下 .part 文件
+        assert!(!dir.join("data/datasync/sql/set_1/a.sql.part").exists());
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_fingerprint() {
+        let hash = (0..32).collect::<Vec<u8>>();
+        assert_eq!(
+            fingerprint(&hash),
+            "SHA256:AAECAwQFBgcICQoLDA0ODxAREhMUFRYXGBkaGxwdHh8"
+        );
+        // 与 ssh-keygen -lf 的输出相同，也可以省略前缀或带填充
+        assert!(fingerprint_matches(
+            "SHA256:AAECAwQFBgcICQoLDA0ODxAREhMUFRYXGBkaGxwdHh8",
+            &hash
+        ));
+        assert!(fingerprint_matches(
+            " AAECAwQFBgcICQoLDA0ODxAREhMUFRYXGBkaGxwdHh8= ",
+            &hash
+        ));
+        assert!(!fingerprint_matches(
+            "SHA256:AAECAwQFBgcICQoLDA0ODxAREhMUFRYXGBkaGxwdHh4",
+            &hash
+        ));
+        assert!(!fingerprint_matches("", &hash));
+    }
+}
@@ -0,0 +1,340 @@
+// 备份文件完整性校验
+// 导出时边写边计算sha256，还原前重新计算并比对，同时检查导出文件末尾的结束标记
+// （mysqldump 为 "Dump completed"，pg_dump 为 "PostgreSQL database dump complete"）
+
+use std::{
+    fs::{self, File},
+    io::{self, Read, Write},
+    path::Path,
+    sync::{Arc, Mutex},
+};
+
+use sha2::{Digest, Sha256};
+
+use crate::storage::backend::BackupStorage;
+
+// mysqldump 正常结束时最后一行为 "-- Dump completed on ..."
+pub const MYSQLDUMP_COMPLETED: &[u8] = b"-- Dump completed";
+// pg_dump（plain格式）正常结束时的最后一行
+pub const PG_DUMP_COMPLETED: &[u8] = b"-- PostgreSQL database dump complete";
+const TAIL_LEN: usize = 256;
+
+// 写入时同时统计大小和计算sha256
+pub struct HashingWriter<W: Write> {
+    inner: W,
+    hasher: Sha256,
+    size: u64,
+}
+
+impl<W: Write> HashingWriter<W> {
+    pub fn new(inner: W) -> Self {
+        HashingWriter {
+            inner,
+            hasher: Sha256::new(),
+            size: 0,
+        }
+    }
+
+    // 返回 (内部writer, 大小, sha256)
+    pub fn into_parts(self) -> (W, u64, String) {
+        (self.inner, self.size, hex::encode(self.hasher.finalize()))
+    }
+}
+
+impl<W: Write> Write for HashingWriter<W> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let n = self.inner.write(buf)?;
+        self.hasher.update(&buf[..n]);
+        self.size += n as u64;
+        Ok(n)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.inner.flush()
+    }
+}
+
+// 读取时计算sha256，读取器交给解密/解压后仍可通过 digest 取得结果
+pub struct HashingReader<R: Read> {
+    inner: R,
+    state: Arc<Mutex<(Sha256, u64)>>,
+}
+
+#[derive(Clone)]
+pub struct DigestHandle(Arc<Mutex<(Sha256, u64)>>);
+
+impl DigestHandle {
+    // 返回 (已读取大小, sha256)
+    pub fn digest(&self) -> (u64, String) {
+        let state = self.0.lock().unwrap();
+        (state.1, hex::encode(state.0.clone().finalize()))
+    }
+}
+
+impl<R: Read> HashingReader<R> {
+    pub fn new(inner: R) -> (Self, DigestHandle) {
+        let state = Arc::new(Mutex::new((Sha256::new(), 0)));
+        (
+            HashingReader {
+                inner,
+                state: state.clone(),
+            },
+            DigestHandle(state),
+        )
+    }
+}
+
+impl<R: Read> Read for HashingReader<R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let n = self.inner.read(buf)?;
+        let mut state = self.state.lock().unwrap();
+        state.0.update(&buf[..n]);
+        state.1 += n as u64;
+        Ok(n)
+    }
+}
+
+// 保留最后 TAIL_LEN 个字节，用于检查结束标记
+pub struct TailWriter<W: Write> {
+    inner: W,
+    tail: Vec<u8>,
+}
+
+impl<W: Write> TailWriter<W> {
+    pub fn new(inner: W) -> Self {
+        TailWriter {
+            inner,
+            tail: Vec::with_capacity(TAIL_LEN * 2),
+        }
+    }
+
+    pub fn dump_completed(&self, marker: &[u8]) -> bool {
+        dump_completed(&self.tail, marker)
+    }
+}
+
+impl<W: Write> Write for TailWriter<W> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let n = self.inner.write(buf)?;
+        push_tail(&mut self.tail, &buf[..n]);
+        Ok(n)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.inner.flush()
+    }
+}
+
+fn push_tail(tail: &mut Vec<u8>, data: &[u8]) {
+    if data.len() >= TAIL_LEN {
+        tail.clear();
+        tail.extend_from_slice(&data[data.len() - TAIL_LEN..]);
+    } else {
+        tail.extend_from_slice(data);
+        if tail.len() > TAIL_LEN {
+            tail.drain(..tail.len() - TAIL_LEN);
+        }
+    }
+}
+
+// 末尾是否包含结束标记
+pub fn dump_completed(tail: &[u8], marker: &[u8]) -> bool {
+    tail.windows(marker.len()).any(|w| w == marker)
+}
+
+// 读完整个（解密、解压后的）数据流，检查末尾标记
+pub fn check_dump_completed<R: Read>(mut reader: R, marker: &[u8]) -> io::Result<bool> {
+    let mut tail = Vec::with_capacity(TAIL_LEN * 2);
+    let mut buf = vec![0u8; 64 * 1024];
+    loop {
+        let n = reader.read(&mut buf)?;
+        if n == 0 {
+            break;
+        }
+        push_tail(&mut tail, &buf[..n]);
+    }
+    Ok(dump_completed(&tail, marker))
+}
+
+// sha256 旁路文件路径：{file}.sha256
+pub fn sidecar_path(file_path: &str) -> String {
+    format!("{}.sha256", file_path)
+}
+
+// 旁路文件内容，格式与 sha256sum 相同
+fn sidecar_line(file_path: &str, sha256: &str) -> String {
+    let file_name = Path::new(file_path)
+        .file_name()
+        .map(|n| n.to_string_lossy().to_string())
+        .unwrap_or_default();
+    format!("{}  {}\n", sha256, file_name)
+}
+
+// 写入旁路文件
+pub fn write_sidecar(file_path: &str, sha256: &str) -> io::Result<()> {
+    fs::write(sidecar_path(file_path), sidecar_line(file_path, sha256))
+}
+
+// 写入存储中的旁路文件
+pub fn write_stored_sidecar(
+    storage: &dyn BackupStorage,
+    file_path: &str,
+    sha256: &str,
+) -> io::Result<()> {
+    storage.write_all(
+        &sidecar_path(file_path),
+        sidecar_line(file_path, sha256).as_bytes(),
+    )
+}
+
+// 校验本地备份文件：sha256与旁路文件一致，且以结束标记结尾
+pub fn verify_file(file_path: &str, marker: &[u8]) -> io::Result<()> {
+    let sidecar = fs::read_to_string(sidecar_path(file_path)).map_err(|e| {
+        io::Error::other(format!(
+            "读取校验文件 {} 失败: {}",
+            sidecar_path(file_path),
+            e
+        ))
+    })?;
+    verify_reader(
+        file_path,
+        &sidecar,
+        Box::new(File::open(file_path)?),
+        marker,
+        Ok,
+    )
+}
+
+// 校验存储中的备份文件，sha256 为存储中的内容，结束标记在 decode（解密）之后检查
+pub fn verify_stored(
+    storage: &dyn BackupStorage,
+    file_path: &str,
+    marker: &[u8],
+    decode: impl FnOnce(Box<dyn Read + Send>) -> io::Result<Box<dyn Read + Send>>,
+) -> io::Result<()> {
+    let sidecar = storage
+        .read_to_string(&sidecar_path(file_path))
+        .map_err(|e| {
+            io::Error::other(format!(
+                "读取校验文件 {} 失败: {}",
+                storage.location(&sidecar_path(file_path)),
+                e
+            ))
+        })?;
+    verify_reader(
+        &storage.location(file_path),
+        &sidecar,
+        storage.open(file_path)?,
+        marker,
+        decode,
+    )
+}
+
+fn verify_reader(
+    label: &str,
+    sidecar: &str,
+    reader: Box<dyn Read + Send>,
+    marker: &[u8],
+    decode: impl FnOnce(Box<dyn Read + Send>) -> io::Result<Box<dyn Read + Send>>,
+) -> io::Result<()> {
+    let expected = sidecar.split_whitespace().next().unwrap_or_default();
+    let (reader, digest) = HashingReader::new(reader);
+    let completed = check_dump_completed(decode(Box::new(reader))?, marker)?;
+    let (_, actual) = digest.digest();
+    if actual != expected {
+        return Err(io::Error::other(format!(
+            "备份文件 {} 校验失败: sha256 {} 与记录的 {} 不一致",
+            label, actual, expected
+        )));
+    }
+    if !completed {
+        return Err(io::Error::other(format!(
+            "备份文件 {} 不完整：缺少结束标记 \"{}\"",
+            label,
+            String::from_utf8_lossy(marker)
+        )));
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod test_checksum {
+    use super::*;
+
+    #[test]
+    fn test_verify_file() {
+        let dir = std::env::temp_dir().join("datasync_test_checksum");
+        fs::create_dir_all(&dir).unwrap();
+        let file_path = dir.join("backup_test.sql").to_string_lossy().to_string();
+
+        let content = b"CREATE TABLE t (id int);\n-- Dump completed on 2025-01-01  2:00:00\n";
+        let mut writer = HashingWriter::new(File::create(&file_path).unwrap());
+        writer.write_all(content).unwrap();
+        let (_, size, sha256) = writer.into_parts();
+        assert_eq!(size, content.len() as u64);
+        write_sidecar(&file_path, &sha256).unwrap();
+        verify_file(&file_path, MYSQLDUMP_COMPLETED).unwrap();
+
+        // 截断的备份文件
+        fs::write(&file_path, &content[..30]).unwrap();
+        assert!(verify_file(&file_path, MYSQLDUMP_COMPLETED).is_err());
+        // 没有结束标记，即使sha256一致也不能还原
+        let mut writer = HashingWriter::new(File::create(&file_path).unwrap());
+        writer.write_all(&content[..30]).unwrap();
+        let (_, _, sha256) = writer.into_parts();
+        write_sidecar(&file_path, &sha256).unwrap();
+        assert!(verify_file(&file_path, MYSQLDUMP_COMPLETED).is_err());
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    // 加密的导出文件：sha256 为密文，结束标记在解密后检查，密钥不对时校验失败
+    #[test]
+    fn test_verify_stored_encrypted() {
+        use crate::{
+            storage::local::LocalStorage,
+            util::crypto::{DecryptReader, EncryptWriter},
+        };
+
+        let dir = std::env::temp_dir().join("datasync_test_checksum_stored");
+        let _ = fs::remove_dir_all(&dir);
+        let storage = LocalStorage::new(&dir);
+        let key = [7u8; 32];
+        let content = b"CREATE TABLE t (id int);\n-- Dump completed on 2025-01-01  2:00:00\n";
+
+        let writer = HashingWriter::new(storage.create("sql/backup_test.sql.enc").unwrap());
+        let mut writer = EncryptWriter::new(writer, &key).unwrap();
+        writer.write_all(content).unwrap();
+        let (writer, _, sha256) = writer.finish().unwrap().into_parts();
+        writer.finish().unwrap();
+        write_stored_sidecar(&storage, "sql/backup_test.sql.enc", &sha256).unwrap();
+
+        let decrypt = |key: [u8; 32]| {
+            move |r: Box<dyn Read + Send>| -> io::Result<Box<dyn Read + Send>> {
+                Ok(Box::new(DecryptReader::new(r, &key)?))
+            }
+        };
+        verify_stored(
+            &storage,
+            "sql/backup_test.sql.enc",
+            MYSQLDUMP_COMPLETED,
+            decrypt(key),
+        )
+        .unwrap();
+        // 不解密时找不到结束标记
+        assert!(
+            verify_stored(&storage, "sql/backup_test.sql.enc", MYSQLDUMP_COMPLETED, Ok).is_err()
+        );
+        assert!(
+            verify_stored(
+                &storage,
+                "sql/backup_test.sql.enc",
+                MYSQLDUMP_COMPLETED,
+                decrypt([8u8; 32]),
+            )
+            .is_err()
+        );
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
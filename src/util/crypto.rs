@@ -0,0 +1,254 @@
+// 备份文件加密（AES-256-GCM，分块流式加密）
+//
+// 文件格式：
+//   头部：MAGIC(8字节) + 随机nonce前缀(7字节)
+//   数据块：密文长度(u32大端) + 密文（含16字节认证标签）
+// 每块nonce = 前缀(7) + 块序号(u32大端) + 是否最后一块(1)，
+// 块被调换顺序、删除或文件被截断都会导致解密失败
+
+use std::{
+    fs,
+    io::{self, Read, Write},
+};
+
+use aes_gcm::{
+    Aes256Gcm, Key, KeyInit, Nonce,
+    aead::{Aead, OsRng, rand_core::RngCore},
+};
+use sha2::{Digest, Sha256};
+
+const MAGIC: &[u8; 8] = b"DSENC1\0\0";
+const NONCE_PREFIX_LEN: usize = 7;
+const CHUNK_SIZE: usize = 64 * 1024;
+const TAG_LEN: usize = 16;
+
+pub const AES_256_GCM: &str = "aes-256-gcm";
+
+// 读取密钥文件：32字节原始密钥或64个十六进制字符
+pub fn load_key(key_file: &str) -> io::Result<[u8; 32]> {
+    let content = fs::read(key_file)?;
+    let text = String::from_utf8_lossy(&content);
+    let bytes = match hex::decode(text.trim()) {
+        Ok(bytes) => bytes,
+        Err(_) => content,
+    };
+    bytes
+        .try_into()
+        .map_err(|_| io::Error::other(format!("密钥文件 {} 必须是32字节或64位十六进制", key_file)))
+}
+
+// 密钥标识（密钥sha256的前16位），记录到备份清单中用于还原时检查密钥是否匹配
+pub fn key_id(key: &[u8; 32]) -> String {
+    hex::encode(Sha256::digest(key))[..16].to_string()
+}
+
+// 配置的加密密钥，Debug 输出时只显示密钥标识
+#[derive(Clone)]
+pub struct EncryptionKey(pub [u8; 32]);
+
+impl std::fmt::Debug for EncryptionKey {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "EncryptionKey({})", key_id(&self.0))
+    }
+}
+
+fn chunk_nonce(prefix: &[u8; NONCE_PREFIX_LEN], counter: u32, last: bool) -> [u8; 12] {
+    let mut nonce = [0u8; 12];
+    nonce[..NONCE_PREFIX_LEN].copy_from_slice(prefix);
+    nonce[NONCE_PREFIX_LEN..11].copy_from_slice(&counter.to_be_bytes());
+    nonce[11] = last as u8;
+    nonce
+}
+
+// 加密写入器，必须调用 finish 写入最后一块
+pub struct EncryptWriter<W: Write> {
+    inner: W,
+    cipher: Aes256Gcm,
+    prefix: [u8; NONCE_PREFIX_LEN],
+    counter: u32,
+    buffer: Vec<u8>,
+}
+
+impl<W: Write> EncryptWriter<W> {
+    pub fn new(mut inner: W, key: &[u8; 32]) -> io::Result<Self> {
+        let mut prefix = [0u8; NONCE_PREFIX_LEN];
+        OsRng.fill_bytes(&mut prefix);
+        inner.write_all(MAGIC)?;
+        inner.write_all(&prefix)?;
+        Ok(EncryptWriter {
+            inner,
+            cipher: Aes256Gcm::new(Key::<Aes256Gcm>::from_slice(key)),
+            prefix,
+            counter: 0,
+            buffer: Vec::with_capacity(CHUNK_SIZE),
+        })
+    }
+
+    fn write_chunk(&mut self, last: bool) -> io::Result<()> {
+        let nonce = chunk_nonce(&self.prefix, self.counter, last);
+        let ciphertext = self
+            .cipher
+            .encrypt(Nonce::from_slice(&nonce), self.buffer.as_slice())
+            .map_err(|_| io::Error::other("加密失败"))?;
+        self.inner
+            .write_all(&(ciphertext.len() as u32).to_be_bytes())?;
+        self.inner.write_all(&ciphertext)?;
+        self.buffer.clear();
+        self.counter = self
+            .counter
+            .checked_add(1)
+            .ok_or_else(|| io::Error::other("加密数据块过多"))?;
+        Ok(())
+    }
+
+    // 写入最后一块，返回内部writer
+    pub fn finish(mut self) -> io::Result<W> {
+        self.write_chunk(true)?;
+        self.inner.flush()?;
+        Ok(self.inner)
+    }
+}
+
+impl<W: Write> Write for EncryptWriter<W> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        // 缓冲区满且还有数据时，当前块肯定不是最后一块
+        if self.buffer.len() == CHUNK_SIZE {
+            self.write_chunk(false)?;
+        }
+        let n = buf.len().min(CHUNK_SIZE - self.buffer.len());
+        self.buffer.extend_from_slice(&buf[..n]);
+        Ok(n)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.inner.flush()
+    }
+}
+
+// 解密读取器
+pub struct DecryptReader<R: Read> {
+    inner: R,
+    cipher: Aes256Gcm,
+    prefix: [u8; NONCE_PREFIX_LEN],
+    counter: u32,
+    next_len: Option<u32>, // 预读的下一块长度，None表示已经结束
+    plaintext: Vec<u8>,
+    pos: usize,
+}
+
+impl<R: Read> DecryptReader<R> {
+    pub fn new(mut inner: R, key: &[u8; 32]) -> io::Result<Self> {
+        let mut magic = [0u8; 8];
+        inner.read_exact(&mut magic)?;
+        if &magic != MAGIC {
+            return Err(io::Error::other("不是datasync加密的备份文件"));
+        }
+        let mut prefix = [0u8; NONCE_PREFIX_LEN];
+        inner.read_exact(&mut prefix)?;
+        let next_len = read_len(&mut inner)?;
+        if next_len.is_none() {
+            return Err(io::Error::other("加密备份文件不完整"));
+        }
+        Ok(DecryptReader {
+            inner,
+            cipher: Aes256Gcm::new(Key::<Aes256Gcm>::from_slice(key)),
+            prefix,
+            counter: 0,
+            next_len,
+            plaintext: Vec::new(),
+            pos: 0,
+        })
+    }
+
+    // 解密下一块，没有更多数据时返回false
+    fn read_chunk(&mut self) -> io::Result<bool> {
+        let Some(len) = self.next_len else {
+            return Ok(false);
+        };
+        if len as usize > CHUNK_SIZE + TAG_LEN {
+            return Err(io::Error::other("加密数据块长度错误"));
+        }
+        let mut ciphertext = vec![0u8; len as usize];
+        self.inner.read_exact(&mut ciphertext)?;
+        // 后面没有数据了，当前块必须是最后一块
+        self.next_len = read_len(&mut self.inner)?;
+        let last = self.next_len.is_none();
+        let nonce = chunk_nonce(&self.prefix, self.counter, last);
+        self.plaintext = self
+            .cipher
+            .decrypt(Nonce::from_slice(&nonce), ciphertext.as_slice())
+            .map_err(|_| io::Error::other("解密失败：密钥错误或备份文件已损坏/被截断"))?;
+        self.pos = 0;
+        self.counter += 1;
+        Ok(true)
+    }
+}
+
+impl<R: Read> Read for DecryptReader<R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        while self.pos == self.plaintext.len() {
+            if !self.read_chunk()? {
+                return Ok(0);
+            }
+        }
+        let n = buf.len().min(self.plaintext.len() - self.pos);
+        buf[..n].copy_from_slice(&self.plaintext[self.pos..self.pos + n]);
+        self.pos += n;
+        Ok(n)
+    }
+}
+
+// 读取块长度，正好在文件末尾时返回None
+fn read_len<R: Read>(reader: &mut R) -> io::Result<Option<u32>> {
+    let mut buf = [0u8; 4];
+    let mut read = 0;
+    while read < buf.len() {
+        match reader.read(&mut buf[read..]) {
+            Ok(0) if read == 0 => return Ok(None),
+            Ok(0) => return Err(io::Error::other("加密备份文件不完整")),
+            Ok(n) => read += n,
+            Err(e) if e.kind() == io::ErrorKind::Interrupted => {}
+            Err(e) => return Err(e),
+        }
+    }
+    Ok(Some(u32::from_be_bytes(buf)))
+}
+
+#[cfg(test)]
+mod test_crypto {
+    use super::*;
+
+    fn encrypt(data: &[u8], key: &[u8; 32]) -> Vec<u8> {
+        let mut writer = EncryptWriter::new(Vec::new(), key).unwrap();
+        writer.write_all(data).unwrap();
+        writer.finish().unwrap()
+    }
+
+    fn decrypt(data: &[u8], key: &[u8; 32]) -> io::Result<Vec<u8>> {
+        let mut plaintext = Vec::new();
+        DecryptReader::new(data, key)?.read_to_end(&mut plaintext)?;
+        Ok(plaintext)
+    }
+
+    #[test]
+    fn test_encrypt_decrypt() {
+        let key = [7u8; 32];
+        for size in [0, 10, CHUNK_SIZE, CHUNK_SIZE * 3 + 5] {
+            let data: Vec<u8> = (0..size).map(|i| (i % 251) as u8).collect();
+            let encrypted = encrypt(&data, &key);
+            assert_eq!(decrypt(&encrypted, &key).unwrap(), data);
+        }
+    }
+
+    #[test]
+    fn test_reject_wrong_key_and_truncation() {
+        let key = [7u8; 32];
+        let data = vec![1u8; CHUNK_SIZE * 2 + 100];
+        let encrypted = encrypt(&data, &key);
+        assert!(decrypt(&encrypted, &[8u8; 32]).is_err());
+        // 截掉最后一块
+        let last_chunk_len = 4 + 100 + TAG_LEN;
+        let truncated = &encrypted[..encrypted.len() - last_chunk_len];
+        assert!(decrypt(truncated, &key).is_err());
+    }
+}
@@ -0,0 +1,297 @@
+// Prometheus 指标：配置 [job] metrics_addr 时在本机地址上提供 HTTP /metrics
+// 同步的库数、字节数、行数、用时来自进度登记（util::progress），连接池使用情况来自 MYSQL_DB_POOLS
+
+use std::{
+    fmt::Write as _,
+    net::SocketAddr,
+    sync::{Arc, atomic::Ordering},
+};
+
+use tokio::{
+    io::{AsyncReadExt, AsyncWriteExt},
+    net::{TcpListener, TcpStream},
+    task::JoinHandle,
+};
+
+use crate::{db::mysql_db::MYSQL_DB_POOLS, util::progress};
+
+// 请求头最大长度，只需要读取请求行
+const MAX_REQUEST: usize = 8192;
+
+// 从一个库的进度中读取计数器的值
+type Counter = fn(&progress::DbProgress) -> u64;
+
+// 读取各库进度的来源，运行时为 progress::snapshot
+pub type Registry = fn() -> Vec<Arc<progress::DbProgress>>;
+
+// 绑定 addr（只允许本机地址，例如 127.0.0.1:9187）并在后台处理请求，任务结束时随进程退出
+pub async fn serve(addr: &str, registry: Registry) -> Result<JoinHandle<()>, String> {
+    // 绑定前检查，非本机地址不会被监听
+    let addr = loopback_addr(addr)?;
+    let listener = TcpListener::bind(addr)
+        .await
+        .map_err(|e| format!("metrics 绑定 {} 失败: {}", addr, e))?;
+    let local = listener
+        .local_addr()
+        .map_err(|e| format!("metrics 绑定 {} 失败: {}", addr, e))?;
+    println!("[metrics] http://{}/metrics", local);
+    Ok(tokio::spawn(async move {
+        loop {
+            match listener.accept().await {
+                Ok((stream, _)) => {
+                    tokio::spawn(async move {
+                        if let Err(e) = handle(stream, registry).await {
+                            eprintln!("[metrics] 请求处理失败: {}", e);
+                        }
+                    });
+                }
+                Err(e) => eprintln!("[metrics] 接受连接失败: {}", e),
+            }
+        }
+    }))
+}
+
+// metrics_addr 为 IP:端口，只能是本机地址
+fn loopback_addr(addr: &str) -> Result<SocketAddr, String> {
+    let socket_addr = addr
+        .parse::<SocketAddr>()
+        .map_err(|e| format!("metrics_addr {} 不是 IP:端口 格式: {}", addr, e))?;
+    if !socket_addr.ip().is_loopback() {
+        return Err(format!(
+            "metrics_addr 只能绑定本机地址（127.0.0.1 或 ::1），当前为 {}",
+            socket_addr
+        ));
+    }
+    Ok(socket_addr)
+}
+
+async fn handle(mut stream: TcpStream, registry: Registry) -> std::io::Result<()> {
+    let mut buf = Vec::new();
+    let mut chunk = [0u8; 1024];
+    while !buf.windows(4).any(|w| w == b"\r\n\r\n") && buf.len() < MAX_REQUEST {
+        let n = stream.read(&mut chunk).await?;
+        if n == 0 {
+            break;
+        }
+        buf.extend_from_slice(&chunk[..n]);
+    }
+    let request = String::from_utf8_lossy(&buf);
+    let mut parts = request.lines().next().unwrap_or("").split_whitespace();
+    let (status, content_type, body) = match (parts.next(), parts.next()) {
+        (Some("GET"), Some("/metrics")) => (
+            "200 OK",
+            "text/plain; version=0.0.4; charset=utf-8",
+            render(&registry()).await,
+        ),
+        (Some("GET"), _) => ("404 Not Found", "text/plain", "not found\n".to_string()),
+        _ => (
+            "405 Method Not Allowed",
+            "text/plain",
+            "method not allowed\n".to_string(),
+        ),
+    };
+    let response = format!(
+        "HTTP/1.1 {}\r\nContent-Type: {}\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+        status,
+        content_type,
+        body.len(),
+        body
+    );
+    stream.write_all(response.as_bytes()).await?;
+    stream.shutdown().await
+}
+
+// 标签值中的反斜杠、双引号和换行需要转义
+fn label(value: &str) -> String {
+    value
+        .replace('\\', "\\\\")
+        .replace('"', "\\\"")
+        .replace('\n', "\\n")
+}
+
+fn header(out: &mut String, name: &str, kind: &str, help: &str) {
+    let _ = writeln!(out, "# HELP {} {}", name, help);
+    let _ = writeln!(out, "# TYPE {} {}", name, kind);
+}
+
+// Prometheus 文本格式
+async fn render(all: &[Arc<progress::DbProgress>]) -> String {
+    let mut out = String::new();
+
+    let started = all.iter().filter(|p| p.elapsed().is_some()).count();
+    let succeeded = all.iter().filter(|p| p.result() == Some(true)).count();
+    let failed = all.iter().filter(|p| p.result() == Some(false)).count();
+    for (name, help, value) in [
+        ("datasync_syncs_started_total", "开始同步的库数", started),
+        (
+            "datasync_syncs_succeeded_total",
+            "同步成功的库数",
+            succeeded,
+        ),
+        ("datasync_syncs_failed_total", "同步失败的库数", failed),
+    ] {
+        header(&mut out, name, "counter", help);
+        let _ = writeln!(out, "{} {}", name, value);
+    }
+
+    let counters: [(&str, &str, Counter); 4] = [
+        ("datasync_dumped_bytes_total", "导出的字节数", |p| {
+            p.dumped_bytes.load(Ordering::Relaxed)
+        }),
+        ("datasync_restored_bytes_total", "还原的字节数", |p| {
+            p.restored_bytes.load(Ordering::Relaxed)
+        }),
+        ("datasync_rows_total", "库内并行导入的行数", |p| {
+            p.rows.load(Ordering::Relaxed)
+        }),
+        ("datasync_tables_done_total", "完成的表数", |p| {
+            p.tables_done.load(Ordering::Relaxed)
+        }),
+    ];
+    for (name, help, value) in counters {
+        header(&mut out, name, "counter", help);
+        for p in all {
+            let _ = writeln!(
+                out,
+                "{}{{database=\"{}\"}} {}",
+                name,
+                label(&p.db_name),
+                value(p)
+            );
+        }
+    }
+
+    header(
+        &mut out,
+        "datasync_database_estimated_bytes",
+        "gauge",
+        "源库 information_schema.TABLES 中的估计数据大小",
+    );
+    for p in all {
+        let _ = writeln!(
+            out,
+            "datasync_database_estimated_bytes{{database=\"{}\"}} {}",
+            label(&p.db_name),
+            p.estimate.bytes
+        );
+    }
+    header(
+        &mut out,
+        "datasync_database_progress_ratio",
+        "gauge",
+        "库的完成比例（0 到 1）",
+    );
+    for p in all {
+        let _ = writeln!(
+            out,
+            "datasync_database_progress_ratio{{database=\"{}\"}} {:.4}",
+            label(&p.db_name),
+            p.fraction()
+        );
+    }
+    header(
+        &mut out,
+        "datasync_database_duration_seconds",
+        "gauge",
+        "库的同步用时，同步中为已用时间",
+    );
+    for p in all {
+        let Some(elapsed) = p.elapsed() else {
+            continue;
+        };
+        let status = match p.result() {
+            Some(true) => "succeeded",
+            Some(false) => "failed",
+            None => "running",
+        };
+        let _ = writeln!(
+            out,
+            "datasync_database_duration_seconds{{database=\"{}\",status=\"{}\"}} {:.3}",
+            label(&p.db_name),
+            status,
+            elapsed.as_secs_f64()
+        );
+    }
+
+    let pools = MYSQL_DB_POOLS.lock().await;
+    let mut names = pools.keys().collect::<Vec<_>>();
+    names.sort();
+    header(
+        &mut out,
+        "datasync_pool_connections",
+        "gauge",
+        "MySQL 连接池的连接数，state 为 active（使用中）或 idle（空闲）",
+    );
+    for name in &names {
+        let pool = &pools[*name];
+        let idle = pool.num_idle() as u64;
+        let active = (pool.size() as u64).saturating_sub(idle);
+        for (state, value) in [("active", active), ("idle", idle)] {
+            let _ = writeln!(
+                out,
+                "datasync_pool_connections{{pool=\"{}\",state=\"{}\"}} {}",
+                label(name),
+                state,
+                value
+            );
+        }
+    }
+    header(
+        &mut out,
+        "datasync_pool_max_connections",
+        "gauge",
+        "MySQL 连接池的最大连接数",
+    );
+    for name in &names {
+        let _ = writeln!(
+            out,
+            "datasync_pool_max_connections{{pool=\"{}\"}} {}",
+            label(name),
+            pools[*name].options().get_max_connections()
+        );
+    }
+    out
+}
+
+#[cfg(test)]
+mod test_metrics {
+    use super::*;
+
+    #[test]
+    fn test_loopback_addr() {
+        assert_eq!(
+            loopback_addr("127.0.0.1:9187").unwrap().to_string(),
+            "127.0.0.1:9187"
+        );
+        assert!(loopback_addr("[::1]:9187").is_ok());
+        assert!(loopback_addr("0.0.0.0:9187").is_err());
+        assert!(loopback_addr("192.168.1.10:9187").is_err());
+        assert!(loopback_addr("localhost:9187").is_err());
+    }
+
+    #[tokio::test]
+    async fn test_metrics() {
+        assert_eq!(label("a\"b\\c\nd"), "a\\\"b\\\\c\\nd");
+        assert!(serve("0.0.0.0:0", progress::snapshot).await.is_err());
+
+        // 不使用全局进度登记，与其它测试互不影响
+        let p = Arc::new(progress::DbProgress::new(
+            "metrics_db".to_string(),
+            progress::Estimate {
+                tables: 1,
+                rows: 10,
+                bytes: 100,
+            },
+        ));
+        p.start();
+        p.dumped_bytes.store(42, Ordering::Relaxed);
+        p.finish(true);
+        let body = render(&[p]).await;
+        assert!(body.contains("datasync_syncs_succeeded_total 1"));
+        assert!(body.contains("# TYPE datasync_syncs_succeeded_total counter"));
+        assert!(body.contains("datasync_dumped_bytes_total{database=\"metrics_db\"} 42"));
+        assert!(body.contains(
+            "datasync_database_duration_seconds{database=\"metrics_db\",status=\"succeeded\"}"
+        ));
+    }
+}
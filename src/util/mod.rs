@@ -1 +1,5 @@
+pub mod checksum;
 pub mod common;
+pub mod crypto;
+pub mod metrics;
+pub mod progress;
@@ -0,0 +1,363 @@
+// 同步进度：每个库导出/还原的字节数、导入的行数和完成的表数，按 information_schema.TABLES 的估计值计算进度和剩余时间
+// 终端中每秒刷新一行进度，不是终端时（例如输出到日志文件）定期输出进度日志
+
+use std::{
+    io::{self, IsTerminal, Read, Write},
+    sync::{
+        Arc, LazyLock, Mutex,
+        atomic::{AtomicU64, Ordering},
+    },
+    time::{Duration, Instant},
+};
+
+use tokio::task::JoinHandle;
+
+// 不是终端时输出进度日志的间隔
+const LOG_INTERVAL: Duration = Duration::from_secs(30);
+
+// mysqldump 输出中每个表的数据以这一行开始
+const TABLE_MARKER: &[u8] = b"\n-- Dumping data for table ";
+
+// 同步开始前的估计值（源库 information_schema.TABLES）
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Estimate {
+    pub tables: u64,
+    pub rows: u64,
+    pub bytes: u64, // DATA_LENGTH，与 mysqldump 输出的大小接近
+}
+
+#[derive(Debug, Default)]
+pub struct DbProgress {
+    pub db_name: String,
+    pub estimate: Estimate,
+    targets: Mutex<Vec<String>>, // 写入的目标库（库名不同、swap 的 staging 库），还原时按目标库名查找
+    pub dumped_bytes: AtomicU64,
+    pub restored_bytes: AtomicU64,
+    pub rows: AtomicU64,
+    pub tables_done: AtomicU64,
+    started: Mutex<Option<Instant>>,
+    finished: Mutex<Option<(Duration, bool)>>, // (用时, 是否成功)
+}
+
+impl DbProgress {
+    pub fn new(db_name: String, estimate: Estimate) -> Self {
+        DbProgress {
+            db_name,
+            estimate,
+            ..Default::default()
+        }
+    }
+
+    pub fn start(&self) {
+        self.started
+            .lock()
+            .unwrap()
+            .get_or_insert_with(Instant::now);
+    }
+
+    pub fn add_target(&self, target_db_name: &str) {
+        let mut targets = self.targets.lock().unwrap();
+        if !targets.iter().any(|t| t == target_db_name) {
+            targets.push(target_db_name.to_string());
+        }
+    }
+
+    pub fn finish(&self, ok: bool) {
+        let elapsed = self.elapsed().unwrap_or_default();
+        *self.finished.lock().unwrap() = Some((elapsed, ok));
+    }
+
+    pub fn elapsed(&self) -> Option<Duration> {
+        if let Some((elapsed, _)) = *self.finished.lock().unwrap() {
+            return Some(elapsed);
+        }
+        self.started.lock().unwrap().map(|s| s.elapsed())
+    }
+
+    // None：未开始；Some(true/false)：已结束
+    pub fn result(&self) -> Option<bool> {
+        self.finished.lock().unwrap().map(|(_, ok)| ok)
+    }
+
+    // 完成比例：库内并行时按行数，mysqldump 方式按导出和还原的字节数（各占一半），结束前不超过 99%
+    pub fn fraction(&self) -> f64 {
+        if self.result().is_some() {
+            return 1.0;
+        }
+        let rows = self.rows.load(Ordering::Relaxed);
+        let fraction = if rows > 0 {
+            rows as f64 / self.estimate.rows.max(rows) as f64
+        } else {
+            let dumped = self.dumped_bytes.load(Ordering::Relaxed);
+            let restored = self.restored_bytes.load(Ordering::Relaxed);
+            let total = self.estimate.bytes.max(dumped).max(restored);
+            if total == 0 {
+                0.0
+            } else {
+                (dumped + restored) as f64 / (2 * total) as f64
+            }
+        };
+        fraction.min(0.99)
+    }
+}
+
+// 本次运行中所有库的进度，/metrics 等也从这里读取
+static PROGRESS: LazyLock<Mutex<Vec<Arc<DbProgress>>>> = LazyLock::new(|| Mutex::new(Vec::new()));
+static RUN_STARTED: LazyLock<Instant> = LazyLock::new(Instant::now);
+
+// 登记将要同步的库和估计值
+pub fn plan(databases: Vec<(String, Estimate)>) {
+    LazyLock::force(&RUN_STARTED);
+    let mut progress = PROGRESS.lock().unwrap();
+    for (db_name, estimate) in databases {
+        if !progress.iter().any(|p| p.db_name == db_name) {
+            progress.push(Arc::new(DbProgress::new(db_name, estimate)));
+        }
+    }
+}
+
+// 按源库名或目标库名查找，没有登记时为 None（不统计进度）
+pub fn find(db_name: &str) -> Option<Arc<DbProgress>> {
+    PROGRESS
+        .lock()
+        .unwrap()
+        .iter()
+        .find(|p| p.db_name == db_name || p.targets.lock().unwrap().iter().any(|t| t == db_name))
+        .cloned()
+}
+
+pub fn snapshot() -> Vec<Arc<DbProgress>> {
+    PROGRESS.lock().unwrap().clone()
+}
+
+// 写入时统计导出的字节数
+pub struct ProgressWriter<W: Write> {
+    inner: W,
+    progress: Option<Arc<DbProgress>>,
+}
+
+impl<W: Write> ProgressWriter<W> {
+    pub fn new(inner: W, progress: Option<Arc<DbProgress>>) -> Self {
+        ProgressWriter { inner, progress }
+    }
+
+    pub fn into_inner(self) -> W {
+        self.inner
+    }
+}
+
+impl<W: Write> Write for ProgressWriter<W> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let n = self.inner.write(buf)?;
+        if let Some(progress) = &self.progress {
+            progress.dumped_bytes.fetch_add(n as u64, Ordering::Relaxed);
+        }
+        Ok(n)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.inner.flush()
+    }
+}
+
+// 读取时统计还原的字节数和开始还原数据的表数
+pub struct ProgressReader<R: Read> {
+    inner: R,
+    progress: Option<Arc<DbProgress>>,
+    tail: Vec<u8>, // 上一次读取的末尾，标记可能跨越两次读取
+}
+
+impl<R: Read> ProgressReader<R> {
+    pub fn new(inner: R, progress: Option<Arc<DbProgress>>) -> Self {
+        ProgressReader {
+            inner,
+            progress,
+            tail: Vec::new(),
+        }
+    }
+}
+
+impl<R: Read> Read for ProgressReader<R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let n = self.inner.read(buf)?;
+        if let Some(progress) = &self.progress {
+            progress
+                .restored_bytes
+                .fetch_add(n as u64, Ordering::Relaxed);
+            let tables = count_markers(&mut self.tail, &buf[..n], TABLE_MARKER);
+            progress.tables_done.fetch_add(tables, Ordering::Relaxed);
+        }
+        Ok(n)
+    }
+}
+
+// buf 中（包括与上一次末尾连接处）marker 出现的次数
+fn count_markers(tail: &mut Vec<u8>, buf: &[u8], marker: &[u8]) -> u64 {
+    tail.extend_from_slice(buf);
+    let count = tail.windows(marker.len()).filter(|w| *w == marker).count() as u64;
+    let keep = tail.len().min(marker.len() - 1);
+    tail.drain(..tail.len() - keep);
+    count
+}
+
+fn format_bytes(bytes: u64) -> String {
+    const UNITS: [&str; 5] = ["B", "KB", "MB", "GB", "TB"];
+    let mut value = bytes as f64;
+    let mut unit = 0;
+    while value >= 1024.0 && unit < UNITS.len() - 1 {
+        value /= 1024.0;
+        unit += 1;
+    }
+    format!("{:.1}{}", value, UNITS[unit])
+}
+
+fn format_duration(duration: Duration) -> String {
+    let secs = duration.as_secs();
+    format!("{:02}:{:02}:{:02}", secs / 3600, secs / 60 % 60, secs % 60)
+}
+
+// 已用时间和完成比例推算剩余时间
+fn eta(elapsed: Duration, fraction: f64) -> String {
+    if fraction <= 0.0 {
+        return "--:--:--".to_string();
+    }
+    format_duration(elapsed.mul_f64((1.0 - fraction) / fraction))
+}
+
+// 一个库的进度
+fn describe(progress: &DbProgress) -> String {
+    let elapsed = progress.elapsed().unwrap_or_default();
+    let rows = progress.rows.load(Ordering::Relaxed);
+    let mut line = format!(
+        "{} {:.1}% 表 {}/{} 导出 {} 还原 {}",
+        progress.db_name,
+        progress.fraction() * 100.0,
+        progress
+            .tables_done
+            .load(Ordering::Relaxed)
+            .min(progress.estimate.tables),
+        progress.estimate.tables,
+        format_bytes(progress.dumped_bytes.load(Ordering::Relaxed)),
+        format_bytes(progress.restored_bytes.load(Ordering::Relaxed)),
+    );
+    if rows > 0 {
+        line.push_str(&format!(
+            " {} 行 {:.0} 行/秒",
+            rows,
+            rows as f64 / elapsed.as_secs_f64().max(1.0)
+        ));
+    }
+    line.push_str(&format!(" ETA {}", eta(elapsed, progress.fraction())));
+    line
+}
+
+// 所有库的进度，按估计大小加权
+fn describe_overall(all: &[Arc<DbProgress>]) -> String {
+    let weight = |p: &DbProgress| p.estimate.bytes.max(1) as f64;
+    let total = all.iter().map(|p| weight(p)).sum::<f64>();
+    let fraction = if total > 0.0 {
+        all.iter().map(|p| p.fraction() * weight(p)).sum::<f64>() / total
+    } else {
+        0.0
+    };
+    let done = all.iter().filter(|p| p.result().is_some()).count();
+    format!(
+        "总进度 {:.1}% 库 {}/{} 已用 {} ETA {}",
+        fraction * 100.0,
+        done,
+        all.len(),
+        format_duration(RUN_STARTED.elapsed()),
+        eta(RUN_STARTED.elapsed(), fraction)
+    )
+}
+
+// 启动进度输出：终端中每秒刷新一行（总进度和正在同步的库），否则每 30 秒输出一次进度日志
+pub fn spawn_reporter() -> JoinHandle<()> {
+    let interactive = io::stderr().is_terminal();
+    tokio::spawn(async move {
+        let mut interval = tokio::time::interval(if interactive {
+            Duration::from_secs(1)
+        } else {
+            LOG_INTERVAL
+        });
+        interval.tick().await;
+        loop {
+            interval.tick().await;
+            let all = snapshot();
+            let running = all
+                .iter()
+                .filter(|p| p.elapsed().is_some() && p.result().is_none())
+                .collect::<Vec<_>>();
+            if interactive {
+                let mut line = describe_overall(&all);
+                for progress in &running {
+                    line.push_str(" | ");
+                    line.push_str(&describe(progress));
+                }
+                let mut stderr = io::stderr().lock();
+                let _ = write!(stderr, "\r\x1b[2K{}", line);
+                let _ = stderr.flush();
+            } else {
+                for progress in &running {
+                    eprintln!("[progress] {}", describe(progress));
+                }
+                eprintln!("[progress] {}", describe_overall(&all));
+            }
+        }
+    })
+}
+
+// 停止进度输出，并输出每个库的结果和用时
+pub fn finish_reporter(reporter: JoinHandle<()>) {
+    reporter.abort();
+    if io::stderr().is_terminal() {
+        eprint!("\r\x1b[2K");
+    }
+    for progress in snapshot() {
+        let status = match progress.result() {
+            Some(true) => "ok",
+            Some(false) => "failed",
+            None => "skipped",
+        };
+        eprintln!(
+            "[progress] {} {} 用时 {}",
+            progress.db_name,
+            status,
+            format_duration(progress.elapsed().unwrap_or_default())
+        );
+    }
+}
+
+#[cfg(test)]
+mod test_progress {
+    use super::*;
+
+    #[test]
+    fn test_progress() {
+        let mut tail = Vec::new();
+        let dump = b"-- x\n-- Dumping data for table `a`\n\n-- Dumping data for table `b`\n";
+        let (first, second) = dump.split_at(20);
+        assert_eq!(count_markers(&mut tail, first, TABLE_MARKER), 0);
+        assert_eq!(count_markers(&mut tail, second, TABLE_MARKER), 2);
+
+        let progress = DbProgress {
+            db_name: "shop".to_string(),
+            estimate: Estimate {
+                tables: 2,
+                rows: 1000,
+                bytes: 1000,
+            },
+            ..Default::default()
+        };
+        progress.dumped_bytes.store(1000, Ordering::Relaxed);
+        progress.restored_bytes.store(500, Ordering::Relaxed);
+        assert!((progress.fraction() - 0.75).abs() < 1e-9);
+        progress.rows.store(250, Ordering::Relaxed);
+        assert!((progress.fraction() - 0.25).abs() < 1e-9);
+        progress.start();
+        progress.finish(true);
+        assert_eq!(progress.fraction(), 1.0);
+        assert_eq!(eta(Duration::from_secs(90), 0.5), "00:01:30");
+        assert_eq!(format_bytes(1536), "1.5KB");
+    }
+}